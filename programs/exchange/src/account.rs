@@ -59,6 +59,28 @@ pub struct Asset {
 #[zero_copy]
 #[derive(PartialEq, Default, Debug)]
 pub struct Collateral {
+    // 215
+    pub asset_index: u8,                        // 1
+    pub collateral_address: Pubkey,             // 32
+    pub reserve_address: Pubkey,                // 32
+    pub liquidation_fund: Pubkey,               // 32
+    pub reserve_balance: Decimal,               // 17
+    pub collateral_ratio: Decimal,              // 17
+    pub max_collateral: Decimal,                // 17
+    pub liquidation_mode: u8,                   // 1 LiquidationMode used in liquidation
+    pub liquidation_threshold: Decimal,         // 17 In % collateral value counted in liquidation
+    pub deprecated: bool,                       // 1 Deprecated collateral cannot be deposited
+    pub deprecation_start: u64,                 // 8 Slot since which collateral_ratio is lowered
+    pub deprecation_length: u32,                // 4 Slots until collateral_ratio reaches 0
+    pub initial_collateral_ratio: Decimal,      // 17 collateral_ratio before deprecation
+    pub initial_liquidation_threshold: Decimal, // 17 liquidation_threshold before deprecation
+    pub vault_count: u16,                       // 2 Vaults using collateral
+}
+// Layout of collateral in assets list before liquidation settings
+// Read only to migrate into current AssetsList
+#[zero_copy]
+#[derive(PartialEq, Default, Debug)]
+pub struct LegacyCollateral {
     // 148
     pub asset_index: u8,            // 1
     pub collateral_address: Pubkey, // 32
@@ -68,6 +90,17 @@ pub struct Collateral {
     pub collateral_ratio: Decimal,  // 17
     pub max_collateral: Decimal,    // 17
 }
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LiquidationMode {
+    Fixed = 0,   // fixed penalty_to_liquidator
+    Auction = 1, // discount growing linearly since liquidation_deadline
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum PriceSource {
+    Spot = 0,         // latest oracle price
+    Twap = 1,         // time weighted average price
+    Conservative = 2, // lower of spot and twap for collateral, higher for debt
+}
 #[zero_copy]
 #[derive(PartialEq, Default, Debug)]
 pub struct Synthetic {
@@ -105,7 +138,23 @@ pub struct State {
     pub staking: Staking,                   // 152
     pub exchange_authority: Pubkey,         // 32
     pub bump: u8,                           // 1
-    pub padding: [u8; 1620],                // 1620 (2048 - 428) reserved for future use
+    pub liquidation_auction_start_discount: Decimal, // 17 In % liquidator discount at deadline
+    pub liquidation_auction_max_discount: Decimal, // 17 In % range 0-25%
+    pub liquidation_auction_length: u32, // 4  Slots needed to reach max discount
+    pub bad_debt: Decimal,               // 17 Debt of accounts without collateral socialized so far
+    pub insurance_fund: Decimal,         // 17 Amount of xUSD kept by insurance fund
+    pub insurance_fund_target: Decimal,  // 17 Amount of insurance fund admin cannot withdraw
+    pub insurance_swap_tax_ratio: Decimal, // 17 In % part of swap tax going to insurance fund
+    pub insurance_penalty_ratio: Decimal, // 17 In % part of penalty_to_exchange going to insurance fund
+    pub liquidation_critical_ratio: Decimal, // 17 In % max_debt/user_debt below which deadline is skipped
+    pub liquidation_bounty: Decimal, // 17 Amount of SNY paid to keeper marking account for liquidation
+    pub liquidation_bounty_cooldown: u32, // 4  Slots between bounties paid for same account
+    pub liquidation_price_source: u8, // 1  PriceSource used to check collateralization in liquidation
+    pub version: u8,                  // 1  Version of state struct
+    pub insurance_withdrawal_delay: u32, // 4  Slots between requesting and executing insurance fund withdrawal
+    pub insurance_withdrawal_amount: Decimal, // 17 Amount of xUSD requested to withdraw from insurance fund
+    pub insurance_withdrawal_slot: u64, // 8  Slot after which requested withdrawal can be executed
+    pub padding: [u8; 1428],            // 1428 (2048 - 620) reserved for future use
 }
 impl Default for State {
     #[inline]
@@ -131,7 +180,23 @@ impl Default for State {
             staking: Staking::default(),
             exchange_authority: Pubkey::default(),
             bump: 0,
-            padding: [0; 1620],
+            liquidation_auction_start_discount: Decimal::default(),
+            liquidation_auction_max_discount: Decimal::default(),
+            liquidation_auction_length: 0,
+            bad_debt: Decimal::default(),
+            insurance_fund: Decimal::default(),
+            insurance_fund_target: Decimal::default(),
+            insurance_swap_tax_ratio: Decimal::default(),
+            insurance_penalty_ratio: Decimal::default(),
+            liquidation_critical_ratio: Decimal::default(),
+            liquidation_bounty: Decimal::default(),
+            liquidation_bounty_cooldown: 0,
+            liquidation_price_source: PriceSource::Spot as u8,
+            version: 0,
+            insurance_withdrawal_delay: 0,
+            insurance_withdrawal_amount: Decimal::default(),
+            insurance_withdrawal_slot: 0,
+            padding: [0; 1428],
         }
     }
 }
@@ -139,7 +204,7 @@ impl Default for State {
 #[account(zero_copy)]
 #[derive(PartialEq, Debug)]
 pub struct ExchangeAccount {
-    // 1412
+    // 2040
     pub owner: Pubkey,                      // 32 Identity controlling account
     pub version: u8,                        // 1 Version of account struct
     pub debt_shares: u64,                   // 8 Shares representing part of entire debt pool
@@ -148,6 +213,18 @@ pub struct ExchangeAccount {
     pub head: u8,                           // 1
     pub bump: u8,                           // 1
     pub collaterals: [CollateralEntry; 32], // 1312
+    pub delegate: Pubkey,                   // 32 Identity allowed to act on behalf of owner
+    pub delegate_permissions: u8,           // 1 Bitmask of DelegatePermission
+    pub id: u8,                             // 1 Index of owner sub-account
+    pub liquidation_bounty_slot: u64, // 8 Slot in which keeper bounty was paid for marking account
+    pub padding: [u8; 586],           // 586 (2040 - 1454) reserved for future use
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum DelegatePermission {
+    // 1 is unused, deposit is open to everyone
+    Burn = 2,
+    Mint = 4,
+    Withdraw = 8,
 }
 impl Default for ExchangeAccount {
     #[inline]
@@ -158,14 +235,34 @@ impl Default for ExchangeAccount {
             version: 0,
             debt_shares: 0,
             liquidation_deadline: 0,
+            liquidation_bounty_slot: 0,
             owner: Pubkey::default(),
             user_staking_data: UserStaking::default(),
             collaterals: [CollateralEntry {
                 ..Default::default()
             }; 32],
+            delegate: Pubkey::default(),
+            delegate_permissions: 0,
+            id: 0,
+            padding: [0; 586],
         }
     }
 }
+// Layout of version 0 stored at seeds [b"accountv1", owner]
+// Read only to migrate into current ExchangeAccount
+#[zero_copy]
+#[derive(PartialEq, Default, Debug)]
+pub struct LegacyExchangeAccount {
+    // 1412
+    pub owner: Pubkey,                      // 32
+    pub version: u8,                        // 1
+    pub debt_shares: u64,                   // 8
+    pub liquidation_deadline: u64,          // 8
+    pub user_staking_data: UserStaking,     // 49
+    pub head: u8,                           // 1
+    pub bump: u8,                           // 1
+    pub collaterals: [CollateralEntry; 32], // 1312
+}
 #[zero_copy]
 #[derive(PartialEq, Default, Debug)]
 pub struct CollateralEntry {
@@ -175,9 +272,25 @@ pub struct CollateralEntry {
     pub index: u8,                  // 1
 }
 impl ExchangeAccount {
-    pub fn append(&mut self, entry: CollateralEntry) {
+    pub fn is_authorized(&self, signer: &Pubkey, permission: DelegatePermission) -> bool {
+        // delegate can never act with empty permissions
+        signer.eq(&self.owner)
+            || (signer.eq(&self.delegate) && self.delegate_permissions & permission as u8 != 0)
+    }
+    pub fn is_owner_or_delegate(&self, signer: &Pubkey) -> bool {
+        signer.eq(&self.owner) || (signer.eq(&self.delegate) && self.delegate_permissions != 0)
+    }
+    pub fn append(&mut self, entry: CollateralEntry) -> Result<()> {
+        // Entries are stored inline and account cannot be resized (no realloc in solana 1.7.8)
+        // TODO paging of entries above limit, until then more collateral types
+        // have to be deposited into another sub-account
+        require!(
+            (self.head as usize) < self.collaterals.len(),
+            CollateralEntriesLimit
+        );
         self.collaterals[(self.head) as usize] = entry;
         self.head += 1;
+        Ok(())
     }
     pub fn remove(&mut self, index: usize) {
         self.collaterals[index] = self.collaterals[(self.head - 1) as usize];
@@ -206,12 +319,12 @@ pub struct Swapline {
 #[account(zero_copy)]
 // #[derive(Default)]
 pub struct AssetsList {
-    // 93333
+    // 110418
     pub head_assets: u8,                // 1
     pub head_collaterals: u8,           // 1
     pub head_synthetics: u8,            // 1
     pub assets: [Asset; 255],           // 27795
-    pub collaterals: [Collateral; 255], // 37740
+    pub collaterals: [Collateral; 255], // 54825
     pub synthetics: [Synthetic; 255],   // 27795
 }
 impl Default for AssetsList {
@@ -243,6 +356,29 @@ impl AssetsList {
         self.collaterals[(self.head_collaterals) as usize] = new_collateral;
         self.head_collaterals += 1;
     }
+    pub fn remove_collateral(&mut self, index: usize) -> Result<()> {
+        require!(index > 0, CollateralNotRemovable);
+        self.collaterals[index] = self.collaterals[(self.head_collaterals - 1) as usize];
+        self.collaterals[(self.head_collaterals - 1) as usize] = Collateral {
+            ..Default::default()
+        };
+        self.head_collaterals -= 1;
+        Ok(())
+    }
+    pub fn get_collateral_index(&self, entry: &CollateralEntry) -> Result<usize> {
+        // index of entry can be outdated after removal of other collateral
+        let index = entry.index as usize;
+        if self.collaterals[index].collateral_address == entry.collateral_address {
+            return Ok(index);
+        }
+        match self.collaterals[..self.head_collaterals as usize]
+            .iter()
+            .position(|x| x.collateral_address == entry.collateral_address)
+        {
+            Some(index) => Ok(index),
+            None => Err(ErrorCode::NoAssetFound.into()),
+        }
+    }
     pub fn append_synthetic(&mut self, new_synthetic: Synthetic) {
         self.synthetics[(self.head_synthetics) as usize] = new_synthetic;
         self.head_synthetics += 1;
@@ -256,6 +392,39 @@ impl AssetsList {
         self.head_synthetics -= 1;
         Ok(())
     }
+    pub fn remove_asset(&mut self, index: usize) -> Result<()> {
+        // xUSD and SNY assets got static indexes
+        require!(index > 1, AssetInUse);
+        let referenced = self.collaterals[..self.head_collaterals as usize]
+            .iter()
+            .any(|x| x.asset_index as usize == index)
+            || self.synthetics[..self.head_synthetics as usize]
+                .iter()
+                .any(|x| x.asset_index as usize == index);
+        require!(!referenced, AssetInUse);
+
+        let last_index = (self.head_assets - 1) as usize;
+        self.assets[index] = self.assets[last_index];
+        self.assets[last_index] = Asset {
+            ..Default::default()
+        };
+        self.head_assets -= 1;
+
+        // Point references of moved asset to its new index
+        let head_collaterals = self.head_collaterals as usize;
+        for collateral in self.collaterals[..head_collaterals].iter_mut() {
+            if collateral.asset_index as usize == last_index {
+                collateral.asset_index = index as u8;
+            }
+        }
+        let head_synthetics = self.head_synthetics as usize;
+        for synthetic in self.synthetics[..head_synthetics].iter_mut() {
+            if synthetic.asset_index as usize == last_index {
+                synthetic.asset_index = index as u8;
+            }
+        }
+        Ok(())
+    }
     pub fn split_borrow(
         &mut self,
     ) -> (
@@ -279,10 +448,19 @@ pub struct Decimal {
     pub scale: u8, // 1
 }
 
+#[zero_copy]
+#[derive(PartialEq, Default, Debug)]
+pub struct OracleSettings {
+    // 22
+    pub max_delay: u32,               // 4 Slots since last price update
+    pub max_price_deviation: Decimal, // 17 In % of twap, zero disables check
+    pub require_trading: bool,        // 1
+}
+
 #[account(zero_copy)]
 #[derive(PartialEq, Default, Debug)]
 pub struct Vault {
-    // 293
+    // 1123
     pub halted: bool,                            // 1
     pub synthetic: Pubkey,                       // 32
     pub collateral: Pubkey,                      // 32
@@ -300,10 +478,63 @@ pub struct Vault {
     pub max_borrow: Decimal,                     // 17
     pub last_update: i64,                        // 8
     pub bump: u8,                                // 1
+    pub vault_type: u8,                          // 1
+    pub oracle_settings: OracleSettings,         // 22
+    pub liquidation_price_source: u8,            // 1 PriceSource used in liquidation
+    pub basket_head: u8,                         // 1
+    pub basket: [BasketCollateral; 7],           // 805 Collaterals next to primary one
 }
 #[account(zero_copy)]
 #[derive(PartialEq, Default, Debug)]
 pub struct VaultEntry {
+    // 235
+    pub owner: Pubkey,                           // 32
+    pub vault: Pubkey,                           // 32
+    pub last_accumulated_interest_rate: Decimal, // 17
+    pub synthetic_amount: Decimal,               // 17
+    pub collateral_amount: Decimal,              // 17
+    pub bump: u8,                                // 1
+    pub basket_amounts: [Decimal; 7],            // 119 Indexed as basket of vault
+}
+#[zero_copy]
+#[derive(PartialEq, Default, Debug)]
+pub struct BasketCollateral {
+    // 115
+    pub collateral: Pubkey,             // 32
+    pub collateral_reserve: Pubkey,     // 32
+    pub collateral_ratio: Decimal,      // 17 In % weight of collateral value in borrow limit
+    pub liquidation_threshold: Decimal, // 17 In % weight of collateral value in liquidation
+    pub collateral_amount: Decimal,     // 17
+}
+// Layout stored at seeds [b"vaultv1", synthetic, collateral] before vault types
+// Read only to migrate into current Vault
+#[zero_copy]
+#[derive(PartialEq, Default, Debug)]
+pub struct LegacyVault {
+    // 293
+    pub halted: bool,                            // 1
+    pub synthetic: Pubkey,                       // 32
+    pub collateral: Pubkey,                      // 32
+    pub debt_interest_rate: Decimal,             // 17
+    pub collateral_ratio: Decimal,               // 17
+    pub liquidation_threshold: Decimal,          // 17
+    pub liquidation_ratio: Decimal,              // 17
+    pub liquidation_penalty_liquidator: Decimal, // 17
+    pub liquidation_penalty_exchange: Decimal,   // 17
+    pub accumulated_interest: Decimal,           // 17
+    pub accumulated_interest_rate: Decimal,      // 17
+    pub collateral_reserve: Pubkey,              // 32
+    pub mint_amount: Decimal,                    // 17
+    pub collateral_amount: Decimal,              // 17
+    pub max_borrow: Decimal,                     // 17
+    pub last_update: i64,                        // 8
+    pub bump: u8,                                // 1
+}
+// Layout stored at seeds [b"vault_entryv1", owner, legacy_vault]
+// Read only to migrate into current VaultEntry
+#[zero_copy]
+#[derive(PartialEq, Default, Debug)]
+pub struct LegacyVaultEntry {
     // 116
     pub owner: Pubkey,                           // 32
     pub vault: Pubkey,                           // 32
@@ -312,3 +543,67 @@ pub struct VaultEntry {
     pub collateral_amount: Decimal,              // 17
     pub bump: u8,                                // 1
 }
+impl Vault {
+    // Primary collateral is at index 0, basket collaterals follow it
+    pub fn get_collateral(&self, index: usize) -> BasketCollateral {
+        match index {
+            0 => BasketCollateral {
+                collateral: self.collateral,
+                collateral_reserve: self.collateral_reserve,
+                collateral_ratio: self.collateral_ratio,
+                liquidation_threshold: self.liquidation_threshold,
+                collateral_amount: self.collateral_amount,
+            },
+            _ => self.basket[index - 1],
+        }
+    }
+    pub fn get_collateral_index(&self, collateral: &Pubkey) -> Result<usize> {
+        if self.collateral.eq(collateral) {
+            return Ok(0);
+        }
+        match self.basket[..self.basket_head as usize]
+            .iter()
+            .position(|x| x.collateral.eq(collateral))
+        {
+            Some(index) => Ok(index + 1),
+            None => Err(ErrorCode::NoAssetFound.into()),
+        }
+    }
+    pub fn set_collateral_amount(&mut self, index: usize, collateral_amount: Decimal) {
+        match index {
+            0 => self.collateral_amount = collateral_amount,
+            _ => self.basket[index - 1].collateral_amount = collateral_amount,
+        }
+    }
+    pub fn collaterals_count(&self) -> usize {
+        self.basket_head as usize + 1
+    }
+    pub fn append(&mut self, entry: BasketCollateral) -> Result<()> {
+        require!(
+            (self.basket_head as usize) < self.basket.len(),
+            CollateralEntriesLimit
+        );
+        self.basket[(self.basket_head) as usize] = entry;
+        self.basket_head += 1;
+        Ok(())
+    }
+}
+impl VaultEntry {
+    pub fn get_collateral_amount(&self, index: usize) -> Decimal {
+        match index {
+            0 => self.collateral_amount,
+            _ => self.basket_amounts[index - 1],
+        }
+    }
+    pub fn set_collateral_amount(&mut self, index: usize, collateral_amount: Decimal) {
+        match index {
+            0 => self.collateral_amount = collateral_amount,
+            _ => self.basket_amounts[index - 1] = collateral_amount,
+        }
+    }
+    pub fn is_empty(&self) -> bool {
+        self.synthetic_amount.val == 0
+            && self.collateral_amount.val == 0
+            && self.basket_amounts.iter().all(|x| x.val == 0)
+    }
+}
@@ -11,6 +11,19 @@ pub struct SetAssetsList<'info> {
     pub admin: AccountInfo<'info>,
 }
 #[derive(Accounts)]
+pub struct MigrateAssetsList<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    // assets list with legacy collaterals layout, closed after migration
+    #[account(mut, constraint = legacy_assets_list.key == &state.load()?.assets_list)]
+    pub legacy_assets_list: AccountInfo<'info>,
+    #[account(zero)]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(mut, signer)]
+    pub admin: AccountInfo<'info>,
+    pub rent: Sysvar<'info, Rent>,
+}
+#[derive(Accounts)]
 #[instruction(bump: u8)]
 pub struct CreateSwapline<'info> {
     #[account(seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
@@ -344,6 +357,44 @@ pub struct SetMaxCollateral<'info> {
     pub collateral_address: CpiAccount<'info, anchor_spl::token::Mint>,
 }
 #[derive(Accounts)]
+pub struct SetCollateralParameter<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(constraint = collateral_address.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral_address: CpiAccount<'info, anchor_spl::token::Mint>,
+}
+#[derive(Accounts)]
+pub struct RemoveSynthetic<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(constraint = synthetic_address.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic_address: CpiAccount<'info, anchor_spl::token::Mint>,
+}
+#[derive(Accounts)]
+pub struct RemoveAsset<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    pub feed_address: AccountInfo<'info>,
+}
+#[derive(Accounts)]
 pub struct SetAdmin<'info> {
     #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
@@ -380,9 +431,9 @@ pub struct AddSynthetic<'info> {
 }
 
 #[derive(Accounts)]
-#[instruction(bump: u8)]
+#[instruction(bump: u8, id: u8)]
 pub struct CreateExchangeAccount<'info> {
-    #[account(init,seeds = [b"accountv1", admin.key.as_ref()], bump=bump, payer=payer )]
+    #[account(init,seeds = [b"accountv1", admin.key.as_ref(), &[id]], bump=bump, payer=payer )]
     pub exchange_account: Loader<'info, ExchangeAccount>,
     pub admin: AccountInfo<'info>,
     #[account(mut, signer)]
@@ -390,6 +441,39 @@ pub struct CreateExchangeAccount<'info> {
     pub rent: Sysvar<'info, Rent>,
     pub system_program: AccountInfo<'info>,
 }
+#[derive(Accounts)]
+pub struct CloseExchangeAccount<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut, has_one = owner, close = owner)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(mut, signer)]
+    pub owner: AccountInfo<'info>,
+}
+#[derive(Accounts)]
+pub struct SetDelegate<'info> {
+    #[account(mut, has_one = owner)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(signer)]
+    pub owner: AccountInfo<'info>,
+    pub delegate: AccountInfo<'info>,
+}
+#[derive(Accounts)]
+#[instruction(bump: u8, id: u8)]
+pub struct TransferPosition<'info> {
+    #[account(seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut, has_one = owner, close = owner)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(init, seeds = [b"accountv1", new_owner.key.as_ref(), &[id]], bump=bump, payer=new_owner)]
+    pub new_exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(mut, signer)]
+    pub owner: AccountInfo<'info>,
+    #[account(mut, signer)]
+    pub new_owner: AccountInfo<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: AccountInfo<'info>,
+}
 
 
 #[derive(Accounts)]
@@ -410,7 +494,9 @@ pub struct Withdraw<'info> {
     pub user_collateral_account: CpiAccount<'info, TokenAccount>,
     #[account(address = token::ID)]
     pub token_program: AccountInfo<'info>,
-    #[account(mut, has_one = owner)]
+    #[account(mut,
+        constraint = exchange_account.load()?.is_authorized(owner.key, DelegatePermission::Withdraw)
+    )]
     pub exchange_account: Loader<'info, ExchangeAccount>,
     #[account(signer)]
     pub owner: AccountInfo<'info>,
@@ -447,7 +533,9 @@ pub struct Mint<'info> {
     pub to: CpiAccount<'info, TokenAccount>, // withdraw xusd to any account
     #[account(address = token::ID)]
     pub token_program: AccountInfo<'info>,
-    #[account(mut, has_one = owner)]
+    #[account(mut,
+        constraint = exchange_account.load()?.is_authorized(owner.key, DelegatePermission::Mint)
+    )]
     pub exchange_account: Loader<'info, ExchangeAccount>,
     #[account(signer)]
     pub owner: AccountInfo<'info>,
@@ -467,7 +555,7 @@ impl<'a, 'b, 'c, 'info> From<&Mint<'info>> for CpiContext<'a, 'b, 'c, 'info, Min
 pub struct Deposit<'info> {
     #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
-    #[account(mut,has_one = owner)]
+    #[account(mut)]
     pub exchange_account: Loader<'info, ExchangeAccount>,
     #[account(mut,
         constraint = &reserve_address.owner == exchange_authority.key
@@ -483,7 +571,7 @@ pub struct Deposit<'info> {
         constraint = assets_list.to_account_info().key == &state.load()?.assets_list
     )]
     pub assets_list: Loader<'info, AssetsList>,
-    // owner can deposit to any exchange_account
+    // anyone can top up existing entries, new entry requires owner or delegate
     #[account(signer)]
     pub owner: AccountInfo<'info>,
     #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
@@ -541,6 +629,75 @@ pub struct Liquidate<'info> {
     pub reserve_account: CpiAccount<'info, TokenAccount>,
 }
 #[derive(Accounts)]
+pub struct FlashLiquidate<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
+    pub exchange_authority: AccountInfo<'info>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+    #[account(mut,
+        constraint = usd_token.to_account_info().key == &assets_list.load()?.synthetics[0].asset_address,
+        constraint = usd_token.to_account_info().owner == &anchor_spl::token::ID
+    )]
+    pub usd_token: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(mut,
+        constraint = &liquidator_usd_account.mint == usd_token.to_account_info().key,
+        constraint = &liquidator_usd_account.owner == signer.key
+    )]
+    pub liquidator_usd_account: CpiAccount<'info, TokenAccount>,
+    // liquidated collateral can be send to any account
+    #[account(mut)]
+    pub liquidator_collateral_account: CpiAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(signer)]
+    pub signer: AccountInfo<'info>,
+    #[account(mut,
+        constraint = liquidation_fund.mint == liquidator_collateral_account.mint,
+        constraint = &liquidation_fund.owner == exchange_authority.key
+    )]
+    pub liquidation_fund: CpiAccount<'info, TokenAccount>,
+    #[account(mut,
+        constraint = reserve_account.mint == liquidator_collateral_account.mint,
+        constraint = &reserve_account.owner == exchange_authority.key
+    )]
+    pub reserve_account: CpiAccount<'info, TokenAccount>,
+    #[account(executable)]
+    pub callback_program: AccountInfo<'info>,
+}
+#[derive(Accounts)]
+pub struct LiquidateMulti<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
+    pub exchange_authority: AccountInfo<'info>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+    #[account(mut,
+        constraint = usd_token.to_account_info().key == &assets_list.load()?.synthetics[0].asset_address,
+        constraint = usd_token.to_account_info().owner == &anchor_spl::token::ID
+    )]
+    pub usd_token: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(mut,
+        constraint = &liquidator_usd_account.mint == usd_token.to_account_info().key,
+        constraint = &liquidator_usd_account.owner == signer.key
+    )]
+    pub liquidator_usd_account: CpiAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(signer)]
+    pub signer: AccountInfo<'info>,
+}
+#[derive(Accounts)]
 pub struct BurnToken<'info> {
     #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
@@ -562,7 +719,9 @@ pub struct BurnToken<'info> {
         constraint = &user_token_account_burn.owner == owner.key
     )]
     pub user_token_account_burn: CpiAccount<'info, TokenAccount>,
-    #[account(mut, has_one = owner)]
+    #[account(mut,
+        constraint = exchange_account.load()?.is_authorized(owner.key, DelegatePermission::Burn)
+    )]
     pub exchange_account: Loader<'info, ExchangeAccount>,
     #[account(signer)]
     pub owner: AccountInfo<'info>,
@@ -644,6 +803,75 @@ pub struct CheckCollateralization<'info> {
     pub assets_list: Loader<'info, AssetsList>,
 }
 #[derive(Accounts)]
+pub struct PreviewLiquidation<'info> {
+    #[account(seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+}
+#[derive(Accounts)]
+pub struct MarkAccountForLiquidation<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
+    pub exchange_authority: AccountInfo<'info>,
+    #[account(mut,
+        constraint = liquidation_fund.to_account_info().key == &assets_list.load()?.collaterals[0].liquidation_fund,
+        constraint = &liquidation_fund.owner == exchange_authority.key
+    )]
+    pub liquidation_fund: CpiAccount<'info, TokenAccount>,
+    // bounty can be send to any account
+    #[account(mut,
+        constraint = keeper_account.mint == liquidation_fund.mint
+    )]
+    pub keeper_account: CpiAccount<'info, TokenAccount>,
+    #[account(signer)]
+    pub signer: AccountInfo<'info>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+}
+#[derive(Accounts)]
+pub struct SettleBadDebt<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+}
+#[derive(Accounts)]
+#[instruction(bump: u8, legacy_bump: u8, id: u8)]
+pub struct MigrateExchangeAccount<'info> {
+    #[account(seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    // account of version 0 created before sub-accounts, closed after migration
+    #[account(mut, seeds = [b"accountv1", owner.key.as_ref()], bump = legacy_bump)]
+    pub legacy_exchange_account: AccountInfo<'info>,
+    #[account(init, seeds = [b"accountv1", owner.key.as_ref(), &[id]], bump = bump, payer = payer)]
+    pub exchange_account: Loader<'info, ExchangeAccount>,
+    // anyone can migrate, e.g. liquidator of outdated account
+    #[account(mut, signer)]
+    pub payer: AccountInfo<'info>,
+    // rent of legacy account goes back to owner
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+    #[account(constraint = assets_list.to_account_info().key == &state.load()?.assets_list)]
+    pub assets_list: Loader<'info, AssetsList>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: AccountInfo<'info>,
+}
+#[derive(Accounts)]
 pub struct ClaimRewards<'info> {
     #[account(mut, seeds = [b"statev1".as_ref()],bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
@@ -826,18 +1054,36 @@ impl<'a, 'b, 'c, 'info> From<&SwapSettledSynthetic<'info>>
         CpiContext::new(cpi_program, cpi_accounts)
     }
 }
+#[derive(Accounts)]
+pub struct CloseSettlement<'info> {
+    #[account(mut, close = admin, seeds = [b"settlement".as_ref(), token_to_settle.to_account_info().key.as_ref()],bump = settlement.load()?.bump)]
+    pub settlement: Loader<'info, Settlement>,
+    #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut, signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(
+        constraint = token_to_settle.to_account_info().key == &settlement.load()?.token_in_address,
+        constraint = token_to_settle.to_account_info().owner == &anchor_spl::token::ID
+    )]
+    pub token_to_settle: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(
+        constraint = settlement_reserve.to_account_info().key == &settlement.load()?.reserve_address
+    )]
+    pub settlement_reserve: CpiAccount<'info, TokenAccount>,
+}
 
 
 #[derive(Accounts)]
-#[instruction(bump: u8)]
+#[instruction(bump: u8, vault_type: u8)]
 pub struct CreateVault<'info> {
     #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
-    #[account(init, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()], bump=bump, payer=admin )]
+    #[account(init, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault_type]], bump=bump, payer=admin )]
     pub vault: Loader<'info, Vault>,
     #[account(mut, signer)]
     pub admin: AccountInfo<'info>,
-    #[account(constraint = assets_list.to_account_info().key == &state.load()?.assets_list)]
+    #[account(mut, constraint = assets_list.to_account_info().key == &state.load()?.assets_list)]
     pub assets_list: Loader<'info, AssetsList>,
     #[account(
         constraint = &collateral_reserve.mint == collateral.to_account_info().key,
@@ -852,6 +1098,27 @@ pub struct CreateVault<'info> {
     pub system_program: AccountInfo<'info>,
 }
 #[derive(Accounts)]
+#[instruction(bump: u8, legacy_bump: u8)]
+pub struct MigrateVault<'info> {
+    #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    // vault created before vault types, closed after migration
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()], bump = legacy_bump)]
+    pub legacy_vault: AccountInfo<'info>,
+    #[account(init, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[0]], bump=bump, payer=admin )]
+    pub vault: Loader<'info, Vault>,
+    #[account(mut, signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(mut, constraint = assets_list.to_account_info().key == &state.load()?.assets_list)]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: AccountInfo<'info>,
+}
+#[derive(Accounts)]
 #[instruction(bump: u8)]
 pub struct CreateVaultEntry<'info> {
     #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
@@ -860,7 +1127,7 @@ pub struct CreateVaultEntry<'info> {
     pub vault_entry: Loader<'info, VaultEntry>,
     #[account(mut, signer)]
     pub owner: AccountInfo<'info>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump )]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
     pub vault: Loader<'info, Vault>,
     #[account(constraint = assets_list.to_account_info().key == &state.load()?.assets_list)]
     pub assets_list: Loader<'info, AssetsList>,
@@ -872,25 +1139,87 @@ pub struct CreateVaultEntry<'info> {
     pub system_program: AccountInfo<'info>,
 }
 #[derive(Accounts)]
+#[instruction(bump: u8, legacy_bump: u8, legacy_vault_bump: u8)]
+pub struct MigrateVaultEntry<'info> {
+    #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    // only address of legacy vault is needed, it can be already closed
+    #[account(seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()], bump = legacy_vault_bump)]
+    pub legacy_vault: AccountInfo<'info>,
+    // entry of legacy vault, closed after migration
+    #[account(mut, seeds = [b"vault_entryv1", owner.key.as_ref(), legacy_vault.key.as_ref()], bump = legacy_bump)]
+    pub legacy_vault_entry: AccountInfo<'info>,
+    #[account(init, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()], bump=bump, payer=payer)]
+    pub vault_entry: Loader<'info, VaultEntry>,
+    // anyone can migrate, e.g. liquidator of legacy entry
+    #[account(mut, signer)]
+    pub payer: AccountInfo<'info>,
+    // rent of legacy entry goes back to owner
+    #[account(mut)]
+    pub owner: AccountInfo<'info>,
+    #[account(seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[0]],bump=vault.load()?.bump )]
+    pub vault: Loader<'info, Vault>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: AccountInfo<'info>,
+}
+#[derive(Accounts)]
+pub struct CloseVaultEntry<'info> {
+    #[account(mut, close = owner, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
+    pub vault_entry: Loader<'info, VaultEntry>,
+    #[account(mut, signer)]
+    pub owner: AccountInfo<'info>,
+    #[account(seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
+    pub vault: Loader<'info, Vault>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+}
+#[derive(Accounts)]
+#[instruction(bump: u8)]
+pub struct TransferVaultPosition<'info> {
+    #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut, close = owner, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
+    pub vault_entry: Loader<'info, VaultEntry>,
+    #[account(init, seeds = [b"vault_entryv1", new_owner.key.as_ref(), vault.to_account_info().key.as_ref()], bump=bump, payer=new_owner)]
+    pub new_vault_entry: Loader<'info, VaultEntry>,
+    #[account(mut, signer)]
+    pub owner: AccountInfo<'info>,
+    #[account(mut, signer)]
+    pub new_owner: AccountInfo<'info>,
+    #[account(seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
+    pub vault: Loader<'info, Vault>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: AccountInfo<'info>,
+}
+#[derive(Accounts)]
 pub struct DepositVault<'info> {
     #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
-    #[account(mut, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
+    #[account(mut, has_one = vault)]
     pub vault_entry: Loader<'info, VaultEntry>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump)]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
     pub vault: Loader<'info, Vault>,
     #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
     #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
     pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    // reserve of primary or basket collateral, checked in instruction
     #[account(mut,
-        constraint = &vault.load()?.collateral_reserve == reserve_address.to_account_info().key,
-        constraint = &reserve_address.mint == collateral.to_account_info().key,
         constraint = &reserve_address.owner == exchange_authority.key
     )]
     pub reserve_address: CpiAccount<'info, TokenAccount>,
     #[account(mut,
-        constraint = &user_collateral_account.mint == collateral.to_account_info().key,
+        constraint = user_collateral_account.mint == reserve_address.mint,
         constraint = &user_collateral_account.owner == owner.key
     )]
     pub user_collateral_account: CpiAccount<'info, TokenAccount>,
@@ -900,6 +1229,7 @@ pub struct DepositVault<'info> {
         constraint = assets_list.to_account_info().key == &state.load()?.assets_list
     )]
     pub assets_list: Loader<'info, AssetsList>,
+    // owner can deposit to any vault_entry
     #[account(mut, signer)]
     pub owner: AccountInfo<'info>,
     #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
@@ -924,7 +1254,7 @@ pub struct BorrowVault<'info> {
     pub state: Loader<'info, State>,
     #[account(mut, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
     pub vault_entry: Loader<'info, VaultEntry>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump)]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
     pub vault: Loader<'info, Vault>,
     #[account(mut,constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
@@ -957,26 +1287,119 @@ impl<'a, 'b, 'c, 'info> From<&BorrowVault<'info>> for CpiContext<'a, 'b, 'c, 'in
     }
 }
 
+#[derive(Accounts)]
+pub struct LeverageVault<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
+    pub vault_entry: Loader<'info, VaultEntry>,
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
+    pub vault: Loader<'info, Vault>,
+    // synthetic is same as swapline_synthetic or swapped into it
+    #[account(mut,seeds = [b"swaplinev1", swapline_synthetic.to_account_info().key.as_ref(),collateral.to_account_info().key.as_ref()], bump = swapline.load()?.bump )]
+    pub swapline: Loader<'info, Swapline>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = swapline_synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub swapline_synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(mut,
+        constraint = &vault.load()?.collateral_reserve == reserve_address.to_account_info().key,
+        constraint = &reserve_address.owner == exchange_authority.key
+    )]
+    pub reserve_address: CpiAccount<'info, TokenAccount>,
+    #[account(mut,
+        constraint = &swapline_reserve.owner == exchange_authority.key,
+        constraint = swapline_reserve.to_account_info().key == &swapline.load()?.collateral_reserve
+    )]
+    pub swapline_reserve: CpiAccount<'info, TokenAccount>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+    #[account(signer)]
+    pub owner: AccountInfo<'info>,
+    #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
+    pub exchange_authority: AccountInfo<'info>,
+}
+impl<'a, 'b, 'c, 'info> From<&LeverageVault<'info>>
+    for CpiContext<'a, 'b, 'c, 'info, Transfer<'info>>
+{
+    fn from(accounts: &LeverageVault<'info>) -> CpiContext<'a, 'b, 'c, 'info, Transfer<'info>> {
+        let cpi_accounts = Transfer {
+            from: accounts.swapline_reserve.to_account_info(),
+            to: accounts.reserve_address.to_account_info(),
+            authority: accounts.exchange_authority.to_account_info(),
+        };
+        let cpi_program = accounts.token_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+#[derive(Accounts)]
+pub struct CloseVaultPosition<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(mut, close = owner, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
+    pub vault_entry: Loader<'info, VaultEntry>,
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
+    pub vault: Loader<'info, Vault>,
+    // swapline_synthetic is same as synthetic or swapped into it
+    #[account(mut,seeds = [b"swaplinev1", swapline_synthetic.to_account_info().key.as_ref(),collateral.to_account_info().key.as_ref()], bump = swapline.load()?.bump )]
+    pub swapline: Loader<'info, Swapline>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = swapline_synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub swapline_synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(mut,
+        constraint = &vault.load()?.collateral_reserve == reserve_address.to_account_info().key,
+        constraint = &reserve_address.owner == exchange_authority.key
+    )]
+    pub reserve_address: CpiAccount<'info, TokenAccount>,
+    #[account(mut,
+        constraint = &swapline_reserve.owner == exchange_authority.key,
+        constraint = swapline_reserve.to_account_info().key == &swapline.load()?.collateral_reserve
+    )]
+    pub swapline_reserve: CpiAccount<'info, TokenAccount>,
+    #[account(mut,
+        constraint = &user_collateral_account.mint == collateral.to_account_info().key,
+        constraint = &user_collateral_account.owner == owner.key
+    )]
+    pub user_collateral_account: CpiAccount<'info, TokenAccount>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+    #[account(mut, signer)]
+    pub owner: AccountInfo<'info>,
+    #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
+    pub exchange_authority: AccountInfo<'info>,
+}
 #[derive(Accounts)]
 pub struct WithdrawVault<'info> {
     #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
     #[account(mut, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
     pub vault_entry: Loader<'info, VaultEntry>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump)]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
     pub vault: Loader<'info, Vault>,
     #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
     #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
     pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    // reserve of primary or basket collateral, checked in instruction
     #[account(mut, 
-        constraint = &vault.load()?.collateral_reserve == reserve_address.to_account_info().key,
         constraint = &reserve_address.owner == exchange_authority.key,
-        constraint = &reserve_address.mint == collateral.to_account_info().key,
     )]
     pub reserve_address: CpiAccount<'info, TokenAccount>,
     #[account(mut,
-        constraint = &user_collateral_account.mint == collateral.to_account_info().key,
+        constraint = user_collateral_account.mint == reserve_address.mint,
         constraint = &user_collateral_account.owner == owner.key
     )]
     pub user_collateral_account: CpiAccount<'info, TokenAccount>,
@@ -1009,7 +1432,7 @@ pub struct RepayVault<'info> {
     pub state: Loader<'info, State>,
     #[account(mut, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
     pub vault_entry: Loader<'info, VaultEntry>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump)]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
     pub vault: Loader<'info, Vault>,
     #[account(mut,constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
@@ -1043,12 +1466,52 @@ impl<'a, 'b, 'c, 'info> From<&RepayVault<'info>> for CpiContext<'a, 'b, 'c, 'inf
     }
 }
 #[derive(Accounts)]
-pub struct LiquidateVault<'info> {
+pub struct RepayVaultFor<'info> {
     #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
     pub state: Loader<'info, State>,
+    // any vault entry can be repaid
+    #[account(mut, has_one = vault)]
+    pub vault_entry: Loader<'info, VaultEntry>,
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump)]
+    pub vault: Loader<'info, Vault>,
+    #[account(mut,constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(mut,
+        constraint = assets_list.to_account_info().key == &state.load()?.assets_list
+    )]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(mut,
+        constraint = &signer_token_account_repay.owner == signer.key,
+        constraint = &signer_token_account_repay.mint == synthetic.to_account_info().key,
+    )]
+    pub signer_token_account_repay: CpiAccount<'info, TokenAccount>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+    #[account(signer)]
+    pub signer: AccountInfo<'info>,
+    #[account(constraint = exchange_authority.key == &state.load()?.exchange_authority)]
+    pub exchange_authority: AccountInfo<'info>,
+}
+impl<'a, 'b, 'c, 'info> From<&RepayVaultFor<'info>> for CpiContext<'a, 'b, 'c, 'info, Burn<'info>> {
+    fn from(accounts: &RepayVaultFor<'info>) -> CpiContext<'a, 'b, 'c, 'info, Burn<'info>> {
+        let cpi_accounts = Burn {
+            mint: accounts.synthetic.to_account_info(),
+            to: accounts.signer_token_account_repay.to_account_info(),
+            authority: accounts.exchange_authority.to_account_info(),
+        };
+        let cpi_program = accounts.token_program.to_account_info();
+        CpiContext::new(cpi_program, cpi_accounts)
+    }
+}
+#[derive(Accounts)]
+pub struct LiquidateVault<'info> {
+    #[account(mut, seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
     #[account(mut,has_one = owner, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
     pub vault_entry: Loader<'info, VaultEntry>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump )]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
     pub vault: Loader<'info, Vault>,
     #[account(mut,constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
@@ -1058,9 +1521,8 @@ pub struct LiquidateVault<'info> {
         constraint = assets_list.to_account_info().key == &state.load()?.assets_list
     )]
     pub assets_list: Loader<'info, AssetsList>,
+    // reserve of seized collateral, checked in instruction
     #[account(mut,
-        constraint = &vault.load()?.collateral_reserve == collateral_reserve.to_account_info().key,
-        constraint = &collateral_reserve.mint == collateral.to_account_info().key,
         constraint = &collateral_reserve.owner == exchange_authority.key
     )]
     pub collateral_reserve: CpiAccount<'info, TokenAccount>,
@@ -1071,12 +1533,12 @@ pub struct LiquidateVault<'info> {
     pub liquidator_synthetic_account: CpiAccount<'info, TokenAccount>,
     #[account(mut,
         constraint = &liquidator_collateral_account.owner == liquidator.key,
-        constraint = &liquidator_collateral_account.mint == collateral.to_account_info().key
+        constraint = liquidator_collateral_account.mint == collateral_reserve.mint
     )]
     pub liquidator_collateral_account: CpiAccount<'info, TokenAccount>,
     #[account(mut,
         constraint = &liquidation_fund.owner == &state.load()?.exchange_authority,
-        constraint = &liquidation_fund.mint == collateral.to_account_info().key
+        constraint = liquidation_fund.mint == collateral_reserve.mint
     )]
     pub liquidation_fund: CpiAccount<'info, TokenAccount>,
     #[account(address = token::ID)]
@@ -1096,7 +1558,7 @@ pub struct TriggerVaultEntryDebtAdjustment<'info> {
     pub admin: AccountInfo<'info>,
     #[account(mut, has_one = owner, seeds = [b"vault_entryv1", owner.key.as_ref(), vault.to_account_info().key.as_ref()],bump=vault_entry.load()?.bump)]
     pub vault_entry: Loader<'info, VaultEntry>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump )]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
     pub vault: Loader<'info, Vault>,
     #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
@@ -1115,7 +1577,7 @@ pub struct SetVaultHalted<'info> {
     pub state: Loader<'info, State>,
     #[account(signer)]
     pub admin: AccountInfo<'info>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump )]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
     pub vault: Loader<'info, Vault>,
     #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
@@ -1133,7 +1595,7 @@ pub struct SetVaultParameter<'info> {
     pub state: Loader<'info, State>,
     #[account(signer)]
     pub admin: AccountInfo<'info>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump )]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
     pub vault: Loader<'info, Vault>,
     #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
     pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
@@ -1147,7 +1609,7 @@ pub struct WithdrawVaultAccumulatedInterest<'info> {
     pub state: Loader<'info, State>,
     #[account(signer)]
     pub admin: AccountInfo<'info>,
-    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref()],bump=vault.load()?.bump )]
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
     pub vault: Loader<'info, Vault>,
     #[account(mut,
         constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID
@@ -1180,4 +1642,41 @@ impl<'a, 'b, 'c, 'info> From<&WithdrawVaultAccumulatedInterest<'info>>
         let cpi_program = accounts.token_program.to_account_info();
         CpiContext::new(cpi_program, cpi_accounts)
     }
-}
\ No newline at end of file
+}
+#[derive(Accounts)]
+pub struct AddVaultCollateral<'info> {
+    #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
+    pub vault: Loader<'info, Vault>,
+    #[account(mut, constraint = assets_list.to_account_info().key == &state.load()?.assets_list)]
+    pub assets_list: Loader<'info, AssetsList>,
+    #[account(
+        constraint = &collateral_reserve.mint == basket_collateral.to_account_info().key,
+        constraint = collateral_reserve.owner == state.load()?.exchange_authority
+    )]
+    pub collateral_reserve: CpiAccount<'info, TokenAccount>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = basket_collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub basket_collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+}
+#[derive(Accounts)]
+pub struct SetVaultBasketCollateral<'info> {
+    #[account(seeds = [b"statev1".as_ref()], bump = state.load()?.bump)]
+    pub state: Loader<'info, State>,
+    #[account(signer)]
+    pub admin: AccountInfo<'info>,
+    #[account(mut, seeds = [b"vaultv1", synthetic.to_account_info().key.as_ref(), collateral.to_account_info().key.as_ref(), &[vault.load()?.vault_type]],bump=vault.load()?.bump )]
+    pub vault: Loader<'info, Vault>,
+    #[account(constraint = synthetic.to_account_info().owner == &anchor_spl::token::ID)]
+    pub synthetic: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+    #[account(constraint = basket_collateral.to_account_info().owner == &anchor_spl::token::ID)]
+    pub basket_collateral: CpiAccount<'info, anchor_spl::token::Mint>,
+}
@@ -0,0 +1,28 @@
+use crate::account::Decimal;
+use anchor_lang::prelude::*;
+
+#[derive(Clone, Copy, PartialEq, Debug, AnchorDeserialize, AnchorSerialize)]
+pub struct CollateralPreview {
+    pub collateral_address: Pubkey,
+    pub liquidation_price: Decimal, // Price of collateral at which account can be liquidated
+    pub max_withdrawable: Decimal,  // Amount of collateral possible to withdraw
+}
+
+#[event]
+#[derive(PartialEq, Debug)]
+pub struct LiquidationPreview {
+    pub owner: Pubkey,
+    pub collateral_ratio: Decimal, // In % value of collaterals divided by debt, 0 without debt
+    pub max_mintable: Decimal,     // Amount of xUSD possible to mint
+    pub collaterals: Vec<CollateralPreview>,
+}
+
+#[event]
+#[derive(PartialEq, Debug)]
+pub struct CollateralDeposited {
+    pub benefactor: Pubkey, // Signer paying the collateral
+    pub owner: Pubkey,      // Owner of credited position
+    pub position: Pubkey,   // Exchange account or vault entry
+    pub collateral_address: Pubkey,
+    pub amount: u64,
+}
@@ -1,46 +1,127 @@
 pub mod account;
 pub mod context;
 pub mod decimal;
+pub mod events;
 pub mod math;
 pub mod utils;
 use account::*;
 use anchor_lang::prelude::*;
 use anchor_spl::token::{self, Burn, MintTo, Transfer};
 use context::*;
+use events::*;
 use pyth::pc::{Price, PriceStatus};
 use utils::*;
 
 const SYNTHETIFY_EXCHANGE_SEED: &str = "Synthetify";
+pub const EXCHANGE_ACCOUNT_VERSION: u8 = 1;
+pub const STATE_VERSION: u8 = 2;
+pub const MAX_LIQUIDATION_BOUNTY: u64 = 100; // in SNY
 #[program]
 pub mod exchange {
-    use std::{borrow::BorrowMut, convert::TryInto};
+    use std::convert::TryInto;
+
+    use anchor_lang::solana_program::{
+        instruction::{AccountMeta, Instruction},
+        program::invoke,
+    };
+    use anchor_lang::Discriminator;
 
     use crate::math::{
-        amount_to_discount, amount_to_shares_by_rounding_down, calculate_burned_shares,
-        calculate_max_debt_in_usd, calculate_max_withdraw_in_usd,
-        calculate_new_shares_by_rounding_up, calculate_swap_out_amount, calculate_swap_tax,
-        calculate_user_debt_in_usd, calculate_value_in_usd, calculate_vault_borrow_limit,
-        calculate_vault_withdraw_limit, usd_to_token_amount,
+        amount_to_discount, calculate_burned_shares, calculate_debt_by_price_source,
+        calculate_deprecated_ratio, calculate_liquidation_preview, calculate_max_debt_in_usd,
+        calculate_max_withdraw_in_usd, calculate_new_shares_by_rounding_up,
+        calculate_swap_in_amount, calculate_swap_out_amount, calculate_swap_tax,
+        calculate_swapline_amount_in, calculate_user_debt_in_usd, calculate_value_in_usd,
+        calculate_vault_borrow_limit, calculate_vault_leverage, calculate_vault_withdraw_limit,
+        get_liquidation_threshold, get_price_by_source, usd_to_token_amount,
     };
 
     use crate::decimal::{
         Add, Compare, DivUp, Mul, MulUp, Sub, PRICE_SCALE, SNY_SCALE, UNIFIED_PERCENT_SCALE,
-        XUSD_SCALE,
     };
 
     use super::*;
 
-    pub fn create_exchange_account(ctx: Context<CreateExchangeAccount>, bump: u8) -> ProgramResult {
+    pub fn create_exchange_account(
+        ctx: Context<CreateExchangeAccount>,
+        bump: u8,
+        id: u8,
+    ) -> ProgramResult {
         let exchange_account = &mut ctx.accounts.exchange_account.load_init()?;
         exchange_account.owner = *ctx.accounts.admin.key;
         exchange_account.debt_shares = 0;
-        exchange_account.version = 0;
+        exchange_account.version = EXCHANGE_ACCOUNT_VERSION;
         exchange_account.bump = bump;
+        exchange_account.id = id;
         exchange_account.liquidation_deadline = u64::MAX;
+        exchange_account.liquidation_bounty_slot = 0;
         exchange_account.user_staking_data = UserStaking::default();
         exchange_account.user_staking_data.amount_to_claim = Decimal::from_sny(0);
         Ok(())
     }
+    #[access_control(version(&ctx.accounts.exchange_account))]
+    pub fn close_exchange_account(ctx: Context<CloseExchangeAccount>) -> Result<()> {
+        msg!("Synthetify: CLOSE EXCHANGE ACCOUNT");
+        let slot = Clock::get()?.slot;
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+
+        // Adjust staking round
+        adjust_staking_rounds(state, slot);
+
+        // adjust current staking points for exchange account
+        adjust_staking_account(exchange_account, &state.staking);
+
+        // only account without debt, collaterals and rewards can be closed
+        require!(
+            exchange_account.debt_shares == 0
+                && exchange_account.head == 0
+                && exchange_account.user_staking_data.amount_to_claim.val == 0,
+            AccountNotEmpty
+        );
+        Ok(())
+    }
+    #[access_control(version(&ctx.accounts.exchange_account))]
+    pub fn set_delegate(ctx: Context<SetDelegate>, permissions: u8) -> Result<()> {
+        msg!("Synthetify: SET DELEGATE");
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+
+        let all_permissions = DelegatePermission::Burn as u8
+            | DelegatePermission::Mint as u8
+            | DelegatePermission::Withdraw as u8;
+        require!(permissions & !all_permissions == 0, ParameterOutOfRange);
+
+        // empty permissions remove delegate
+        exchange_account.delegate = match permissions {
+            0 => Pubkey::default(),
+            _ => *ctx.accounts.delegate.key,
+        };
+        exchange_account.delegate_permissions = permissions;
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
+    pub fn transfer_position(ctx: Context<TransferPosition>, bump: u8, id: u8) -> Result<()> {
+        msg!("Synthetify: TRANSFER POSITION");
+        let exchange_account = ctx.accounts.exchange_account.load()?;
+        let new_exchange_account = &mut ctx.accounts.new_exchange_account.load_init()?;
+
+        // move debt, collaterals and staking data to new owner
+        new_exchange_account.owner = *ctx.accounts.new_owner.key;
+        new_exchange_account.version = exchange_account.version;
+        new_exchange_account.debt_shares = exchange_account.debt_shares;
+        new_exchange_account.liquidation_deadline = exchange_account.liquidation_deadline;
+        new_exchange_account.liquidation_bounty_slot = exchange_account.liquidation_bounty_slot;
+        new_exchange_account.user_staking_data = exchange_account.user_staking_data;
+        new_exchange_account.head = exchange_account.head;
+        new_exchange_account.collaterals = exchange_account.collaterals;
+        new_exchange_account.bump = bump;
+        new_exchange_account.id = id;
+        // delegate is not transferred
+        new_exchange_account.delegate = Pubkey::default();
+        new_exchange_account.delegate_permissions = 0;
+        Ok(())
+    }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
     pub fn create_list(ctx: Context<InitializeAssetsList>) -> Result<()> {
         let assets_list = &mut ctx.accounts.assets_list.load_init()?;
@@ -80,6 +161,14 @@ pub mod exchange {
             reserve_address: *ctx.accounts.sny_reserve.to_account_info().key,
             liquidation_fund: *ctx.accounts.sny_liquidation_fund.to_account_info().key,
             max_collateral: Decimal::from_sny(u64::MAX.into()),
+            liquidation_mode: LiquidationMode::Fixed as u8,
+            liquidation_threshold: Decimal::from_percent(10), // 10%
+            deprecated: false,
+            deprecation_start: 0,
+            deprecation_length: 0,
+            initial_collateral_ratio: Decimal::from_percent(0),
+            initial_liquidation_threshold: Decimal::from_percent(0),
+            vault_count: 0,
         };
 
         assets_list.append_asset(usd_asset);
@@ -170,6 +259,27 @@ pub mod exchange {
                 None => return Err(ErrorCode::NoAssetFound.into()),
             }
         }
+
+        // Lower ratios of deprecated collaterals
+        let slot = Clock::get()?.slot;
+        let head_collaterals = assets_list.head_collaterals as usize;
+        for collateral in assets_list.collaterals[..head_collaterals]
+            .iter_mut()
+            .filter(|x| x.deprecated)
+        {
+            collateral.collateral_ratio = calculate_deprecated_ratio(
+                collateral.initial_collateral_ratio,
+                collateral.deprecation_start,
+                collateral.deprecation_length,
+                slot,
+            );
+            collateral.liquidation_threshold = calculate_deprecated_ratio(
+                collateral.initial_liquidation_threshold,
+                collateral.deprecation_start,
+                collateral.deprecation_length,
+                slot,
+            );
+        }
         Ok(())
     }
     pub fn init(
@@ -203,6 +313,22 @@ pub mod exchange {
         state.penalty_to_exchange = Decimal::from_percent(5); // 5%
         state.accumulated_debt_interest = Decimal::from_usd(0);
         state.liquidation_rate = Decimal::from_percent(20); // 20%
+        state.liquidation_auction_start_discount = Decimal::from_percent(1); // 1%
+        state.liquidation_auction_max_discount = Decimal::from_percent(10); // 10%
+        state.liquidation_auction_length = 7200; // about 1 Hour
+        state.bad_debt = Decimal::from_usd(0);
+        state.insurance_fund = Decimal::from_usd(0);
+        state.insurance_fund_target = Decimal::from_usd(0);
+        state.insurance_swap_tax_ratio = Decimal::from_percent(0);
+        state.insurance_penalty_ratio = Decimal::from_percent(0);
+        state.liquidation_critical_ratio = Decimal::from_percent(0);
+        state.liquidation_bounty = Decimal::from_sny(0);
+        state.liquidation_bounty_cooldown = 172800; // about 24 Hours
+        state.liquidation_price_source = PriceSource::Spot as u8;
+        state.insurance_withdrawal_delay = 172800; // about 24 Hours
+        state.insurance_withdrawal_amount = Decimal::from_usd(0);
+        state.insurance_withdrawal_slot = 0;
+        state.version = STATE_VERSION;
 
         // TODO decide about length of buffer
         // Maybe just couple of minutes will be enough ?
@@ -232,7 +358,8 @@ pub mod exchange {
         };
         Ok(())
     }
-    #[access_control(halted(&ctx.accounts.state))]
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
     pub fn deposit(ctx: Context<Deposit>, amount: u64) -> Result<()> {
         msg!("Synthetify: DEPOSIT");
         let state = &mut ctx.accounts.state.load_mut()?;
@@ -258,6 +385,7 @@ pub mod exchange {
             })
             .unwrap();
         let collateral = &mut assets_list.collaterals[collateral_index];
+        require!(!collateral.deprecated, CollateralDeprecated);
 
         let amount_decimal = Decimal {
             val: amount.into(),
@@ -275,13 +403,24 @@ pub mod exchange {
             .find(|x| x.collateral_address.eq(&collateral.collateral_address));
 
         match exchange_account_collateral {
-            Some(entry) => entry.amount = entry.amount.checked_add(amount).unwrap(),
-            None => exchange_account.append(CollateralEntry {
-                amount,
-                collateral_address: collateral.collateral_address,
-                index: collateral_index as u8,
-                ..Default::default()
-            }),
+            Some(entry) => {
+                entry.amount = entry.amount.checked_add(amount).unwrap();
+                // index could change after removal of other collateral
+                entry.index = collateral_index as u8;
+            }
+            None => {
+                // new entry takes one of limited slots, so strangers cannot fill them with dust
+                require!(
+                    exchange_account.is_owner_or_delegate(ctx.accounts.owner.key),
+                    Unauthorized
+                );
+                exchange_account.append(CollateralEntry {
+                    amount,
+                    collateral_address: collateral.collateral_address,
+                    index: collateral_index as u8,
+                    ..Default::default()
+                })?
+            }
         }
 
         // Transfer token
@@ -290,9 +429,18 @@ pub mod exchange {
         let cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
 
         token::transfer(cpi_ctx, amount)?;
+
+        emit!(CollateralDeposited {
+            benefactor: *ctx.accounts.owner.key,
+            owner: exchange_account.owner,
+            position: *ctx.accounts.exchange_account.to_account_info().key,
+            collateral_address: collateral.collateral_address,
+            amount,
+        });
         Ok(())
     }
-    #[access_control(halted(&ctx.accounts.state))]
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
     pub fn mint(ctx: Context<Mint>, amount: u64) -> Result<()> {
         msg!("Synthetify: MINT");
         let mut state = &mut ctx.accounts.state.load_mut()?;
@@ -304,6 +452,12 @@ pub mod exchange {
         adjust_staking_rounds(&mut state, slot);
 
         let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+        // delegate can mint only to accounts of owner
+        require!(
+            ctx.accounts.owner.key.eq(&exchange_account.owner)
+                || ctx.accounts.to.owner.eq(&exchange_account.owner),
+            Unauthorized
+        );
         // adjust current staking points for exchange account
         adjust_staking_account(exchange_account, &state.staking);
 
@@ -312,7 +466,7 @@ pub mod exchange {
         let total_debt =
             calculate_debt_with_adjustment(state, assets_list, slot, timestamp).unwrap();
         let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
-        let max_debt = calculate_max_debt_in_usd(exchange_account, assets_list);
+        let max_debt = calculate_max_debt_in_usd(exchange_account, assets_list)?;
         let mint_limit = max_debt.mul(state.health_factor);
 
         let synthetics = &mut assets_list.synthetics;
@@ -355,7 +509,8 @@ pub mod exchange {
         token::mint_to(mint_cpi_ctx, amount.to_u64())?;
         Ok(())
     }
-    #[access_control(halted(&ctx.accounts.state))]
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
     pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
         msg!("Synthetify: WITHDRAW");
         let mut state = &mut ctx.accounts.state.load_mut()?;
@@ -370,13 +525,19 @@ pub mod exchange {
         adjust_staking_account(exchange_account, &state.staking);
 
         let user_collateral_account = &mut ctx.accounts.user_collateral_account;
+        // delegate can withdraw only to accounts of owner
+        require!(
+            ctx.accounts.owner.key.eq(&exchange_account.owner)
+                || user_collateral_account.owner.eq(&exchange_account.owner),
+            Unauthorized
+        );
 
         // Calculate debt
         let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
         let total_debt =
             calculate_debt_with_adjustment(state, assets_list, slot, timestamp).unwrap();
         let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
-        let max_debt = calculate_max_debt_in_usd(exchange_account, assets_list);
+        let max_debt = calculate_max_debt_in_usd(exchange_account, assets_list)?;
 
         let max_borrow = max_debt.mul(state.health_factor);
 
@@ -468,7 +629,8 @@ pub mod exchange {
         token::transfer(cpi_ctx, amount_to_withdraw.val.try_into().unwrap())?;
         Ok(())
     }
-    #[access_control(halted(&ctx.accounts.state))]
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
     pub fn swap(ctx: Context<Swap>, amount: u64) -> Result<()> {
         msg!("Synthetify: SWAP");
         let mut state = &mut ctx.accounts.state.load_mut()?;
@@ -551,7 +713,13 @@ pub mod exchange {
 
         // Update swap_tax_reserve
         let swap_tax_reserve = calculate_swap_tax(fee_usd, state.swap_tax_ratio);
-        state.swap_tax_reserve = state.swap_tax_reserve.add(swap_tax_reserve).unwrap();
+        // Part of swap tax goes to insurance fund
+        let insurance_amount = swap_tax_reserve.mul(state.insurance_swap_tax_ratio);
+        state.insurance_fund = state.insurance_fund.add(insurance_amount).unwrap();
+        state.swap_tax_reserve = state
+            .swap_tax_reserve
+            .add(swap_tax_reserve.sub(insurance_amount).unwrap())
+            .unwrap();
 
         // Update xUSD supply based on tax
         let new_xusd_supply = synthetics[0].supply.add(swap_tax_reserve).unwrap();
@@ -581,7 +749,8 @@ pub mod exchange {
         token::mint_to(cpi_ctx_mint, amount_for.into())?;
         Ok(())
     }
-    #[access_control(halted(&ctx.accounts.state))]
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
     pub fn burn(ctx: Context<BurnToken>, amount: u64) -> Result<()> {
         msg!("Synthetify: BURN");
 
@@ -721,32 +890,17 @@ pub mod exchange {
             return Err(ErrorCode::InvalidSigner.into());
         }
 
-        // Time given user to adjust collateral ratio passed
-        if exchange_account.liquidation_deadline > slot {
-            return Err(ErrorCode::LiquidationDeadline.into());
-        }
-
         let total_debt =
             calculate_debt_with_adjustment(state, assets_list, slot, timestamp).unwrap();
-        let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
-        let max_debt = calculate_max_debt_in_usd(exchange_account, assets_list);
-
-        // Check collateral ratio
-        if max_debt.gt(user_debt).unwrap() {
-            return Err(ErrorCode::InvalidLiquidation.into());
-        }
-        // Cannot payback more than liquidation_rate of user debt
-        let max_repay = user_debt.mul(state.liquidation_rate).to_usd().to_u64();
-
-        let amount: u64 = match amount {
-            u64::MAX => max_repay,
-            _ => amount,
-        };
-
-        if amount.gt(&max_repay) {
-            return Err(ErrorCode::InvalidLiquidation.into());
-        }
-        let (assets, collaterals, _) = assets_list.split_borrow();
+        let liquidation_amount_preflight = check_liquidation(
+            exchange_account,
+            assets_list,
+            state,
+            total_debt,
+            amount,
+            slot,
+        )?;
+        let (assets, collaterals, synthetics) = assets_list.split_borrow();
 
         // finding collateral also validate reserve_account.mint, liquidation_fund.mint, liquidator_collateral_account.mint
         let liquidated_collateral = match collaterals.iter_mut().find(|x| {
@@ -757,117 +911,31 @@ pub mod exchange {
             Some(v) => v,
             None => return Err(ErrorCode::NoAssetFound.into()),
         };
+        if !liquidation_fund
+            .to_account_info()
+            .key
+            .eq(&liquidated_collateral.liquidation_fund)
+        {
+            return Err(ErrorCode::ExchangeLiquidationAccount.into());
+        }
 
         let liquidated_asset = &assets[liquidated_collateral.asset_index as usize];
-        let liquidation_amount_preflight = Decimal {
-            val: amount.into(),
-            scale: XUSD_SCALE,
-        };
-
-        let seized_collateral_in_usd_preflight = liquidation_amount_preflight
-            .mul_up(
-                state
-                    .penalty_to_liquidator
-                    .add(state.penalty_to_exchange)
-                    .unwrap(),
-            )
-            .add(liquidation_amount_preflight)
-            .unwrap();
-
-        let seized_collateral_in_token_preflight = usd_to_token_amount(
+        let seized = seize_collateral(
+            state,
+            exchange_account,
             liquidated_asset,
-            seized_collateral_in_usd_preflight,
-            liquidated_collateral.reserve_balance.scale,
-        );
-        let exchange_account_collateral_index =
-            match exchange_account.collaterals.iter().position(|x| {
-                x.collateral_address
-                    .eq(&liquidated_collateral.collateral_address)
-            }) {
-                Some(v) => v,
-                None => return Err(ErrorCode::NoAssetFound.into()),
-            };
-
-        let preflight_check = seized_collateral_in_token_preflight.val
-            <= exchange_account.collaterals[exchange_account_collateral_index]
-                .amount
-                .into();
-
-        let (seized_collateral_in_token, liquidation_amount) = match preflight_check {
-            true => (
-                seized_collateral_in_token_preflight,
-                liquidation_amount_preflight,
-            ),
-            false => {
-                let seized_collateral_in_token = Decimal {
-                    val: exchange_account.collaterals[exchange_account_collateral_index]
-                        .amount
-                        .into(),
-                    scale: liquidated_collateral.reserve_balance.scale,
-                };
-                (
-                    seized_collateral_in_token,
-                    calculate_value_in_usd(liquidated_asset.price, seized_collateral_in_token),
-                )
-            }
-        };
-        // Rounding down - debt is burned in favor of the system
-
-        let burned_debt_shares = amount_to_shares_by_rounding_down(
-            state.debt_shares,
-            total_debt.to_u64(),
-            liquidation_amount.to_u64(),
-        );
-        state.debt_shares = state.debt_shares.checked_sub(burned_debt_shares).unwrap();
-
-        exchange_account.debt_shares = exchange_account
-            .debt_shares
-            .checked_sub(burned_debt_shares)
-            .unwrap();
-
-        let exchange_account_collateral =
-            &mut exchange_account.collaterals[exchange_account_collateral_index];
-
-        exchange_account_collateral.amount = exchange_account_collateral
-            .amount
-            .checked_sub(seized_collateral_in_token.to_u64())
-            .unwrap();
-        liquidated_collateral.reserve_balance = liquidated_collateral
-            .reserve_balance
-            .sub(seized_collateral_in_token)
-            .unwrap();
-
-        let collateral_to_exchange = seized_collateral_in_token
-            .mul(state.penalty_to_exchange)
-            .div_up(
-                Decimal::from_percent(100)
-                    .add(state.penalty_to_liquidator)
-                    .unwrap()
-                    .add(state.penalty_to_exchange)
-                    .unwrap(),
-            );
-
-        let collateral_to_liquidator = seized_collateral_in_token
-            .sub(collateral_to_exchange)
-            .unwrap();
-
-        // Remove staking for liquidation
-        state.staking.next_round.all_points = state.debt_shares;
-        state.staking.current_round.all_points = state
-            .staking
-            .current_round
-            .all_points
-            .checked_sub(exchange_account.user_staking_data.current_round_points)
-            .unwrap();
-        state.staking.finished_round.all_points = state
-            .staking
-            .finished_round
-            .all_points
-            .checked_sub(exchange_account.user_staking_data.finished_round_points)
-            .unwrap();
-        exchange_account.user_staking_data.finished_round_points = 0u64;
-        exchange_account.user_staking_data.current_round_points = 0u64;
-        exchange_account.user_staking_data.next_round_points = exchange_account.debt_shares;
+            liquidated_collateral,
+            liquidation_amount_preflight,
+            slot,
+        )?;
+        let burned_amount = settle_liquidation(
+            state,
+            exchange_account,
+            &mut synthetics[0],
+            total_debt,
+            seized.repaid_amount,
+            seized.insurance_amount,
+        )?;
 
         let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
         let signer_seeds = &[&seeds[..]];
@@ -882,18 +950,9 @@ pub mod exchange {
             let token_program = ctx.accounts.token_program.to_account_info();
             let transfer =
                 CpiContext::new(token_program, liquidator_accounts).with_signer(signer_seeds);
-            token::transfer(transfer, collateral_to_liquidator.to_u64())?;
+            token::transfer(transfer, seized.to_liquidator.to_u64())?;
         }
         {
-            if !ctx
-                .accounts
-                .liquidation_fund
-                .to_account_info()
-                .key
-                .eq(&liquidated_collateral.liquidation_fund)
-            {
-                return Err(ErrorCode::ExchangeLiquidationAccount.into());
-            }
             // transfer collateral to liquidation_account
             let exchange_accounts = Transfer {
                 from: ctx.accounts.reserve_account.to_account_info(),
@@ -903,16 +962,10 @@ pub mod exchange {
             let token_program = ctx.accounts.token_program.to_account_info();
             let transfer =
                 CpiContext::new(token_program, exchange_accounts).with_signer(signer_seeds);
-            token::transfer(transfer, collateral_to_exchange.try_into().unwrap())?;
+            token::transfer(transfer, seized.to_exchange.to_u64())?;
         }
         {
             // burn xUSD
-            let new_supply = assets_list.synthetics[0]
-                .supply
-                .sub(liquidation_amount)
-                .unwrap();
-            assets_list.synthetics[0].set_supply_safely(new_supply)?;
-
             let burn_accounts = Burn {
                 mint: ctx.accounts.usd_token.to_account_info(),
                 to: ctx.accounts.liquidator_usd_account.to_account_info(),
@@ -920,102 +973,183 @@ pub mod exchange {
             };
             let token_program = ctx.accounts.token_program.to_account_info();
             let burn = CpiContext::new(token_program, burn_accounts).with_signer(signer_seeds);
-            token::burn(burn, liquidation_amount.to_u64())?;
-        }
-        // Clean user collateral if empty
-        if exchange_account.collaterals[exchange_account_collateral_index].amount == 0 {
-            exchange_account.remove(exchange_account_collateral_index);
+            token::burn(burn, burned_amount.to_u64())?;
         }
 
         Ok(())
     }
     #[access_control(halted(&ctx.accounts.state))]
-    pub fn check_account_collateralization(ctx: Context<CheckCollateralization>) -> Result<()> {
-        msg!("Synthetify: CHECK ACCOUNT COLLATERALIZATION");
+    pub fn flash_liquidate<'info>(
+        ctx: Context<'_, '_, '_, 'info, FlashLiquidate<'info>>,
+        amount: u64,
+        callback_data: Vec<u8>,
+    ) -> Result<()> {
+        msg!("Synthetify: FLASH LIQUIDATE");
 
         let slot = Clock::get()?.slot;
         let timestamp = Clock::get()?.unix_timestamp;
-        let mut state = &mut ctx.accounts.state.load_mut()?;
 
-        // Adjust staking round
-        adjust_staking_rounds(&mut state, slot);
+        // Exchange cannot be used as callback
+        require!(
+            !ctx.accounts.callback_program.key.eq(ctx.program_id),
+            InvalidAccount
+        );
 
-        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
-        // adjust current staking points for exchange account
-        adjust_staking_account(exchange_account, &state.staking);
+        // Seized collateral is transferred before xUSD is collected
+        let (burned_amount, nonce) = {
+            let mut state = &mut ctx.accounts.state.load_mut()?;
 
-        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+            // Adjust staking round
+            adjust_staking_rounds(&mut state, slot);
 
-        let total_debt =
-            calculate_debt_with_adjustment(state, assets_list.borrow_mut(), slot, timestamp)
-                .unwrap();
-        let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
-        let max_debt = calculate_max_debt_in_usd(exchange_account, assets_list);
+            let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+            // adjust current staking points for exchange account
+            adjust_staking_account(exchange_account, &state.staking);
 
-        // If account is undercollateralized set liquidation_deadline
-        // After liquidation_deadline slot account can be liquidated
-        if max_debt.gt(user_debt)? {
-            exchange_account.liquidation_deadline = u64::MAX;
-        } else {
-            if exchange_account.liquidation_deadline == u64::MAX {
-                exchange_account.liquidation_deadline =
-                    slot.checked_add(state.liquidation_buffer.into()).unwrap();
-            }
-        }
+            let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+            let signer = ctx.accounts.signer.key;
+            let reserve_account = &ctx.accounts.reserve_account;
+            let liquidation_fund = &ctx.accounts.liquidation_fund;
+            let liquidator_collateral_account = &ctx.accounts.liquidator_collateral_account;
+            let liquidator_usd_account = &ctx.accounts.liquidator_usd_account;
 
-        Ok(())
-    }
+            // Signer need to be owner of source amount
+            if !signer.eq(&liquidator_usd_account.owner) {
+                return Err(ErrorCode::InvalidSigner.into());
+            }
 
-    #[access_control(halted(&ctx.accounts.state))]
-    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
-        msg!("Synthetify: CLAIM REWARDS");
+            let total_debt =
+                calculate_debt_with_adjustment(state, assets_list, slot, timestamp).unwrap();
+            let liquidation_amount_preflight = check_liquidation(
+                exchange_account,
+                assets_list,
+                state,
+                total_debt,
+                amount,
+                slot,
+            )?;
+            let (assets, collaterals, synthetics) = assets_list.split_borrow();
+
+            // finding collateral also validate reserve_account.mint, liquidation_fund.mint, liquidator_collateral_account.mint
+            let liquidated_collateral = match collaterals.iter_mut().find(|x| {
+                x.collateral_address.eq(&reserve_account.mint)
+                    && x.collateral_address.eq(&liquidation_fund.mint)
+                    && x.collateral_address.eq(&liquidator_collateral_account.mint)
+            }) {
+                Some(v) => v,
+                None => return Err(ErrorCode::NoAssetFound.into()),
+            };
+            if !liquidation_fund
+                .to_account_info()
+                .key
+                .eq(&liquidated_collateral.liquidation_fund)
+            {
+                return Err(ErrorCode::ExchangeLiquidationAccount.into());
+            }
 
-        let slot = Clock::get()?.slot;
-        let mut state = &mut ctx.accounts.state.load_mut()?;
+            let liquidated_asset = &assets[liquidated_collateral.asset_index as usize];
+            let seized = seize_collateral(
+                state,
+                exchange_account,
+                liquidated_asset,
+                liquidated_collateral,
+                liquidation_amount_preflight,
+                slot,
+            )?;
+            // xUSD is burned after callback
+            let burned_amount = settle_liquidation(
+                state,
+                exchange_account,
+                &mut synthetics[0],
+                total_debt,
+                seized.repaid_amount,
+                seized.insurance_amount,
+            )?;
+
+            let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+            let signer_seeds = &[&seeds[..]];
 
-        // Adjust staking round
-        adjust_staking_rounds(&mut state, slot);
-        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+            {
+                // transfer collateral to liquidator
+                let liquidator_accounts = Transfer {
+                    from: ctx.accounts.reserve_account.to_account_info(),
+                    to: ctx.accounts.liquidator_collateral_account.to_account_info(),
+                    authority: ctx.accounts.exchange_authority.to_account_info(),
+                };
+                let token_program = ctx.accounts.token_program.to_account_info();
+                let transfer =
+                    CpiContext::new(token_program, liquidator_accounts).with_signer(signer_seeds);
+                token::transfer(transfer, seized.to_liquidator.to_u64())?;
+            }
+            {
+                // transfer collateral to liquidation_account
+                let exchange_accounts = Transfer {
+                    from: ctx.accounts.reserve_account.to_account_info(),
+                    to: ctx.accounts.liquidation_fund.to_account_info(),
+                    authority: ctx.accounts.exchange_authority.to_account_info(),
+                };
+                let token_program = ctx.accounts.token_program.to_account_info();
+                let transfer =
+                    CpiContext::new(token_program, exchange_accounts).with_signer(signer_seeds);
+                token::transfer(transfer, seized.to_exchange.to_u64())?;
+            }
 
-        // adjust current staking points for exchange account
-        adjust_staking_account(exchange_account, &state.staking);
+            (burned_amount, state.nonce)
+        };
 
-        if state
-            .staking
-            .finished_round
-            .amount
-            .gt(Decimal::from_sny(0))?
+        // Liquidator swaps seized collateral for xUSD in callback program
         {
-            let reward_amount: u64 = state
-                .staking
-                .finished_round
-                .amount
-                .val
-                .checked_mul(
-                    exchange_account
-                        .user_staking_data
-                        .finished_round_points
-                        .into(),
-                )
-                .unwrap()
-                .checked_div(state.staking.finished_round.all_points.into())
-                .unwrap() as u64;
+            let callback_program = &ctx.accounts.callback_program;
+            let accounts = ctx
+                .remaining_accounts
+                .iter()
+                .map(|x| match x.is_writable {
+                    true => AccountMeta::new(*x.key, x.is_signer),
+                    false => AccountMeta::new_readonly(*x.key, x.is_signer),
+                })
+                .collect();
+            let callback = Instruction {
+                program_id: *callback_program.key,
+                accounts,
+                data: callback_data,
+            };
+            let mut account_infos = ctx.remaining_accounts.to_vec();
+            account_infos.push(callback_program.clone());
+            invoke(&callback, &account_infos)?;
+        }
 
-            exchange_account.user_staking_data.amount_to_claim = exchange_account
-                .user_staking_data
-                .amount_to_claim
-                .add(Decimal::from_sny(reward_amount.into()))?;
+        // xUSD has to be repaid within the same instruction
+        let usd_balance = ctx.accounts.liquidator_usd_account.reload()?.amount;
+        require!(
+            usd_balance >= burned_amount.to_u64(),
+            FlashLiquidationNotRepaid
+        );
 
-            exchange_account.user_staking_data.finished_round_points = 0;
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[nonce]];
+        let signer_seeds = &[&seeds[..]];
+        {
+            // burn xUSD
+            let burn_accounts = Burn {
+                mint: ctx.accounts.usd_token.to_account_info(),
+                to: ctx.accounts.liquidator_usd_account.to_account_info(),
+                authority: ctx.accounts.exchange_authority.to_account_info(),
+            };
+            let token_program = ctx.accounts.token_program.to_account_info();
+            let burn = CpiContext::new(token_program, burn_accounts).with_signer(signer_seeds);
+            token::burn(burn, burned_amount.to_u64())?;
         }
 
         Ok(())
     }
     #[access_control(halted(&ctx.accounts.state))]
-    pub fn withdraw_rewards(ctx: Context<WithdrawRewards>) -> Result<()> {
-        msg!("Synthetify: WITHDRAW REWARDS");
+    pub fn liquidate_multi<'info>(
+        ctx: Context<'_, '_, '_, 'info, LiquidateMulti<'info>>,
+        amount: u64,
+    ) -> Result<()> {
+        msg!("Synthetify: LIQUIDATE MULTI");
 
         let slot = Clock::get()?.slot;
+        let timestamp = Clock::get()?.unix_timestamp;
         let mut state = &mut ctx.accounts.state.load_mut()?;
 
         // Adjust staking round
@@ -1025,8 +1159,357 @@ pub mod exchange {
         // adjust current staking points for exchange account
         adjust_staking_account(exchange_account, &state.staking);
 
-        if exchange_account.user_staking_data.amount_to_claim.val == 0u128 {
-            return Err(ErrorCode::NoRewards.into());
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+        let signer = ctx.accounts.signer.key;
+        let liquidator_usd_account = &ctx.accounts.liquidator_usd_account;
+
+        // Signer need to be owner of source amount
+        if !signer.eq(&liquidator_usd_account.owner) {
+            return Err(ErrorCode::InvalidSigner.into());
+        }
+
+        // Collaterals are passed in priority order as
+        // (reserve_account, liquidation_fund, liquidator_collateral_account)
+        let collateral_accounts = ctx.remaining_accounts;
+        require!(
+            !collateral_accounts.is_empty() && collateral_accounts.len() % 3 == 0,
+            InvalidAccount
+        );
+
+        let total_debt =
+            calculate_debt_with_adjustment(state, assets_list, slot, timestamp).unwrap();
+        let mut remaining_amount = check_liquidation(
+            exchange_account,
+            assets_list,
+            state,
+            total_debt,
+            amount,
+            slot,
+        )?;
+
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+        let signer_seeds = &[&seeds[..]];
+
+        let mut liquidation_amount = Decimal::from_usd(0);
+        let mut insurance_amount = Decimal::from_usd(0);
+        let (assets, collaterals, synthetics) = assets_list.split_borrow();
+
+        for accounts in collateral_accounts.chunks(3) {
+            if remaining_amount.val == 0 {
+                break;
+            }
+            let reserve_account = &accounts[0];
+            let liquidation_fund = &accounts[1];
+            let liquidator_collateral_account = &accounts[2];
+
+            // finding collateral also validate reserve_account
+            let liquidated_collateral = match collaterals
+                .iter_mut()
+                .find(|x| x.reserve_address.eq(reserve_account.key))
+            {
+                Some(v) => v,
+                None => return Err(ErrorCode::NoAssetFound.into()),
+            };
+            if !liquidation_fund
+                .key
+                .eq(&liquidated_collateral.liquidation_fund)
+            {
+                return Err(ErrorCode::ExchangeLiquidationAccount.into());
+            }
+            let liquidated_asset = &assets[liquidated_collateral.asset_index as usize];
+
+            let seized = seize_collateral(
+                state,
+                exchange_account,
+                liquidated_asset,
+                liquidated_collateral,
+                remaining_amount,
+                slot,
+            )?;
+            liquidation_amount = liquidation_amount.add(seized.repaid_amount).unwrap();
+            insurance_amount = insurance_amount.add(seized.insurance_amount).unwrap();
+            remaining_amount = match remaining_amount.gt(seized.repaid_amount).unwrap() {
+                true => remaining_amount.sub(seized.repaid_amount).unwrap(),
+                false => Decimal::from_usd(0),
+            };
+
+            {
+                // transfer collateral to liquidator
+                let liquidator_accounts = Transfer {
+                    from: reserve_account.clone(),
+                    to: liquidator_collateral_account.clone(),
+                    authority: ctx.accounts.exchange_authority.to_account_info(),
+                };
+                let token_program = ctx.accounts.token_program.to_account_info();
+                let transfer =
+                    CpiContext::new(token_program, liquidator_accounts).with_signer(signer_seeds);
+                token::transfer(transfer, seized.to_liquidator.to_u64())?;
+            }
+            {
+                // transfer collateral to liquidation_account
+                let exchange_accounts = Transfer {
+                    from: reserve_account.clone(),
+                    to: liquidation_fund.clone(),
+                    authority: ctx.accounts.exchange_authority.to_account_info(),
+                };
+                let token_program = ctx.accounts.token_program.to_account_info();
+                let transfer =
+                    CpiContext::new(token_program, exchange_accounts).with_signer(signer_seeds);
+                token::transfer(transfer, seized.to_exchange.to_u64())?;
+            }
+        }
+
+        let burned_amount = settle_liquidation(
+            state,
+            exchange_account,
+            &mut synthetics[0],
+            total_debt,
+            liquidation_amount,
+            insurance_amount,
+        )?;
+        {
+            // burn xUSD
+            let burn_accounts = Burn {
+                mint: ctx.accounts.usd_token.to_account_info(),
+                to: ctx.accounts.liquidator_usd_account.to_account_info(),
+                authority: ctx.accounts.exchange_authority.to_account_info(),
+            };
+            let token_program = ctx.accounts.token_program.to_account_info();
+            let burn = CpiContext::new(token_program, burn_accounts).with_signer(signer_seeds);
+            token::burn(burn, burned_amount.to_u64())?;
+        }
+
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state))]
+    pub fn check_account_collateralization(ctx: Context<CheckCollateralization>) -> Result<()> {
+        msg!("Synthetify: CHECK ACCOUNT COLLATERALIZATION");
+
+        let slot = Clock::get()?.slot;
+        let timestamp = Clock::get()?.unix_timestamp;
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+
+        update_account_collateralization(state, exchange_account, assets_list, slot, timestamp)?;
+
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state))]
+    pub fn preview_liquidation(ctx: Context<PreviewLiquidation>) -> Result<()> {
+        msg!("Synthetify: PREVIEW LIQUIDATION");
+
+        let slot = Clock::get()?.slot;
+        let state = ctx.accounts.state.load()?;
+        let exchange_account = ctx.accounts.exchange_account.load()?;
+        let assets_list = &ctx.accounts.assets_list.load()?;
+
+        // Read only, debt interest not adjusted yet is not included
+        let total_debt = calculate_debt_by_price_source(
+            assets_list,
+            slot,
+            state.max_delay,
+            PriceSource::Spot as u8,
+        )?;
+        let user_debt =
+            calculate_user_debt_in_usd(&exchange_account, total_debt, state.debt_shares);
+
+        let preview = calculate_liquidation_preview(
+            &exchange_account,
+            assets_list,
+            user_debt,
+            state.health_factor,
+        )?;
+        // set_return_data is not available in solana-program 1.7.8,
+        // preview is returned as event read from simulated transaction logs
+        emit!(preview);
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state))]
+    pub fn mark_account_for_liquidation(ctx: Context<MarkAccountForLiquidation>) -> Result<()> {
+        msg!("Synthetify: MARK ACCOUNT FOR LIQUIDATION");
+
+        let slot = Clock::get()?.slot;
+        let timestamp = Clock::get()?.unix_timestamp;
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+
+        let marked = update_account_collateralization(
+            state,
+            exchange_account,
+            assets_list,
+            slot,
+            timestamp,
+        )?;
+
+        // Bounty is paid only to first keeper marking account
+        // Owner can mark own account from another wallet, so cooldown is the only guard
+        // against draining fund by repeated marking of same account
+        let bounty = state.liquidation_bounty;
+        let cooldown_passed = slot
+            >= exchange_account
+                .liquidation_bounty_slot
+                .checked_add(state.liquidation_bounty_cooldown.into())
+                .unwrap();
+        let fund_sufficient = ctx.accounts.liquidation_fund.amount >= bounty.to_u64();
+        if !marked || !cooldown_passed || !fund_sufficient || bounty.val == 0 {
+            return Ok(());
+        }
+        exchange_account.liquidation_bounty_slot = slot;
+
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+        let signer_seeds = &[&seeds[..]];
+        {
+            // Transfer bounty to keeper
+            let keeper_accounts = Transfer {
+                from: ctx.accounts.liquidation_fund.to_account_info(),
+                to: ctx.accounts.keeper_account.to_account_info(),
+                authority: ctx.accounts.exchange_authority.to_account_info(),
+            };
+            let token_program = ctx.accounts.token_program.to_account_info();
+            let transfer =
+                CpiContext::new(token_program, keeper_accounts).with_signer(signer_seeds);
+            token::transfer(transfer, bounty.to_u64())?;
+        }
+
+        Ok(())
+    }
+
+    #[access_control(halted(&ctx.accounts.state))]
+    pub fn settle_bad_debt(ctx: Context<SettleBadDebt>) -> Result<()> {
+        msg!("Synthetify: SETTLE BAD DEBT");
+
+        let slot = Clock::get()?.slot;
+        let timestamp = Clock::get()?.unix_timestamp;
+        let mut state = &mut ctx.accounts.state.load_mut()?;
+
+        // Adjust staking round
+        adjust_staking_rounds(&mut state, slot);
+
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+        // adjust current staking points for exchange account
+        adjust_staking_account(exchange_account, &state.staking);
+
+        // Only accounts with debt and without any collateral left
+        require!(
+            exchange_account.head == 0 && exchange_account.debt_shares > 0,
+            NoBadDebt
+        );
+
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+        let total_debt =
+            calculate_debt_with_adjustment(state, assets_list, slot, timestamp).unwrap();
+        let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
+
+        let uncovered_debt =
+            cover_with_insurance_fund(state, &mut assets_list.synthetics[0], user_debt);
+        socialize_bad_debt(state, exchange_account, uncovered_debt);
+        exchange_account.liquidation_deadline = u64::MAX;
+
+        Ok(())
+    }
+    pub fn migrate_exchange_account(
+        ctx: Context<MigrateExchangeAccount>,
+        bump: u8,
+        _legacy_bump: u8,
+        id: u8,
+    ) -> Result<()> {
+        msg!("Synthetify: MIGRATE EXCHANGE ACCOUNT");
+        let legacy_exchange_account = &ctx.accounts.legacy_exchange_account;
+        let owner = &ctx.accounts.owner;
+        let exchange_account = &mut ctx.accounts.exchange_account.load_init()?;
+        let assets_list = &ctx.accounts.assets_list.load()?;
+        require!(
+            legacy_exchange_account.owner == ctx.program_id,
+            InvalidAccount
+        );
+
+        {
+            // both layouts share discriminator of ExchangeAccount
+            let data = legacy_exchange_account.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == ExchangeAccount::discriminator(),
+                InvalidAccount
+            );
+            migrate_exchange_account_layout(exchange_account, &data[8..], assets_list)?;
+        }
+        require!(exchange_account.owner == *owner.key, InvalidAccount);
+        exchange_account.bump = bump;
+        exchange_account.id = id;
+
+        // Close legacy account and return rent to owner
+        let lamports = legacy_exchange_account.lamports();
+        **owner.try_borrow_mut_lamports()? = owner.lamports().checked_add(lamports).unwrap();
+        **legacy_exchange_account.try_borrow_mut_lamports()? = 0;
+        let mut data = legacy_exchange_account.try_borrow_mut_data()?;
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state) version(&ctx.accounts.exchange_account))]
+    pub fn claim_rewards(ctx: Context<ClaimRewards>) -> Result<()> {
+        msg!("Synthetify: CLAIM REWARDS");
+
+        let slot = Clock::get()?.slot;
+        let mut state = &mut ctx.accounts.state.load_mut()?;
+
+        // Adjust staking round
+        adjust_staking_rounds(&mut state, slot);
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+
+        // adjust current staking points for exchange account
+        adjust_staking_account(exchange_account, &state.staking);
+
+        if state
+            .staking
+            .finished_round
+            .amount
+            .gt(Decimal::from_sny(0))?
+        {
+            let reward_amount: u64 = state
+                .staking
+                .finished_round
+                .amount
+                .val
+                .checked_mul(
+                    exchange_account
+                        .user_staking_data
+                        .finished_round_points
+                        .into(),
+                )
+                .unwrap()
+                .checked_div(state.staking.finished_round.all_points.into())
+                .unwrap() as u64;
+
+            exchange_account.user_staking_data.amount_to_claim = exchange_account
+                .user_staking_data
+                .amount_to_claim
+                .add(Decimal::from_sny(reward_amount.into()))?;
+
+            exchange_account.user_staking_data.finished_round_points = 0;
+        }
+
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state)
+    version(&ctx.accounts.exchange_account))]
+    pub fn withdraw_rewards(ctx: Context<WithdrawRewards>) -> Result<()> {
+        msg!("Synthetify: WITHDRAW REWARDS");
+
+        let slot = Clock::get()?.slot;
+        let mut state = &mut ctx.accounts.state.load_mut()?;
+
+        // Adjust staking round
+        adjust_staking_rounds(&mut state, slot);
+
+        let exchange_account = &mut ctx.accounts.exchange_account.load_mut()?;
+        // adjust current staking points for exchange account
+        adjust_staking_account(exchange_account, &state.staking);
+
+        if exchange_account.user_staking_data.amount_to_claim.val == 0u128 {
+            return Err(ErrorCode::NoRewards.into());
         }
         let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
         let signer_seeds = &[&seeds[..]];
@@ -1127,6 +1610,48 @@ pub mod exchange {
         Ok(())
     }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn request_insurance_fund_withdrawal(ctx: Context<AdminAction>, amount: u64) -> Result<()> {
+        msg!("Synthetify:Admin: REQUEST INSURANCE FUND WITHDRAWAL");
+        let slot = Clock::get()?.slot;
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        // Only excess above target can be withdrawn
+        let max_withdrawable = get_insurance_fund_excess(state);
+        // u64::MAX mean all available
+        let actual_amount = match amount {
+            u64::MAX => max_withdrawable,
+            _ => Decimal {
+                val: amount.into(),
+                scale: state.insurance_fund.scale,
+            },
+        };
+        // check valid amount
+        if actual_amount.gt(max_withdrawable)? {
+            return Err(ErrorCode::InsufficientAmountAdminWithdraw.into());
+        }
+        // New request replaces pending one and restarts timelock
+        state.insurance_withdrawal_amount = actual_amount;
+        state.insurance_withdrawal_slot = slot
+            .checked_add(state.insurance_withdrawal_delay.into())
+            .unwrap();
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn withdraw_insurance_fund(ctx: Context<AdminWithdraw>) -> Result<()> {
+        msg!("Synthetify:Admin: WITHDRAW INSURANCE FUND");
+        let slot = Clock::get()?.slot;
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        let amount = withdraw_from_insurance_fund(state, slot)?;
+
+        // Mint xUSD to admin
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+        let signer = &[&seeds[..]];
+        let mint_cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
+        token::mint_to(mint_cpi_ctx, amount)?;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
     pub fn withdraw_accumulated_debt_interest(
         ctx: Context<WithdrawAccumulatedDebtInterest>,
         amount: u64,
@@ -1207,6 +1732,102 @@ pub mod exchange {
         Ok(())
     }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn migrate_state(ctx: Context<AdminAction>) -> Result<()> {
+        msg!("Synthetify:Admin: MIGRATE STATE");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        migrate_state_layout(state);
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn migrate_assets_list(ctx: Context<MigrateAssetsList>) -> Result<()> {
+        msg!("Synthetify:Admin: MIGRATE ASSETS LIST");
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let legacy_assets_list = &ctx.accounts.legacy_assets_list;
+        let admin = &ctx.accounts.admin;
+        let assets_list = &mut ctx.accounts.assets_list.load_init()?;
+        require!(legacy_assets_list.owner == ctx.program_id, InvalidAccount);
+
+        {
+            // both layouts share discriminator of AssetsList
+            let data = legacy_assets_list.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == AssetsList::discriminator(),
+                InvalidAccount
+            );
+            migrate_assets_list_layout(assets_list, &data[8..])?;
+        }
+        state.assets_list = *ctx.accounts.assets_list.to_account_info().key;
+
+        // Close legacy assets list and return rent to admin
+        let lamports = legacy_assets_list.lamports();
+        **admin.try_borrow_mut_lamports()? = admin.lamports().checked_add(lamports).unwrap();
+        **legacy_assets_list.try_borrow_mut_lamports()? = 0;
+        // account without lamports is purged after transaction, clearing discriminator is enough
+        let mut data = legacy_assets_list.try_borrow_mut_data()?;
+        for byte in data[..8].iter_mut() {
+            *byte = 0;
+        }
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_liquidation_bounty(
+        ctx: Context<AdminAction>,
+        liquidation_bounty: Decimal,
+        liquidation_bounty_cooldown: u32,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET LIQUIDATION BOUNTY");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        require!(
+            liquidation_bounty.scale == state.liquidation_bounty.scale,
+            DifferentScale
+        );
+        // Bounty is capped and cooldown is required to limit draining of liquidation fund
+        let max_bounty = Decimal::from_integer(MAX_LIQUIDATION_BOUNTY).to_sny();
+        let in_range = liquidation_bounty.lte(max_bounty)?;
+        require!(
+            in_range && liquidation_bounty_cooldown > 0,
+            ParameterOutOfRange
+        );
+
+        state.liquidation_bounty = liquidation_bounty;
+        state.liquidation_bounty_cooldown = liquidation_bounty_cooldown;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_liquidation_critical_ratio(
+        ctx: Context<AdminAction>,
+        liquidation_critical_ratio: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET LIQUIDATION CRITICAL RATIO");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        // liquidation_critical_ratio should be less or equals 100%
+        let same_scale = liquidation_critical_ratio.scale == state.liquidation_critical_ratio.scale;
+        let in_range = liquidation_critical_ratio.lte(Decimal::from_percent(100))?;
+        require!(same_scale && in_range, ParameterOutOfRange);
+
+        state.liquidation_critical_ratio = liquidation_critical_ratio;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_liquidation_price_source(
+        ctx: Context<AdminAction>,
+        liquidation_price_source: u8,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET LIQUIDATION PRICE SOURCE");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        require!(
+            liquidation_price_source <= PriceSource::Conservative as u8,
+            ParameterOutOfRange
+        );
+
+        state.liquidation_price_source = liquidation_price_source;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
     pub fn set_liquidation_rate(
         ctx: Context<AdminAction>,
         liquidation_rate: Decimal,
@@ -1342,6 +1963,78 @@ pub mod exchange {
         Ok(())
     }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_insurance_ratios(
+        ctx: Context<AdminAction>,
+        swap_tax_ratio: Decimal,
+        penalty_ratio: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET INSURANCE RATIOS");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        // swap_tax_ratio and penalty_ratio must be less or equals 100%
+        let same_scale = swap_tax_ratio.scale == state.insurance_swap_tax_ratio.scale;
+        let in_range = swap_tax_ratio.lte(Decimal::from_percent(100))?;
+        require!(same_scale && in_range, ParameterOutOfRange);
+
+        let same_scale = penalty_ratio.scale == state.insurance_penalty_ratio.scale;
+        let in_range = penalty_ratio.lte(Decimal::from_percent(100))?;
+        require!(same_scale && in_range, ParameterOutOfRange);
+
+        state.insurance_swap_tax_ratio = swap_tax_ratio;
+        state.insurance_penalty_ratio = penalty_ratio;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_insurance_fund_target(
+        ctx: Context<AdminAction>,
+        insurance_fund_target: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET INSURANCE FUND TARGET");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        require!(
+            insurance_fund_target.scale == state.insurance_fund_target.scale,
+            DifferentScale
+        );
+
+        state.insurance_fund_target = insurance_fund_target;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_insurance_withdrawal_delay(
+        ctx: Context<AdminAction>,
+        insurance_withdrawal_delay: u32,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET INSURANCE WITHDRAWAL DELAY");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        state.insurance_withdrawal_delay = insurance_withdrawal_delay;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_liquidation_auction(
+        ctx: Context<AdminAction>,
+        start_discount: Decimal,
+        max_discount: Decimal,
+        auction_length: u32,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET LIQUIDATION AUCTION");
+        let state = &mut ctx.accounts.state.load_mut()?;
+
+        // max_discount must be less or equals 25% and not lower than start_discount
+        let same_scale = start_discount.scale == state.liquidation_auction_start_discount.scale
+            && max_discount.scale == state.liquidation_auction_max_discount.scale;
+        require!(same_scale, ParameterOutOfRange);
+        let in_range =
+            max_discount.lte(Decimal::from_percent(25))? && start_discount.lte(max_discount)?;
+        require!(in_range, ParameterOutOfRange);
+
+        state.liquidation_auction_start_discount = start_discount;
+        state.liquidation_auction_max_discount = max_discount;
+        state.liquidation_auction_length = auction_length;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
     pub fn add_collateral(
         ctx: Context<AddCollateral>,
         reserve_balance: Decimal,
@@ -1377,6 +2070,14 @@ pub mod exchange {
             collateral_ratio,
             reserve_balance,
             max_collateral,
+            liquidation_mode: LiquidationMode::Fixed as u8,
+            liquidation_threshold: collateral_ratio,
+            deprecated: false,
+            deprecation_start: 0,
+            deprecation_length: 0,
+            initial_collateral_ratio: Decimal::from_percent(0),
+            initial_liquidation_threshold: Decimal::from_percent(0),
+            vault_count: 0,
         };
         assets_list.append_collateral(new_collateral);
         Ok(())
@@ -1395,35 +2096,140 @@ pub mod exchange {
             Some(asset) => asset,
             None => return Err(ErrorCode::NoAssetFound.into()),
         };
-        // collateral_ratio must be less or equals 100%
-        let same_scale = collateral.collateral_ratio.scale == collateral_ratio.scale;
-        let in_range = collateral_ratio.lte(Decimal::from_percent(100))?;
-        require!(same_scale && in_range, ParameterOutOfRange);
+        // collateral_ratio must be less or equals 100%
+        let same_scale = collateral.collateral_ratio.scale == collateral_ratio.scale;
+        let in_range = collateral_ratio.lte(Decimal::from_percent(100))?;
+        require!(same_scale && in_range, ParameterOutOfRange);
+
+        // liquidation_threshold cannot be lower than collateral_ratio
+        let liquidation_threshold = get_liquidation_threshold(collateral);
+        collateral.liquidation_threshold = match liquidation_threshold.lt(collateral_ratio)? {
+            true => collateral_ratio,
+            false => liquidation_threshold,
+        };
+        collateral.collateral_ratio = collateral_ratio;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_collateral_liquidation_threshold(
+        ctx: Context<SetCollateralParameter>,
+        liquidation_threshold: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET COLLATERAL LIQUIDATION THRESHOLD");
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+
+        let collateral = match assets_list.collaterals.iter_mut().find(|x| {
+            x.collateral_address == *ctx.accounts.collateral_address.to_account_info().key
+        }) {
+            Some(asset) => asset,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        // liquidation_threshold must be less or equals 100% and not less than collateral_ratio
+        let same_scale = collateral.collateral_ratio.scale == liquidation_threshold.scale;
+        let in_range = liquidation_threshold.lte(Decimal::from_percent(100))?;
+        let not_less_than_collateral_ratio =
+            liquidation_threshold.gte(collateral.collateral_ratio)?;
+        require!(
+            same_scale && in_range && not_less_than_collateral_ratio,
+            ParameterOutOfRange
+        );
+
+        collateral.liquidation_threshold = liquidation_threshold;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_max_collateral(
+        ctx: Context<SetMaxCollateral>,
+        max_collateral: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET COLLATERAL RATIO");
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+
+        let collateral = match assets_list.collaterals.iter_mut().find(|x| {
+            x.collateral_address == *ctx.accounts.collateral_address.to_account_info().key
+        }) {
+            Some(asset) => asset,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+
+        require!(
+            collateral.max_collateral.scale == max_collateral.scale,
+            DifferentScale
+        );
+
+        collateral.max_collateral = max_collateral;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_collateral_liquidation_mode(
+        ctx: Context<SetCollateralParameter>,
+        liquidation_mode: u8,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET COLLATERAL LIQUIDATION MODE");
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+
+        let collateral = match assets_list.collaterals.iter_mut().find(|x| {
+            x.collateral_address == *ctx.accounts.collateral_address.to_account_info().key
+        }) {
+            Some(asset) => asset,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        require!(
+            liquidation_mode <= LiquidationMode::Auction as u8,
+            ParameterOutOfRange
+        );
 
-        collateral.collateral_ratio = collateral_ratio;
+        collateral.liquidation_mode = liquidation_mode;
         Ok(())
     }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
-    pub fn set_max_collateral(
-        ctx: Context<SetMaxCollateral>,
-        max_collateral: Decimal,
+    pub fn deprecate_collateral(
+        ctx: Context<SetCollateralParameter>,
+        deprecation_length: u32,
     ) -> Result<()> {
-        msg!("Synthetify:Admin: SET COLLATERAL RATIO");
+        msg!("Synthetify:Admin: DEPRECATE COLLATERAL");
         let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+        let slot = Clock::get()?.slot;
 
-        let collateral = match assets_list.collaterals.iter_mut().find(|x| {
+        let collateral_index = match assets_list.collaterals.iter_mut().position(|x| {
             x.collateral_address == *ctx.accounts.collateral_address.to_account_info().key
         }) {
-            Some(asset) => asset,
+            Some(index) => index,
             None => return Err(ErrorCode::NoAssetFound.into()),
         };
+        // SNY collateral cannot be deprecated
+        require!(collateral_index > 0, CollateralNotRemovable);
+        let collateral = &mut assets_list.collaterals[collateral_index];
+        require!(!collateral.deprecated, CollateralDeprecated);
 
+        collateral.initial_collateral_ratio = collateral.collateral_ratio;
+        collateral.initial_liquidation_threshold = get_liquidation_threshold(collateral);
+        collateral.deprecated = true;
+        collateral.deprecation_start = slot;
+        collateral.deprecation_length = deprecation_length;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn remove_collateral(ctx: Context<SetCollateralParameter>) -> Result<()> {
+        msg!("Synthetify:Admin: REMOVE COLLATERAL");
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+
+        let collateral_index = match assets_list.collaterals.iter_mut().position(|x| {
+            x.collateral_address == *ctx.accounts.collateral_address.to_account_info().key
+        }) {
+            Some(index) => index,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        // only deprecated collateral without any deposits and vaults can be removed
+        let collateral = &assets_list.collaterals[collateral_index];
         require!(
-            collateral.max_collateral.scale == max_collateral.scale,
-            DifferentScale
+            collateral.deprecated
+                && collateral.reserve_balance.val == 0
+                && collateral.vault_count == 0,
+            CollateralNotRemovable
         );
 
-        collateral.max_collateral = max_collateral;
+        assets_list.remove_collateral(collateral_index)?;
         Ok(())
     }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
@@ -1488,6 +2294,49 @@ pub mod exchange {
         assets_list.append_synthetic(new_synthetic);
         Ok(())
     }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn remove_synthetic(ctx: Context<RemoveSynthetic>) -> Result<()> {
+        msg!("Synthetify:Admin: REMOVE SYNTHETIC");
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+
+        let synthetic_address = ctx.accounts.synthetic_address.to_account_info().key;
+        let synthetic_index = match assets_list
+            .synthetics
+            .iter_mut()
+            .position(|x| x.asset_address == *synthetic_address)
+        {
+            Some(index) => index,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        // only synthetic without any supply can be removed
+        let synthetic = &assets_list.synthetics[synthetic_index];
+        require!(
+            synthetic.supply.val == 0
+                && synthetic.borrowed_supply.val == 0
+                && synthetic.swapline_supply.val == 0,
+            AssetInUse
+        );
+
+        assets_list.remove_synthetic(synthetic_index)?;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn remove_asset(ctx: Context<RemoveAsset>) -> Result<()> {
+        msg!("Synthetify:Admin: REMOVE ASSET");
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+
+        let head_assets = assets_list.head_assets as usize;
+        let asset_index = match assets_list.assets[..head_assets]
+            .iter()
+            .position(|x| x.feed_address == *ctx.accounts.feed_address.key)
+        {
+            Some(index) => index,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+
+        assets_list.remove_asset(asset_index)?;
+        Ok(())
+    }
     pub fn settle_synthetic(ctx: Context<SettleSynthetic>, bump: u8) -> Result<()> {
         let slot = Clock::get()?.slot;
 
@@ -1568,6 +2417,14 @@ pub mod exchange {
         Ok(())
     }
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn close_settlement(ctx: Context<CloseSettlement>) -> Result<()> {
+        msg!("Synthetify:Admin: CLOSE SETTLEMENT");
+
+        // every settled token has to be swapped before closing
+        require!(ctx.accounts.settlement_reserve.amount == 0, AccountNotEmpty);
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
     pub fn create_swapline(ctx: Context<CreateSwapline>, bump: u8, limit: u64) -> Result<()> {
         msg!("Synthetify: CREATE SWAPLINE");
 
@@ -1778,6 +2635,7 @@ pub mod exchange {
     pub fn create_vault(
         ctx: Context<CreateVault>,
         bump: u8,
+        vault_type: u8,
         debt_interest_rate: Decimal,
         collateral_ratio: Decimal,
         max_borrow: Decimal,
@@ -1788,26 +2646,28 @@ pub mod exchange {
     ) -> Result<()> {
         msg!("Synthetify: CREATE VAULT");
 
+        let state = ctx.accounts.state.load()?;
         let mut vault = ctx.accounts.vault.load_init()?;
-        let assets_list = ctx.accounts.assets_list.load()?;
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
         let timestamp = Clock::get()?.unix_timestamp;
+        let (_, collaterals, synthetics) = assets_list.split_borrow();
 
-        let synthetic = assets_list
-            .synthetics
+        let synthetic = synthetics
             .iter()
             .find(|x| {
                 x.asset_address
                     .eq(ctx.accounts.synthetic.to_account_info().key)
             })
             .unwrap();
-        let collateral = assets_list.collaterals.iter().find(|x| {
+        let collateral = match collaterals.iter_mut().find(|x| {
             x.collateral_address
                 .eq(ctx.accounts.collateral.to_account_info().key)
-        });
-        if collateral == None {
-            return Err(ErrorCode::NoAssetFound.into());
-        }
-        let collateral = collateral.unwrap();
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        // Collateral cannot be removed while used by vault
+        collateral.vault_count = collateral.vault_count.checked_add(1).unwrap();
 
         require!(
             collateral_ratio.lte(Decimal::from_percent(100))?,
@@ -1816,6 +2676,7 @@ pub mod exchange {
         // Init vault struct
         {
             vault.bump = bump;
+            vault.vault_type = vault_type;
             vault.halted = false;
             vault.synthetic = *ctx.accounts.synthetic.to_account_info().key;
             vault.collateral = *ctx.accounts.collateral.to_account_info().key;
@@ -1833,8 +2694,64 @@ pub mod exchange {
             vault.liquidation_ratio = liquidation_ratio;
             vault.liquidation_penalty_liquidator = penalty_to_liquidator;
             vault.liquidation_penalty_exchange = penalty_to_exchange;
+            // Oracle checks same as for exchange until set by admin
+            vault.oracle_settings = OracleSettings {
+                max_delay: state.max_delay,
+                max_price_deviation: Decimal::from_percent(0),
+                require_trading: false,
+            };
+            vault.liquidation_price_source = PriceSource::Spot as u8;
+            // Basket collaterals are added by admin later
+            vault.basket_head = 0;
+        }
+
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn migrate_vault(ctx: Context<MigrateVault>, bump: u8, _legacy_bump: u8) -> Result<()> {
+        msg!("Synthetify:Admin: MIGRATE VAULT");
+        let legacy_vault = &ctx.accounts.legacy_vault;
+        let admin = &ctx.accounts.admin;
+        let state = &ctx.accounts.state.load()?;
+        let vault = &mut ctx.accounts.vault.load_init()?;
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+        require!(legacy_vault.owner == ctx.program_id, InvalidAccount);
+
+        {
+            // both layouts share discriminator of Vault
+            let data = legacy_vault.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == Vault::discriminator(),
+                InvalidAccount
+            );
+            migrate_vault_layout(vault, &data[8..], state)?;
         }
+        require!(
+            vault.synthetic == *ctx.accounts.synthetic.to_account_info().key
+                && vault.collateral == *ctx.accounts.collateral.to_account_info().key,
+            InvalidAccount
+        );
+        vault.bump = bump;
 
+        // Legacy vaults were not counted by collateral
+        let collateral = match assets_list
+            .collaterals
+            .iter_mut()
+            .find(|x| x.collateral_address.eq(&vault.collateral))
+        {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        collateral.vault_count = collateral.vault_count.checked_add(1).unwrap();
+
+        // Close legacy vault and return rent to admin
+        let lamports = legacy_vault.lamports();
+        **admin.try_borrow_mut_lamports()? = admin.lamports().checked_add(lamports).unwrap();
+        **legacy_vault.try_borrow_mut_lamports()? = 0;
+        let mut data = legacy_vault.try_borrow_mut_data()?;
+        for byte in data.iter_mut() {
+            *byte = 0;
+        }
         Ok(())
     }
     #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
@@ -1865,8 +2782,82 @@ pub mod exchange {
             vault_entry.last_accumulated_interest_rate = vault.accumulated_interest_rate;
             vault_entry.synthetic_amount = Decimal::new(0, synthetic.max_supply.scale);
             vault_entry.collateral_amount = Decimal::new(0, collateral.reserve_balance.scale);
+            for (i, basket_collateral) in vault.basket[..vault.basket_head as usize]
+                .iter()
+                .enumerate()
+            {
+                vault_entry.basket_amounts[i] =
+                    Decimal::new(0, basket_collateral.collateral_amount.scale);
+            }
+        }
+
+        Ok(())
+    }
+    pub fn migrate_vault_entry(
+        ctx: Context<MigrateVaultEntry>,
+        bump: u8,
+        _legacy_bump: u8,
+        _legacy_vault_bump: u8,
+    ) -> Result<()> {
+        msg!("Synthetify: MIGRATE VAULT ENTRY");
+        let legacy_vault_entry = &ctx.accounts.legacy_vault_entry;
+        let owner = &ctx.accounts.owner;
+        let vault = &ctx.accounts.vault.load()?;
+        let vault_entry = &mut ctx.accounts.vault_entry.load_init()?;
+        require!(legacy_vault_entry.owner == ctx.program_id, InvalidAccount);
+
+        {
+            // both layouts share discriminator of VaultEntry
+            let data = legacy_vault_entry.try_borrow_data()?;
+            require!(
+                data.len() >= 8 && data[..8] == VaultEntry::discriminator(),
+                InvalidAccount
+            );
+            migrate_vault_entry_layout(vault_entry, &data[8..], ctx.accounts.legacy_vault.key)?;
+        }
+        require!(vault_entry.owner == *owner.key, InvalidAccount);
+        vault_entry.bump = bump;
+        vault_entry.vault = *ctx.accounts.vault.to_account_info().key;
+        // Basket could be extended since vault migration
+        for (i, basket_collateral) in vault.basket[..vault.basket_head as usize]
+            .iter()
+            .enumerate()
+        {
+            vault_entry.basket_amounts[i] =
+                Decimal::new(0, basket_collateral.collateral_amount.scale);
+        }
+
+        // Close legacy entry and return rent to owner
+        let lamports = legacy_vault_entry.lamports();
+        **owner.try_borrow_mut_lamports()? = owner.lamports().checked_add(lamports).unwrap();
+        **legacy_vault_entry.try_borrow_mut_lamports()? = 0;
+        let mut data = legacy_vault_entry.try_borrow_mut_data()?;
+        for byte in data.iter_mut() {
+            *byte = 0;
         }
+        Ok(())
+    }
+    pub fn close_vault_entry(ctx: Context<CloseVaultEntry>) -> Result<()> {
+        msg!("Synthetify: CLOSE VAULT ENTRY");
+        let vault_entry = ctx.accounts.vault_entry.load()?;
 
+        // only entry without debt and collateral can be closed
+        require!(vault_entry.is_empty(), AccountNotEmpty);
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
+    pub fn transfer_vault_position(ctx: Context<TransferVaultPosition>, bump: u8) -> Result<()> {
+        msg!("Synthetify: TRANSFER VAULT POSITION");
+        let vault_entry = ctx.accounts.vault_entry.load()?;
+        let new_vault_entry = &mut ctx.accounts.new_vault_entry.load_init()?;
+
+        new_vault_entry.bump = bump;
+        new_vault_entry.owner = *ctx.accounts.new_owner.key;
+        new_vault_entry.vault = vault_entry.vault;
+        new_vault_entry.last_accumulated_interest_rate = vault_entry.last_accumulated_interest_rate;
+        new_vault_entry.synthetic_amount = vault_entry.synthetic_amount;
+        new_vault_entry.collateral_amount = vault_entry.collateral_amount;
+        new_vault_entry.basket_amounts = vault_entry.basket_amounts;
         Ok(())
     }
 
@@ -1879,15 +2870,19 @@ pub mod exchange {
         let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
         let vault_entry = &mut ctx.accounts.vault_entry.load_mut()?;
         let vault = &mut ctx.accounts.vault.load_mut()?;
-        let (_, collaterals, synthetics) = assets_list.split_borrow();
+        let (_, _, synthetics) = assets_list.split_borrow();
 
-        let collateral = collaterals
-            .iter()
-            .find(|x| {
-                x.collateral_address
-                    .eq(ctx.accounts.collateral.to_account_info().key)
-            })
-            .unwrap();
+        // Reserve picks which collateral of vault is deposited
+        let index = vault.get_collateral_index(&ctx.accounts.reserve_address.mint)?;
+        let vault_collateral = vault.get_collateral(index);
+        require!(
+            vault_collateral.collateral_reserve.eq(ctx
+                .accounts
+                .reserve_address
+                .to_account_info()
+                .key),
+            InvalidAccount
+        );
 
         let synthetic = synthetics
             .iter_mut()
@@ -1901,10 +2896,18 @@ pub mod exchange {
 
         let amount_decimal = Decimal {
             val: amount.into(),
-            scale: collateral.reserve_balance.scale,
+            scale: vault_collateral.collateral_amount.scale,
         };
-        vault_entry.collateral_amount = vault_entry.collateral_amount.add(amount_decimal)?;
-        vault.collateral_amount = vault.collateral_amount.add(amount_decimal)?;
+        // entry could be created before collateral was added to basket
+        let entry_amount = Decimal::new(
+            vault_entry.get_collateral_amount(index).val,
+            amount_decimal.scale,
+        );
+        vault_entry.set_collateral_amount(index, entry_amount.add(amount_decimal)?);
+        vault.set_collateral_amount(
+            index,
+            vault_collateral.collateral_amount.add(amount_decimal)?,
+        );
 
         // Transfer token
         let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
@@ -1912,6 +2915,13 @@ pub mod exchange {
         let cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
         token::transfer(cpi_ctx, amount)?;
 
+        emit!(CollateralDeposited {
+            benefactor: *ctx.accounts.owner.key,
+            owner: vault_entry.owner,
+            position: *ctx.accounts.vault_entry.to_account_info().key,
+            collateral_address: vault_collateral.collateral,
+            amount,
+        });
         Ok(())
     }
     #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
@@ -1934,61 +2944,380 @@ pub mod exchange {
             })
             .unwrap();
 
-        let collateral_position = collaterals
-            .iter()
-            .position(|x| {
-                x.collateral_address
-                    .eq(ctx.accounts.collateral.to_account_info().key)
-            })
-            .unwrap();
-
         let synthetic = &mut synthetics[synthetic_position];
-        let collateral = &mut collaterals[collateral_position];
         let synthetic_asset = assets[synthetic.asset_index as usize];
-        let collateral_asset = assets[collateral.asset_index as usize];
 
         adjust_vault_entry_interest_debt(vault, vault_entry, synthetic, timestamp);
 
-        if (synthetic_asset.last_update as u64) < slot - state.max_delay as u64 {
-            return Err(ErrorCode::OutdatedOracle.into());
+        let basket =
+            get_vault_entry_collaterals(vault, vault_entry, &assets[..], &collaterals[..], false);
+        let oracle_settings = vault.oracle_settings;
+        check_vault_oracle(&synthetic_asset, &oracle_settings, slot)?;
+        for (collateral_asset, collateral_amount, _) in basket.iter() {
+            // collateral without deposit does not affect borrow limit
+            if collateral_amount.val != 0 {
+                check_vault_oracle(collateral_asset, &oracle_settings, slot)?;
+            }
         }
-        if (collateral_asset.last_update as u64) < slot - state.max_delay as u64 {
-            return Err(ErrorCode::OutdatedOracle.into());
+
+        let amount_borrow_limit =
+            calculate_vault_borrow_limit(&basket, synthetic_asset, *synthetic);
+
+        let borrow_amount = match amount {
+            u64::MAX => amount_borrow_limit
+                .sub(vault_entry.synthetic_amount)
+                .unwrap(),
+            _ => Decimal::new(amount.into(), synthetic.supply.scale),
+        };
+        let amount_after_borrow = vault_entry.synthetic_amount.add(borrow_amount).unwrap();
+        if amount_borrow_limit.lt(amount_after_borrow)? {
+            return Err(ErrorCode::UserBorrowLimit.into());
+        }
+        vault_entry.increase_supply_cascade(vault, synthetic, borrow_amount)?;
+
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+        let signer = &[&seeds[..]];
+        let mint_cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
+        token::mint_to(mint_cpi_ctx, borrow_amount.to_u64())?;
+
+        Ok(())
+    }
+
+    #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
+    pub fn leverage_vault(
+        ctx: Context<LeverageVault>,
+        target_ratio: Decimal,
+        iterations: u8,
+        min_collateral_out: u64,
+    ) -> Result<()> {
+        msg!("Synthetify: LEVERAGE VAULT");
+        let timestamp = Clock::get()?.unix_timestamp;
+        let slot = Clock::get()?.slot;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+        let vault_entry = &mut ctx.accounts.vault_entry.load_mut()?;
+        let vault = &mut ctx.accounts.vault.load_mut()?;
+        let swapline = &mut ctx.accounts.swapline.load_mut()?;
+        let (assets, collaterals, synthetics) = assets_list.split_borrow();
+
+        require!(!swapline.halted, Halted);
+
+        let synthetic_index = match synthetics.iter().position(|x| {
+            x.asset_address
+                .eq(ctx.accounts.synthetic.to_account_info().key)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        let swapline_synthetic_index = match synthetics.iter().position(|x| {
+            x.asset_address
+                .eq(ctx.accounts.swapline_synthetic.to_account_info().key)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        let collateral = match collaterals.iter().find(|x| {
+            x.collateral_address
+                .eq(ctx.accounts.collateral.to_account_info().key)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        // swapline_synthetic is swapped into collateral through swapline
+        require!(
+            synthetics[swapline_synthetic_index].asset_index == collateral.asset_index,
+            MismatchedTokens
+        );
+        let synthetic_asset = assets[synthetics[synthetic_index].asset_index as usize];
+        let swapline_asset = assets[synthetics[swapline_synthetic_index].asset_index as usize];
+        let swapline_scale = synthetics[swapline_synthetic_index].supply.scale;
+        let swap_route = swapline_synthetic_index != synthetic_index;
+
+        adjust_vault_entry_interest_debt(
+            vault,
+            vault_entry,
+            &mut synthetics[synthetic_index],
+            timestamp,
+        );
+
+        let same_scale = target_ratio.scale == vault.collateral_ratio.scale;
+        require!(
+            same_scale && target_ratio.lt(vault.collateral_ratio)?,
+            ParameterOutOfRange
+        );
+
+        // whole basket counts in borrow limit, same as in borrow_vault
+        let basket =
+            get_vault_entry_collaterals(vault, vault_entry, &assets[..], &collaterals[..], false);
+        let oracle_settings = vault.oracle_settings;
+        check_vault_oracle(&synthetic_asset, &oracle_settings, slot)?;
+        check_vault_oracle(&swapline_asset, &oracle_settings, slot)?;
+        for (collateral_asset, collateral_amount, _) in basket.iter() {
+            if collateral_amount.val != 0 {
+                check_vault_oracle(collateral_asset, &oracle_settings, slot)?;
+            }
         }
 
-        let amount_borrow_limit = calculate_vault_borrow_limit(
-            collateral_asset,
+        let swap_fee = state.fee;
+        let swapline_fee = swapline.fee;
+        let collateral_scale = vault_entry.collateral_amount.scale;
+        // synthetic is swapped into swapline_synthetic at oracle prices if they differ
+        let swap_out = |amount: Decimal| -> Result<Decimal> {
+            let swapline_amount = match swap_route {
+                true => {
+                    calculate_swap_out_amount(
+                        &synthetic_asset,
+                        &swapline_asset,
+                        swapline_scale,
+                        amount,
+                        swap_fee,
+                    )?
+                    .0
+                }
+                false => amount,
+            };
+            let fee = swapline_amount.mul(swapline_fee);
+            Ok(swapline_amount.sub(fee)?.to_scale(collateral_scale))
+        };
+        let (borrow_amount, _) = calculate_vault_leverage(
+            &basket,
             synthetic_asset,
-            *synthetic,
-            vault_entry.collateral_amount,
-            vault.collateral_ratio,
+            synthetics[synthetic_index],
+            vault_entry.synthetic_amount,
+            target_ratio,
+            iterations,
+            &swap_out,
+        )?;
+        // swapping whole amount at once returns not less than simulated steps
+        let collateral_out = match borrow_amount.val {
+            0 => Decimal::new(0, collateral_scale),
+            _ => swap_out(borrow_amount)?,
+        };
+        require!(
+            collateral_out.gte(Decimal::new(
+                min_collateral_out.into(),
+                collateral_out.scale
+            ))?,
+            LeverageSlippage
+        );
+
+        // borrow synthetic
+        vault_entry.increase_supply_cascade(
+            vault,
+            &mut synthetics[synthetic_index],
+            borrow_amount,
+        )?;
+
+        let swapline_amount = match swap_route && borrow_amount.val != 0 {
+            true => {
+                let (amount_out, fee_usd) = calculate_swap_out_amount(
+                    &synthetic_asset,
+                    &swapline_asset,
+                    swapline_scale,
+                    borrow_amount,
+                    state.fee,
+                )?;
+
+                // same bookkeeping as swap
+                let swap_tax_reserve = calculate_swap_tax(fee_usd, state.swap_tax_ratio);
+                let insurance_amount = swap_tax_reserve.mul(state.insurance_swap_tax_ratio);
+                state.insurance_fund = state.insurance_fund.add(insurance_amount)?;
+                state.swap_tax_reserve = state
+                    .swap_tax_reserve
+                    .add(swap_tax_reserve.sub(insurance_amount)?)?;
+                let new_xusd_supply = synthetics[0].supply.add(swap_tax_reserve)?;
+                synthetics[0].set_supply_safely(new_xusd_supply)?;
+                amount_out
+            }
+            false => borrow_amount,
+        };
+
+        // borrowed synthetic is burned by swap or swapline
+        let synthetic = &mut synthetics[synthetic_index];
+        let new_supply_synthetic = synthetic.supply.sub(borrow_amount)?;
+        synthetic.set_supply_safely(new_supply_synthetic)?;
+
+        // swap synthetic to native, same bookkeeping as synthetic_to_native
+        // swapline_synthetic minted by swap is burned right away
+        let swapline_synthetic = &mut synthetics[swapline_synthetic_index];
+        swapline_synthetic.swapline_supply =
+            swapline_synthetic.swapline_supply.sub(swapline_amount)?;
+        swapline.balance = swapline
+            .balance
+            .sub(collateral_out.to_scale(swapline.balance.scale))?;
+
+        // deposit collateral
+        vault_entry.collateral_amount = vault_entry.collateral_amount.add(collateral_out)?;
+        vault.collateral_amount = vault.collateral_amount.add(collateral_out)?;
+
+        // Move collateral from swapline reserve to vault reserve
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+        let signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
+        token::transfer(cpi_ctx, collateral_out.to_u64())?;
+
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
+    pub fn close_vault_position(
+        ctx: Context<CloseVaultPosition>,
+        max_collateral_in: u64,
+    ) -> Result<()> {
+        msg!("Synthetify: CLOSE VAULT POSITION");
+        let timestamp = Clock::get()?.unix_timestamp;
+        let slot = Clock::get()?.slot;
+
+        let state = &mut ctx.accounts.state.load_mut()?;
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+        let vault_entry = &mut ctx.accounts.vault_entry.load_mut()?;
+        let vault = &mut ctx.accounts.vault.load_mut()?;
+        let swapline = &mut ctx.accounts.swapline.load_mut()?;
+        let (assets, collaterals, synthetics) = assets_list.split_borrow();
+
+        require!(!swapline.halted, Halted);
+        // only primary collateral is swapped, basket has to be withdrawn before closing entry
+        require!(
+            vault_entry.basket_amounts.iter().all(|x| x.val == 0),
+            AccountNotEmpty
+        );
+
+        let synthetic_index = match synthetics.iter().position(|x| {
+            x.asset_address
+                .eq(ctx.accounts.synthetic.to_account_info().key)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        let swapline_synthetic_index = match synthetics.iter().position(|x| {
+            x.asset_address
+                .eq(ctx.accounts.swapline_synthetic.to_account_info().key)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        let collateral = match collaterals.iter().find(|x| {
+            x.collateral_address
+                .eq(ctx.accounts.collateral.to_account_info().key)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        // collateral is swapped into swapline_synthetic through swapline
+        require!(
+            synthetics[swapline_synthetic_index].asset_index == collateral.asset_index,
+            MismatchedTokens
+        );
+
+        adjust_vault_entry_interest_debt(
+            vault,
+            vault_entry,
+            &mut synthetics[synthetic_index],
+            timestamp,
         );
 
-        let borrow_amount = match amount {
-            u64::MAX => amount_borrow_limit
-                .sub(vault_entry.synthetic_amount)
-                .unwrap(),
-            _ => Decimal::new(amount.into(), synthetic.supply.scale),
+        // synthetic needed to repay whole debt
+        let repay_amount = vault_entry.synthetic_amount;
+        let swapline_amount = match swapline_synthetic_index == synthetic_index {
+            true => repay_amount,
+            false => {
+                // swapline synthetic is swapped into synthetic at oracle prices
+                let asset_in = assets[synthetics[swapline_synthetic_index].asset_index as usize];
+                let asset_for = assets[synthetics[synthetic_index].asset_index as usize];
+                let oracle_settings = vault.oracle_settings;
+                check_vault_oracle(&asset_in, &oracle_settings, slot)?;
+                check_vault_oracle(&asset_for, &oracle_settings, slot)?;
+
+                let (amount_in, fee_usd) = calculate_swap_in_amount(
+                    &asset_in,
+                    &asset_for,
+                    synthetics[swapline_synthetic_index].supply.scale,
+                    repay_amount,
+                    state.fee,
+                )?;
+
+                // same bookkeeping as swap
+                let swap_tax_reserve = calculate_swap_tax(fee_usd, state.swap_tax_ratio);
+                let insurance_amount = swap_tax_reserve.mul(state.insurance_swap_tax_ratio);
+                state.insurance_fund = state.insurance_fund.add(insurance_amount)?;
+                state.swap_tax_reserve = state
+                    .swap_tax_reserve
+                    .add(swap_tax_reserve.sub(insurance_amount)?)?;
+                let new_xusd_supply = synthetics[0].supply.add(swap_tax_reserve)?;
+                synthetics[0].set_supply_safely(new_xusd_supply)?;
+
+                let new_supply_output = synthetics[synthetic_index].supply.add(repay_amount)?;
+                synthetics[synthetic_index].set_supply_safely(new_supply_output)?;
+                amount_in
+            }
         };
-        let amount_after_borrow = vault_entry.synthetic_amount.add(borrow_amount).unwrap();
-        if amount_borrow_limit.lt(amount_after_borrow)? {
-            return Err(ErrorCode::UserBorrowLimit.into());
+
+        // collateral needed to get swapline_amount
+        let collateral_in = calculate_swapline_amount_in(
+            swapline_amount,
+            swapline.fee,
+            vault_entry.collateral_amount.scale,
+        )?;
+        require!(
+            collateral_in.lte(vault_entry.collateral_amount)?,
+            VaultWithdrawLimit
+        );
+        require!(
+            collateral_in.lte(Decimal::new(max_collateral_in.into(), collateral_in.scale))?,
+            CloseSlippage
+        );
+
+        // swap native to synthetic, same bookkeeping as native_to_synthetic
+        let swapline_synthetic = &mut synthetics[swapline_synthetic_index];
+        let fee = collateral_in.sub(swapline_amount.to_scale_up(collateral_in.scale))?;
+        swapline_synthetic.swapline_supply =
+            swapline_synthetic.swapline_supply.add(swapline_amount)?;
+        // swapped synthetic is burned right away in swap path
+        if swapline_synthetic_index == synthetic_index {
+            let new_supply = swapline_synthetic.supply.add(swapline_amount)?;
+            swapline_synthetic.set_supply_safely(new_supply)?;
         }
-        vault_entry.increase_supply_cascade(vault, synthetic, borrow_amount)?;
+        swapline.accumulated_fee = swapline.accumulated_fee.add(fee)?;
+        swapline.balance = swapline.balance.add(collateral_in)?;
+        require!(swapline.balance.lte(swapline.limit)?, SwaplineLimit);
+
+        // repay debt
+        let synthetic = &mut synthetics[synthetic_index];
+        vault_entry.decrease_supply_cascade(vault, synthetic, repay_amount)?;
+
+        // withdraw rest of collateral
+        let amount_to_withdraw = vault_entry.collateral_amount.sub(collateral_in)?;
+        vault.collateral_amount = vault.collateral_amount.sub(vault_entry.collateral_amount)?;
+        vault_entry.collateral_amount = Decimal::new(0, vault_entry.collateral_amount.scale);
 
         let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
         let signer = &[&seeds[..]];
-        let mint_cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
-        token::mint_to(mint_cpi_ctx, borrow_amount.to_u64())?;
+        let cpi_program = ctx.accounts.token_program.to_account_info();
+        // Transfer swapped collateral to swapline
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_address.to_account_info(),
+            to: ctx.accounts.swapline_reserve.to_account_info(),
+            authority: ctx.accounts.exchange_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(cpi_program.clone(), cpi_accounts).with_signer(signer);
+        token::transfer(cpi_ctx, collateral_in.to_u64())?;
+        // Send rest of collateral to user
+        let cpi_accounts = Transfer {
+            from: ctx.accounts.reserve_address.to_account_info(),
+            to: ctx.accounts.user_collateral_account.to_account_info(),
+            authority: ctx.accounts.exchange_authority.to_account_info(),
+        };
+        let cpi_ctx = CpiContext::new(cpi_program, cpi_accounts).with_signer(signer);
+        token::transfer(cpi_ctx, amount_to_withdraw.to_u64())?;
 
         Ok(())
     }
-
     #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
     pub fn withdraw_vault(ctx: Context<WithdrawVault>, amount: u64) -> Result<()> {
         msg!("Synthetify: WITHDRAW_VAULT");
 
         let timestamp = Clock::get()?.unix_timestamp;
+        let slot = Clock::get()?.slot;
 
         let state = ctx.accounts.state.load()?;
         let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
@@ -2004,34 +3333,48 @@ pub mod exchange {
             })
             .unwrap();
 
-        let collateral_position = collaterals
-            .iter()
-            .position(|x| {
-                x.collateral_address
-                    .eq(ctx.accounts.collateral.to_account_info().key)
-            })
-            .unwrap();
+        // Reserve picks which collateral of vault is withdrawn
+        let index = vault.get_collateral_index(&ctx.accounts.reserve_address.mint)?;
+        let vault_collateral = vault.get_collateral(index);
+        require!(
+            vault_collateral.collateral_reserve.eq(ctx
+                .accounts
+                .reserve_address
+                .to_account_info()
+                .key),
+            InvalidAccount
+        );
 
         let synthetic_asset = assets[synthetics[synthetic_position].asset_index as usize];
-        let collateral_asset = assets[collaterals[collateral_position].asset_index as usize];
         let synthetic = &mut synthetics[synthetic_position];
 
         adjust_vault_entry_interest_debt(vault, vault_entry, synthetic, timestamp);
 
+        let basket =
+            get_vault_entry_collaterals(vault, vault_entry, &assets[..], &collaterals[..], false);
+        // prices are needed only to keep debt covered
+        if vault_entry.synthetic_amount.val != 0 {
+            let oracle_settings = vault.oracle_settings;
+            check_vault_oracle(&synthetic_asset, &oracle_settings, slot)?;
+            for (collateral_asset, collateral_amount, _) in basket.iter() {
+                if collateral_amount.val != 0 {
+                    check_vault_oracle(collateral_asset, &oracle_settings, slot)?;
+                }
+            }
+        }
+
         let vault_withdraw_limit = calculate_vault_withdraw_limit(
-            collateral_asset,
+            &basket,
             synthetic_asset,
-            vault_entry.collateral_amount,
             vault_entry.synthetic_amount,
-            vault.collateral_ratio,
+            index,
         )
         .unwrap();
-
-        adjust_vault_entry_interest_debt(vault, vault_entry, synthetic, timestamp);
+        let (_, collateral_amount, _) = basket[index];
 
         let amount_to_withdraw = match amount {
             u64::MAX => vault_withdraw_limit,
-            _ => Decimal::new(amount as u128, vault_entry.collateral_amount.scale),
+            _ => Decimal::new(amount as u128, collateral_amount.scale),
         };
 
         if amount_to_withdraw.gt(vault_withdraw_limit)? {
@@ -2039,11 +3382,15 @@ pub mod exchange {
         }
 
         // update vault, vault_entry balances
-        vault.collateral_amount = vault.collateral_amount.sub(amount_to_withdraw).unwrap();
-        vault_entry.collateral_amount = vault_entry
-            .collateral_amount
-            .sub(amount_to_withdraw)
-            .unwrap();
+        vault.set_collateral_amount(
+            index,
+            vault_collateral
+                .collateral_amount
+                .sub(amount_to_withdraw)
+                .unwrap(),
+        );
+        vault_entry
+            .set_collateral_amount(index, collateral_amount.sub(amount_to_withdraw).unwrap());
 
         // Send withdrawn collateral to user
         let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
@@ -2096,13 +3443,54 @@ pub mod exchange {
         Ok(())
     }
     #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
+    pub fn repay_vault_for(ctx: Context<RepayVaultFor>, amount: u64) -> Result<()> {
+        msg!("Synthetify: REPAY_VAULT_FOR");
+
+        let timestamp = Clock::get()?.unix_timestamp;
+
+        let state = ctx.accounts.state.load()?;
+        let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
+        let vault_entry = &mut ctx.accounts.vault_entry.load_mut()?;
+        let vault = &mut ctx.accounts.vault.load_mut()?;
+        let (_, _, synthetics) = assets_list.split_borrow();
+
+        let synthetic = synthetics
+            .iter_mut()
+            .find(|x| {
+                x.asset_address
+                    .eq(ctx.accounts.synthetic.to_account_info().key)
+            })
+            .unwrap();
+
+        adjust_vault_entry_interest_debt(vault, vault_entry, synthetic, timestamp);
+
+        // determine repay_amount
+        let mut repay_amount = match amount {
+            u64::MAX => vault_entry.synthetic_amount,
+            _ => Decimal::new(amount.into(), vault_entry.synthetic_amount.scale),
+        };
+
+        if repay_amount.gt(vault_entry.synthetic_amount)? {
+            repay_amount = vault_entry.synthetic_amount;
+        };
+        vault_entry.decrease_supply_cascade(vault, synthetic, repay_amount)?;
+
+        // burn tokens of signer
+        let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
+        let signer = &[&seeds[..]];
+        let cpi_ctx = CpiContext::from(&*ctx.accounts).with_signer(signer);
+        token::burn(cpi_ctx, repay_amount.to_u64())?;
+
+        Ok(())
+    }
+    #[access_control(halted(&ctx.accounts.state) vault_halted(&ctx.accounts.vault))]
     pub fn liquidate_vault(ctx: Context<LiquidateVault>, amount: u64) -> Result<()> {
         msg!("Synthetify: LIQUIDATE VAULT");
 
         let timestamp = Clock::get()?.unix_timestamp;
         let slot = Clock::get()?.slot;
 
-        let state = ctx.accounts.state.load()?;
+        let state = &mut ctx.accounts.state.load_mut()?;
         let assets_list = &mut ctx.accounts.assets_list.load_mut()?;
         let vault_entry = &mut ctx.accounts.vault_entry.load_mut()?;
         let vault = &mut ctx.accounts.vault.load_mut()?;
@@ -2116,37 +3504,52 @@ pub mod exchange {
             })
             .unwrap();
 
-        let collateral_position = collaterals
+        // Liquidator picks which collateral of vault is seized
+        let index = vault.get_collateral_index(&ctx.accounts.collateral_reserve.mint)?;
+        let vault_collateral = vault.get_collateral(index);
+        require!(
+            vault_collateral.collateral_reserve.eq(ctx
+                .accounts
+                .collateral_reserve
+                .to_account_info()
+                .key),
+            InvalidAccount
+        );
+        let collateral = collaterals
             .iter()
-            .position(|x| {
-                x.collateral_address
-                    .eq(ctx.accounts.collateral.to_account_info().key)
-            })
+            .find(|x| x.collateral_address.eq(&vault_collateral.collateral))
             .unwrap();
 
         let synthetic = &mut synthetics[synthetic_position];
-        let collateral = &mut collaterals[collateral_position];
         let synthetic_asset = &assets[synthetic.asset_index as usize];
         let collateral_asset = &assets[collateral.asset_index as usize];
 
         adjust_vault_entry_interest_debt(vault, vault_entry, synthetic, timestamp);
 
-        if (synthetic_asset.last_update as u64) < slot.checked_sub(state.max_delay as u64).unwrap()
-        {
+        let basket =
+            get_vault_entry_collaterals(vault, vault_entry, &assets[..], &collaterals[..], true);
+        // liquidation only requires fresh prices
+        let max_delay = vault.oracle_settings.max_delay as u64;
+        if (synthetic_asset.last_update as u64) < slot.checked_sub(max_delay).unwrap() {
             return Err(ErrorCode::OutdatedOracle.into());
         }
-        if (collateral_asset.last_update as u64) < slot.checked_sub(state.max_delay as u64).unwrap()
-        {
-            return Err(ErrorCode::OutdatedOracle.into());
+        for (basket_asset, basket_amount, _) in basket.iter() {
+            if basket_amount.val != 0
+                && (basket_asset.last_update as u64) < slot.checked_sub(max_delay).unwrap()
+            {
+                return Err(ErrorCode::OutdatedOracle.into());
+            }
         }
 
         // Amount of synthetic safely collateralized
+        let price_source = vault.liquidation_price_source;
         let amount_liquidation_limit = calculate_vault_borrow_limit(
-            *collateral_asset,
-            *synthetic_asset,
+            &basket,
+            Asset {
+                price: get_price_by_source(synthetic_asset, price_source, true),
+                ..*synthetic_asset
+            },
             *synthetic,
-            vault_entry.collateral_amount,
-            vault.liquidation_threshold,
         );
         // Fail if user is safe
         require!(
@@ -2181,11 +3584,25 @@ pub mod exchange {
             .mul(synthetic_asset.price);
 
         // Amount seized in token
+        let (_, collateral_amount, _) = basket[index];
         let seized_collateral_in_token = usd_to_token_amount(
             &collateral_asset,
             seized_collateral_in_usd,
-            collateral.reserve_balance.scale,
+            collateral_amount.scale,
         );
+        // Seize whole picked collateral if it does not cover liquidation
+        let (seized_collateral_in_token, liquidation_amount) =
+            match seized_collateral_in_token.gt(collateral_amount)? {
+                true => (
+                    collateral_amount,
+                    usd_to_token_amount(
+                        synthetic_asset,
+                        calculate_value_in_usd(collateral_asset.price, collateral_amount),
+                        liquidation_amount.scale,
+                    ),
+                ),
+                false => (seized_collateral_in_token, liquidation_amount),
+            };
 
         let collateral_to_exchange = seized_collateral_in_token
             .mul(vault.liquidation_penalty_exchange)
@@ -2202,18 +3619,32 @@ pub mod exchange {
             .unwrap();
 
         // Adjust vault_entry variables
-        vault_entry.collateral_amount = vault_entry
-            .collateral_amount
-            .sub(seized_collateral_in_token)
-            .unwrap();
+        vault_entry.set_collateral_amount(
+            index,
+            collateral_amount.sub(seized_collateral_in_token).unwrap(),
+        );
 
-        vault.collateral_amount = vault
-            .collateral_amount
-            .sub(seized_collateral_in_token)
-            .unwrap();
+        vault.set_collateral_amount(
+            index,
+            vault_collateral
+                .collateral_amount
+                .sub(seized_collateral_in_token)
+                .unwrap(),
+        );
 
         vault_entry.decrease_supply_cascade(vault, synthetic, liquidation_amount)?;
 
+        // Debt left without any collateral is covered by insurance fund and debt pool
+        let no_collateral =
+            (0..vault.collaterals_count()).all(|i| vault_entry.get_collateral_amount(i).val == 0);
+        if no_collateral && vault_entry.synthetic_amount.val > 0 {
+            let shortfall = vault_entry.release_shortfall_cascade(vault, synthetic);
+            let shortfall_in_usd = calculate_value_in_usd(synthetic_asset.price, shortfall);
+            let uncovered_debt =
+                cover_with_insurance_fund(state, &mut synthetics[0], shortfall_in_usd);
+            state.bad_debt = state.bad_debt.add(uncovered_debt).unwrap();
+        }
+
         let seeds = &[SYNTHETIFY_EXCHANGE_SEED.as_bytes(), &[state.nonce]];
         let signer_seeds = &[&seeds[..]];
         {
@@ -2427,6 +3858,46 @@ pub mod exchange {
         Ok(())
     }
 
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_vault_oracle_settings(
+        ctx: Context<SetVaultParameter>,
+        max_delay: u32,
+        max_price_deviation: Decimal,
+        require_trading: bool,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET VAULT ORACLE SETTINGS");
+        let vault = &mut ctx.accounts.vault.load_mut()?;
+
+        require!(
+            max_price_deviation.scale == UNIFIED_PERCENT_SCALE,
+            ParameterOutOfRange
+        );
+
+        vault.oracle_settings = OracleSettings {
+            max_delay,
+            max_price_deviation,
+            require_trading,
+        };
+        Ok(())
+    }
+
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_vault_liquidation_price_source(
+        ctx: Context<SetVaultParameter>,
+        liquidation_price_source: u8,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET VAULT LIQUIDATION PRICE SOURCE");
+        let vault = &mut ctx.accounts.vault.load_mut()?;
+
+        require!(
+            liquidation_price_source <= PriceSource::Conservative as u8,
+            ParameterOutOfRange
+        );
+
+        vault.liquidation_price_source = liquidation_price_source;
+        Ok(())
+    }
+
     #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
     pub fn withdraw_vault_accumulated_interest(
         ctx: Context<WithdrawVaultAccumulatedInterest>,
@@ -2464,6 +3935,74 @@ pub mod exchange {
 
         Ok(())
     }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn add_vault_collateral(
+        ctx: Context<AddVaultCollateral>,
+        collateral_ratio: Decimal,
+        liquidation_threshold: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: ADD VAULT COLLATERAL");
+        let mut vault = ctx.accounts.vault.load_mut()?;
+        let mut assets_list = ctx.accounts.assets_list.load_mut()?;
+        let collateral_address = ctx.accounts.basket_collateral.to_account_info().key;
+
+        let collateral = match assets_list
+            .collaterals
+            .iter_mut()
+            .find(|x| x.collateral_address.eq(collateral_address))
+        {
+            Some(collateral) => collateral,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+        require!(
+            vault.get_collateral_index(collateral_address).is_err(),
+            AlreadyACollateral
+        );
+        // collateral_ratio <= liquidation_threshold <= 100%
+        let same_scale = collateral_ratio.scale == vault.collateral_ratio.scale
+            && liquidation_threshold.scale == vault.liquidation_threshold.scale;
+        let in_range = collateral_ratio.lte(liquidation_threshold)?
+            && liquidation_threshold.lte(Decimal::from_percent(100))?;
+        require!(same_scale && in_range, ParameterOutOfRange);
+
+        // Collateral cannot be removed while used by vault
+        collateral.vault_count = collateral.vault_count.checked_add(1).unwrap();
+
+        vault.append(BasketCollateral {
+            collateral: *collateral_address,
+            collateral_reserve: *ctx.accounts.collateral_reserve.to_account_info().key,
+            collateral_ratio,
+            liquidation_threshold,
+            collateral_amount: Decimal::new(0, collateral.reserve_balance.scale),
+        })?;
+        Ok(())
+    }
+    #[access_control(admin(&ctx.accounts.state, &ctx.accounts.admin))]
+    pub fn set_vault_basket_collateral(
+        ctx: Context<SetVaultBasketCollateral>,
+        collateral_ratio: Decimal,
+        liquidation_threshold: Decimal,
+    ) -> Result<()> {
+        msg!("Synthetify:Admin: SET VAULT BASKET COLLATERAL");
+        let vault = &mut ctx.accounts.vault.load_mut()?;
+        let collateral_address = ctx.accounts.basket_collateral.to_account_info().key;
+
+        // primary collateral has its own setters
+        let index = vault.get_collateral_index(collateral_address)?;
+        require!(index != 0, InvalidAccount);
+
+        // collateral_ratio <= liquidation_threshold <= 100%
+        let basket_collateral = &mut vault.basket[index - 1];
+        let same_scale = basket_collateral.collateral_ratio.scale == collateral_ratio.scale
+            && basket_collateral.liquidation_threshold.scale == liquidation_threshold.scale;
+        let in_range = collateral_ratio.lte(liquidation_threshold)?
+            && liquidation_threshold.lte(Decimal::from_percent(100))?;
+        require!(same_scale && in_range, ParameterOutOfRange);
+
+        basket_collateral.collateral_ratio = collateral_ratio;
+        basket_collateral.liquidation_threshold = liquidation_threshold;
+        Ok(())
+    }
 }
 
 // some error code may be unused (future use)
@@ -2543,6 +4082,30 @@ pub enum ErrorCode {
     VaultWithdrawLimit = 35,
     #[msg("Invalid Account")]
     InvalidAccount = 36,
+    #[msg("Account has no bad debt")]
+    NoBadDebt = 37,
+    #[msg("Flash liquidation was not repaid")]
+    FlashLiquidationNotRepaid = 38,
+    #[msg("Collateral is deprecated")]
+    CollateralDeprecated = 39,
+    #[msg("Collateral cannot be removed")]
+    CollateralNotRemovable = 40,
+    #[msg("Asset is still in use")]
+    AssetInUse = 41,
+    #[msg("Limit of collateral entries reached")]
+    CollateralEntriesLimit = 42,
+    #[msg("Exchange account has to be migrated")]
+    OutdatedAccountVersion = 43,
+    #[msg("Account is not empty")]
+    AccountNotEmpty = 44,
+    #[msg("Leveraged collateral below minimum")]
+    LeverageSlippage = 45,
+    #[msg("Collateral needed to close position above maximum")]
+    CloseSlippage = 46,
+    #[msg("Oracle price is not reliable")]
+    UnreliablePrice = 47,
+    #[msg("Insurance fund withdrawal is timelocked")]
+    InsuranceWithdrawalLocked = 48,
 }
 
 // Access control modifiers.
@@ -2558,6 +4121,15 @@ fn halted<'info>(state_loader: &Loader<State>) -> Result<()> {
     require!(!state.halted, Halted);
     Ok(())
 }
+// Exchange account has to be migrated to current version
+fn version<'info>(exchange_account_loader: &Loader<ExchangeAccount>) -> Result<()> {
+    let exchange_account = exchange_account_loader.load()?;
+    require!(
+        exchange_account.version == EXCHANGE_ACCOUNT_VERSION,
+        OutdatedAccountVersion
+    );
+    Ok(())
+}
 // Vault containers
 fn vault_halted<'info>(vault_loader: &Loader<Vault>) -> Result<()> {
     let vault = vault_loader.load()?;
@@ -2582,14 +4154,18 @@ mod tests {
             let mut exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            exchange_account.append(CollateralEntry {
-                index: 1,
-                ..Default::default()
-            });
-            exchange_account.append(CollateralEntry {
-                index: 2,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    index: 1,
+                    ..Default::default()
+                })
+                .unwrap();
+            exchange_account
+                .append(CollateralEntry {
+                    index: 2,
+                    ..Default::default()
+                })
+                .unwrap();
             assert_eq!(exchange_account.head, 2);
             assert_eq!(exchange_account.collaterals[0].index, 1);
             assert_eq!(exchange_account.collaterals[1].index, 2);
@@ -2599,14 +4175,18 @@ mod tests {
             let mut exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            exchange_account.append(CollateralEntry {
-                index: 1,
-                ..Default::default()
-            });
-            exchange_account.append(CollateralEntry {
-                index: 2,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    index: 1,
+                    ..Default::default()
+                })
+                .unwrap();
+            exchange_account
+                .append(CollateralEntry {
+                    index: 2,
+                    ..Default::default()
+                })
+                .unwrap();
             exchange_account.remove(0);
             assert_eq!(exchange_account.head, 1);
             assert_eq!(exchange_account.collaterals[0].index, 2);
@@ -2617,21 +4197,92 @@ mod tests {
                 ..Default::default()
             };
 
-            exchange_account.append(CollateralEntry {
-                index: 1,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    index: 1,
+                    ..Default::default()
+                })
+                .unwrap();
             exchange_account.remove(0);
-            exchange_account.append(CollateralEntry {
-                index: 2,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    index: 2,
+                    ..Default::default()
+                })
+                .unwrap();
 
             assert_eq!(exchange_account.head, 1);
             assert_eq!(exchange_account.collaterals[0].index, 2);
         }
+        // Append over limit
+        {
+            let mut exchange_account = ExchangeAccount {
+                ..Default::default()
+            };
+            for i in 0..32 {
+                exchange_account
+                    .append(CollateralEntry {
+                        index: i,
+                        ..Default::default()
+                    })
+                    .unwrap();
+            }
+            let result = exchange_account.append(CollateralEntry {
+                index: 32,
+                ..Default::default()
+            });
+            assert!(result.is_err());
+            assert_eq!(exchange_account.head, 32);
+            assert_eq!(exchange_account.collaterals[31].index, 31);
+        }
     }
 
+    #[test]
+    fn test_exchange_account_delegate() {
+        let owner = Pubkey::new_unique();
+        let delegate = Pubkey::new_unique();
+        let other = Pubkey::new_unique();
+        // No delegate
+        {
+            let exchange_account = ExchangeAccount {
+                owner,
+                ..Default::default()
+            };
+            assert!(exchange_account.is_authorized(&owner, DelegatePermission::Withdraw));
+            assert!(!exchange_account.is_authorized(&delegate, DelegatePermission::Burn));
+            assert!(!exchange_account.is_authorized(&Pubkey::default(), DelegatePermission::Burn));
+            assert!(exchange_account.is_owner_or_delegate(&owner));
+            assert!(!exchange_account.is_owner_or_delegate(&Pubkey::default()));
+        }
+        // Mint and burn only
+        {
+            let exchange_account = ExchangeAccount {
+                owner,
+                delegate,
+                delegate_permissions: DelegatePermission::Mint as u8
+                    | DelegatePermission::Burn as u8,
+                ..Default::default()
+            };
+            assert!(exchange_account.is_authorized(&owner, DelegatePermission::Withdraw));
+            assert!(exchange_account.is_authorized(&delegate, DelegatePermission::Mint));
+            assert!(exchange_account.is_authorized(&delegate, DelegatePermission::Burn));
+            assert!(!exchange_account.is_authorized(&delegate, DelegatePermission::Withdraw));
+            assert!(!exchange_account.is_authorized(&other, DelegatePermission::Burn));
+            assert!(exchange_account.is_owner_or_delegate(&delegate));
+            assert!(!exchange_account.is_owner_or_delegate(&other));
+        }
+        // Delegate with empty permissions
+        {
+            let exchange_account = ExchangeAccount {
+                owner,
+                delegate,
+                delegate_permissions: 0,
+                ..Default::default()
+            };
+            assert!(!exchange_account.is_authorized(&delegate, DelegatePermission::Burn));
+            assert!(!exchange_account.is_owner_or_delegate(&delegate));
+        }
+    }
     #[test]
     fn test_assets_list_appending() {
         // Freshly created
@@ -2709,6 +4360,108 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_assets_list_removing_collateral() {
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        let addresses = [
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+            Pubkey::new_unique(),
+        ];
+        for (i, address) in addresses.iter().enumerate() {
+            assets_list.append_collateral(Collateral {
+                asset_index: i as u8,
+                collateral_address: *address,
+                ..Default::default()
+            });
+        }
+        let entry = CollateralEntry {
+            collateral_address: addresses[2],
+            index: 2,
+            ..Default::default()
+        };
+        assert_eq!(assets_list.get_collateral_index(&entry), Ok(2));
+
+        // SNY collateral cannot be removed
+        assert!(assets_list.remove_collateral(0).is_err());
+        assert_eq!(assets_list.head_collaterals, 3);
+
+        // Last collateral is moved in place of removed one
+        assets_list.remove_collateral(1).unwrap();
+        assert_eq!(assets_list.head_collaterals, 2);
+        assert_eq!(
+            { assets_list.collaterals[1].collateral_address },
+            addresses[2]
+        );
+        assert_eq!({ assets_list.collaterals[1].asset_index }, 2);
+        assert_eq!(assets_list.collaterals[2], Collateral::default());
+
+        // Outdated index of entry is found by address
+        assert_eq!(assets_list.get_collateral_index(&entry), Ok(1));
+
+        // Removed collateral is not found
+        let removed_entry = CollateralEntry {
+            collateral_address: addresses[1],
+            index: 1,
+            ..Default::default()
+        };
+        assert!(assets_list.get_collateral_index(&removed_entry).is_err());
+    }
+    #[test]
+    fn test_assets_list_removing_asset() {
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        for i in 0..5 {
+            assets_list.append_asset(Asset {
+                price: Decimal::from_price(i),
+                ..Default::default()
+            });
+        }
+        assets_list.append_synthetic(Synthetic {
+            asset_index: 0,
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            asset_index: 1,
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            asset_index: 4,
+            ..Default::default()
+        });
+        assets_list.append_synthetic(Synthetic {
+            asset_index: 3,
+            ..Default::default()
+        });
+
+        // xUSD and SNY assets cannot be removed
+        assert!(assets_list.remove_asset(0).is_err());
+        assert!(assets_list.remove_asset(1).is_err());
+        // Referenced assets cannot be removed
+        assert!(assets_list.remove_asset(3).is_err());
+        assert!(assets_list.remove_asset(4).is_err());
+        assert_eq!(assets_list.head_assets, 5);
+
+        // Last asset is moved in place of removed one
+        assets_list.remove_asset(2).unwrap();
+        assert_eq!(assets_list.head_assets, 4);
+        assert_eq!({ assets_list.assets[2].price }, Decimal::from_price(4));
+        assert_eq!(assets_list.assets[4], Asset::default());
+        assert_eq!({ assets_list.collaterals[0].asset_index }, 1);
+        assert_eq!({ assets_list.collaterals[1].asset_index }, 2);
+        assert_eq!({ assets_list.synthetics[0].asset_index }, 0);
+        assert_eq!({ assets_list.synthetics[1].asset_index }, 3);
+
+        // Removing last asset does not move anything
+        assets_list.remove_synthetic(1).unwrap();
+        assets_list.remove_asset(3).unwrap();
+        assert_eq!(assets_list.head_assets, 3);
+        assert_eq!({ assets_list.assets[2].price }, Decimal::from_price(4));
+        assert_eq!({ assets_list.collaterals[1].asset_index }, 2);
+    }
     #[test]
     fn test_assets_list_split_borrow() {
         let mut assets_list = AssetsList {
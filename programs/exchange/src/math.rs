@@ -1,7 +1,8 @@
 use std::{cell::RefMut, convert::TryInto};
 
 use crate::decimal::{
-    Add, Compare, Div, DivScale, DivUp, Mul, MulUp, PowAccuracy, Sub, XUSD_SCALE,
+    Add, Compare, Div, DivScale, DivUp, Mul, MulUp, PowAccuracy, Sub, UNIFIED_PERCENT_SCALE,
+    XUSD_SCALE,
 };
 use crate::*;
 
@@ -16,6 +17,18 @@ pub fn calculate_debt(
     slot: u64,
     max_delay: u32,
     twap: bool,
+) -> Result<Decimal> {
+    let price_source = match twap {
+        true => PriceSource::Twap,
+        false => PriceSource::Spot,
+    };
+    calculate_debt_by_price_source(assets_list, slot, max_delay, price_source as u8)
+}
+pub fn calculate_debt_by_price_source(
+    assets_list: &AssetsList,
+    slot: u64,
+    max_delay: u32,
+    price_source: u8,
 ) -> Result<Decimal> {
     let mut debt = Decimal::from_usd(0);
     let synthetics = &assets_list.synthetics;
@@ -25,10 +38,7 @@ pub fn calculate_debt(
         if asset.last_update < (slot - max_delay as u64) {
             return Err(ErrorCode::OutdatedOracle.into());
         }
-        let price = match twap {
-            true => asset.twap,
-            _ => asset.price,
-        };
+        let price = get_price_by_source(asset, price_source, true);
         let debt_supply = synthetic
             .supply
             .sub(synthetic.swapline_supply)
@@ -40,12 +50,16 @@ pub fn calculate_debt(
     }
     Ok(debt)
 }
-pub fn calculate_max_debt_in_usd(account: &ExchangeAccount, assets_list: &AssetsList) -> Decimal {
+pub fn calculate_max_debt_in_usd(
+    account: &ExchangeAccount,
+    assets_list: &AssetsList,
+) -> Result<Decimal> {
     let mut max_debt = Decimal::from_usd(0);
     let head = account.head as usize;
 
     for collateral_entry in account.collaterals[..head].iter() {
-        let collateral = &assets_list.collaterals[collateral_entry.index as usize];
+        let collateral =
+            &assets_list.collaterals[assets_list.get_collateral_index(collateral_entry)?];
         let asset = &assets_list.assets[collateral.asset_index as usize];
         // rounding up to be sure that debt is not less than minted tokens
 
@@ -63,7 +77,67 @@ pub fn calculate_max_debt_in_usd(account: &ExchangeAccount, assets_list: &Assets
             )
             .unwrap();
     }
-    return max_debt;
+    Ok(max_debt)
+}
+pub fn calculate_max_debt_in_usd_for_liquidation(
+    account: &ExchangeAccount,
+    assets_list: &AssetsList,
+    price_source: u8,
+) -> Result<Decimal> {
+    let mut max_debt = Decimal::from_usd(0);
+    let head = account.head as usize;
+
+    for collateral_entry in account.collaterals[..head].iter() {
+        let collateral =
+            &assets_list.collaterals[assets_list.get_collateral_index(collateral_entry)?];
+        let asset = &assets_list.assets[collateral.asset_index as usize];
+        let liquidation_threshold = get_liquidation_threshold(collateral);
+        let price = get_price_by_source(asset, price_source, false);
+
+        let amount_of_collateral = Decimal {
+            val: collateral_entry.amount.into(),
+            scale: collateral.reserve_balance.scale,
+        };
+        max_debt = max_debt
+            .add(
+                price
+                    .mul(amount_of_collateral)
+                    .mul(liquidation_threshold)
+                    .to_usd(),
+            )
+            .unwrap();
+    }
+    Ok(max_debt)
+}
+pub fn get_price_by_source(asset: &Asset, price_source: u8, is_debt: bool) -> Decimal {
+    // twap is empty before first oracle update, fall back to spot price
+    if asset.twap.val == 0 {
+        return asset.price;
+    }
+    match price_source {
+        x if x == PriceSource::Twap as u8 => asset.twap,
+        x if x == PriceSource::Conservative as u8 => {
+            // higher price for debt, lower for collateral
+            match asset.twap.gt(asset.price).unwrap() == is_debt {
+                true => asset.twap,
+                false => asset.price,
+            }
+        }
+        _ => asset.price,
+    }
+}
+pub fn get_liquidation_threshold(collateral: &Collateral) -> Decimal {
+    // liquidation_threshold is never lower than collateral_ratio (also when not set)
+    let is_set = collateral.liquidation_threshold.scale == collateral.collateral_ratio.scale;
+    match is_set
+        && collateral
+            .liquidation_threshold
+            .gt(collateral.collateral_ratio)
+            .unwrap()
+    {
+        true => collateral.liquidation_threshold,
+        false => collateral.collateral_ratio,
+    }
 }
 pub fn calculate_user_debt_in_usd(
     user_account: &ExchangeAccount,
@@ -198,6 +272,21 @@ pub fn calculate_swap_out_amount(
     let amount_out = usd_to_token_amount(asset_for, value_out_usd, decimals_out);
     return Ok((amount_out, fee));
 }
+// Amount of synthetic needed to get amount_out of other synthetic in swap
+pub fn calculate_swap_in_amount(
+    asset_in: &Asset,
+    asset_for: &Asset,
+    decimals_in: u8,
+    amount_out: Decimal,
+    fee: Decimal,
+) -> Result<(Decimal, Decimal)> {
+    let value_out_usd = (asset_for.price).mul_up(amount_out).to_usd_up();
+    let fee_complement = Decimal::from_percent(100).sub(fee)?;
+    let value_in_usd = value_out_usd.div_up(fee_complement);
+    let fee = value_in_usd.sub(value_out_usd)?;
+    let amount_in = value_in_usd.to_scale_up(decimals_in).div_up(asset_in.price);
+    Ok((amount_in, fee))
+}
 pub fn calculate_burned_shares(
     asset: &Asset,
     all_debt: Decimal,
@@ -235,43 +324,270 @@ pub fn calculate_debt_interest_rate(debt_interest_rate: u16) -> Decimal {
 pub fn calculate_minute_interest_rate(apr: Decimal) -> Decimal {
     Decimal::from_interest_rate(apr.val.checked_div(MINUTES_IN_YEAR.into()).unwrap())
 }
+pub fn calculate_vault_max_debt_value(collaterals: &[(Asset, Decimal, Decimal)]) -> Decimal {
+    // collaterals are (collateral_asset, collateral_amount, collateral_ratio)
+    collaterals.iter().fold(
+        Decimal::from_usd(0),
+        |acc, (collateral_asset, collateral_amount, collateral_ratio)| {
+            let collateral_value =
+                calculate_value_in_usd(collateral_asset.price, *collateral_amount);
+            acc.add(collateral_value.mul(*collateral_ratio)).unwrap()
+        },
+    )
+}
 pub fn calculate_vault_borrow_limit(
-    collateral_asset: Asset,
+    collaterals: &[(Asset, Decimal, Decimal)],
     synthetic_asset: Asset,
     synthetic: Synthetic,
-    collateral_amount: Decimal,
-    collateral_ratio: Decimal,
 ) -> Decimal {
-    let collateral_value = calculate_value_in_usd(collateral_asset.price, collateral_amount);
-    let max_debt = collateral_value.mul(collateral_ratio);
+    let max_debt = calculate_vault_max_debt_value(collaterals);
     let max_synthetic_amount =
         usd_to_token_amount(&synthetic_asset, max_debt, synthetic.supply.scale);
 
     return max_synthetic_amount;
 }
 pub fn calculate_vault_withdraw_limit(
-    collateral_asset: Asset,
+    collaterals: &[(Asset, Decimal, Decimal)],
     synthetic_asset: Asset,
-    collateral_amount: Decimal,
     synthetic_amount: Decimal,
-    collateral_ratio: Decimal,
+    index: usize,
 ) -> Result<Decimal> {
+    let (collateral_asset, collateral_amount, collateral_ratio) = collaterals[index];
+    // collateral not counted in borrow limit can always be withdrawn
+    if synthetic_amount.val == 0 || collateral_ratio.val == 0 {
+        return Ok(collateral_amount);
+    }
     let vault_debt_value = calculate_value_in_usd(synthetic_asset.price, synthetic_amount);
-    let collateral_value = calculate_value_in_usd(collateral_asset.price, collateral_amount);
-    let min_collateralized_value = vault_debt_value.div(collateral_ratio);
-    let max_debt_value = collateral_value.mul(collateral_ratio);
+    let max_debt_value = calculate_vault_max_debt_value(collaterals);
 
     if vault_debt_value.gte(max_debt_value)? {
         return Err(ErrorCode::VaultWithdrawLimit.into());
     }
 
-    let max_withdraw_value = collateral_value.sub(min_collateralized_value).unwrap();
+    let max_withdraw_value = max_debt_value
+        .sub(vault_debt_value)
+        .unwrap()
+        .div(collateral_ratio);
     let max_withdraw_amount = usd_to_token_amount(
         &collateral_asset,
         max_withdraw_value,
         collateral_amount.scale,
     );
-    return Ok(max_withdraw_amount);
+    match max_withdraw_amount.gt(collateral_amount)? {
+        true => Ok(collateral_amount),
+        false => Ok(max_withdraw_amount),
+    }
+}
+// Simulates borrowing synthetic and swapping it into primary collateral until target ratio is reached
+// collaterals are basket of vault entry with primary collateral first, swap_out is route of swap
+// Returns borrowed synthetic and collateral received after fees
+pub fn calculate_vault_leverage<F: Fn(Decimal) -> Result<Decimal>>(
+    collaterals: &[(Asset, Decimal, Decimal)],
+    synthetic_asset: Asset,
+    synthetic: Synthetic,
+    synthetic_amount: Decimal,
+    target_ratio: Decimal,
+    iterations: u8,
+    swap_out: F,
+) -> Result<(Decimal, Decimal)> {
+    let mut collaterals = collaterals.to_vec();
+    let mut synthetic_amount = synthetic_amount;
+    let mut total_borrow = Decimal::new(0, synthetic_amount.scale);
+    let mut total_collateral = Decimal::new(0, collaterals[0].1.scale);
+
+    for _ in 0..iterations {
+        // target ratio is applied to value of whole basket
+        let target_collaterals: Vec<(Asset, Decimal, Decimal)> = collaterals
+            .iter()
+            .map(|(asset, amount, _)| (*asset, *amount, target_ratio))
+            .collect();
+        let target_limit =
+            calculate_vault_borrow_limit(&target_collaterals, synthetic_asset, synthetic);
+        if target_limit.lte(synthetic_amount)? {
+            break;
+        }
+        let borrow_amount = target_limit.sub(synthetic_amount)?;
+        let amount_after_borrow = synthetic_amount.add(borrow_amount)?;
+
+        let borrow_limit = calculate_vault_borrow_limit(&collaterals, synthetic_asset, synthetic);
+        require!(borrow_limit.gte(amount_after_borrow)?, UserBorrowLimit);
+
+        let collateral_out = swap_out(borrow_amount)?;
+
+        synthetic_amount = amount_after_borrow;
+        collaterals[0].1 = collaterals[0].1.add(collateral_out)?;
+        total_borrow = total_borrow.add(borrow_amount)?;
+        total_collateral = total_collateral.add(collateral_out)?;
+    }
+    Ok((total_borrow, total_collateral))
+}
+// Amount of native token swapline needs to return given synthetic amount after fee
+pub fn calculate_swapline_amount_in(
+    amount_out: Decimal,
+    fee: Decimal,
+    scale_in: u8,
+) -> Result<Decimal> {
+    let fee_complement = Decimal::from_percent(100).sub(fee)?;
+    Ok(amount_out.to_scale_up(scale_in).div_up(fee_complement))
+}
+pub fn calculate_liquidation_seized_collateral(
+    asset: &Asset,
+    collateral_amount: Decimal,
+    liquidation_amount: Decimal,
+    penalty: Decimal,
+) -> (Decimal, Decimal) {
+    // seized collateral covers liquidation_amount increased by all penalties
+    let seized_collateral_in_usd = liquidation_amount
+        .mul_up(penalty)
+        .add(liquidation_amount)
+        .unwrap();
+    let seized_collateral_in_token =
+        usd_to_token_amount(asset, seized_collateral_in_usd, collateral_amount.scale);
+
+    match seized_collateral_in_token.lte(collateral_amount).unwrap() {
+        true => (seized_collateral_in_token, liquidation_amount),
+        // seize whole collateral and decrease liquidation_amount to its value
+        false => (
+            collateral_amount,
+            calculate_value_in_usd(asset.price, collateral_amount),
+        ),
+    }
+}
+pub fn calculate_liquidation_auction_discount(
+    start_discount: Decimal,
+    max_discount: Decimal,
+    auction_length: u32,
+    elapsed_slots: u64,
+) -> Decimal {
+    if elapsed_slots >= auction_length.into() {
+        return max_discount;
+    }
+    // discount grows linearly from start_discount to max_discount
+    let discount_range = max_discount.sub(start_discount).unwrap();
+    let discount_growth = Decimal {
+        val: discount_range
+            .val
+            .checked_mul(elapsed_slots.into())
+            .unwrap()
+            .checked_div(auction_length.into())
+            .unwrap(),
+        scale: discount_range.scale,
+    };
+    start_discount.add(discount_growth).unwrap()
+}
+pub fn calculate_deprecated_ratio(
+    initial_ratio: Decimal,
+    deprecation_start: u64,
+    deprecation_length: u32,
+    slot: u64,
+) -> Decimal {
+    let elapsed_slots = slot.saturating_sub(deprecation_start);
+    if elapsed_slots >= deprecation_length.into() {
+        return Decimal::new(0, initial_ratio.scale);
+    }
+    // ratio decreases linearly from initial_ratio to 0
+    let remaining_slots = (deprecation_length as u64)
+        .checked_sub(elapsed_slots)
+        .unwrap();
+    Decimal {
+        val: initial_ratio
+            .val
+            .checked_mul(remaining_slots.into())
+            .unwrap()
+            .checked_div(deprecation_length.into())
+            .unwrap(),
+        scale: initial_ratio.scale,
+    }
+}
+pub fn calculate_liquidation_preview(
+    account: &ExchangeAccount,
+    assets_list: &AssetsList,
+    user_debt: Decimal,
+    health_factor: Decimal,
+) -> Result<LiquidationPreview> {
+    let max_debt = calculate_max_debt_in_usd(account, assets_list)?;
+    let max_borrow = max_debt.mul(health_factor);
+    // liquidation prices are estimated for spot prices
+    let max_liquidation_debt =
+        calculate_max_debt_in_usd_for_liquidation(account, assets_list, PriceSource::Spot as u8)?;
+    let head = account.head as usize;
+
+    let mut collaterals_value = Decimal::from_usd(0);
+    let mut collaterals = Vec::with_capacity(head);
+    for collateral_entry in account.collaterals[..head].iter() {
+        let collateral =
+            &assets_list.collaterals[assets_list.get_collateral_index(collateral_entry)?];
+        let asset = &assets_list.assets[collateral.asset_index as usize];
+        let amount = Decimal {
+            val: collateral_entry.amount.into(),
+            scale: collateral.reserve_balance.scale,
+        };
+        collaterals_value = collaterals_value
+            .add(calculate_value_in_usd(asset.price, amount))
+            .unwrap();
+
+        // Price for which max_debt drops to user_debt with other prices unchanged
+        let weighted_amount = amount.mul(get_liquidation_threshold(collateral));
+        let entry_max_debt = asset.price.mul(weighted_amount).to_usd();
+        let other_max_debt = max_liquidation_debt.sub(entry_max_debt).unwrap();
+        let liquidation_price =
+            match user_debt.gt(other_max_debt).unwrap() && weighted_amount.val != 0 {
+                true => user_debt
+                    .sub(other_max_debt)
+                    .unwrap()
+                    .div_to_scale(weighted_amount, asset.price.scale),
+                false => Decimal::new(0, asset.price.scale),
+            };
+
+        // Collateral without collateral ratio does not back any debt
+        let max_withdrawable = match collateral.collateral_ratio.val == 0 {
+            true => amount,
+            false => {
+                let max_withdrawable_in_usd = calculate_max_withdraw_in_usd(
+                    max_borrow,
+                    user_debt,
+                    collateral.collateral_ratio,
+                    health_factor,
+                );
+                let max_withdrawable_in_token =
+                    usd_to_token_amount(asset, max_withdrawable_in_usd, amount.scale);
+                match max_withdrawable_in_token.gt(amount).unwrap() {
+                    true => amount,
+                    false => max_withdrawable_in_token,
+                }
+            }
+        };
+
+        collaterals.push(CollateralPreview {
+            collateral_address: collateral.collateral_address,
+            liquidation_price,
+            max_withdrawable,
+        });
+    }
+
+    let collateral_ratio = match user_debt.val == 0 {
+        true => Decimal::from_percent(0),
+        false => collaterals_value.div_to_scale(user_debt, UNIFIED_PERCENT_SCALE),
+    };
+    let max_mintable = match max_borrow.gt(user_debt).unwrap() {
+        true => max_borrow.sub(user_debt).unwrap(),
+        false => Decimal::from_usd(0),
+    };
+
+    Ok(LiquidationPreview {
+        owner: account.owner,
+        collateral_ratio,
+        max_mintable,
+        collaterals,
+    })
+}
+pub fn is_liquidation_critical(
+    max_debt: Decimal,
+    user_debt: Decimal,
+    critical_ratio: Decimal,
+) -> bool {
+    // max_debt / user_debt < critical_ratio
+    max_debt.lt(user_debt.mul(critical_ratio)).unwrap()
 }
 
 #[cfg(test)]
@@ -511,6 +827,17 @@ mod tests {
                 Ok(debt) => assert_eq!(debt, Decimal::from_integer(3850).to_usd()),
                 Err(_) => assert!(false, "Shouldn't check"),
             }
+            // conservative debt 4500 USD - higher of price and twap
+            let conservative_debt = calculate_debt_by_price_source(
+                &assets_ref,
+                slot,
+                100,
+                PriceSource::Conservative as u8,
+            );
+            assert_eq!(
+                conservative_debt.unwrap(),
+                Decimal::from_integer(4500).to_usd()
+            );
         }
         {
             let slot = 100;
@@ -831,7 +1158,7 @@ mod tests {
             let exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list);
+            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list).unwrap();
             assert_eq!(result, Decimal::from_integer(0).to_usd());
         }
         // Simple calculations
@@ -839,13 +1166,15 @@ mod tests {
             let mut exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            exchange_account.append(CollateralEntry {
-                amount: 1 * 10u64.pow(6),
-                index: 0,
-                ..Default::default()
-            });
-
-            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list);
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1 * 10u64.pow(6),
+                    index: 0,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list).unwrap();
             assert_eq!(result, Decimal::from_integer(1).to_usd());
         }
         // Multiple collaterals
@@ -854,25 +1183,31 @@ mod tests {
                 ..Default::default()
             };
             // 1 * 50000 * 0.5
-            exchange_account.append(CollateralEntry {
-                amount: 1 * 10u64.pow(6),
-                index: 0,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1 * 10u64.pow(6),
+                    index: 0,
+                    ..Default::default()
+                })
+                .unwrap();
             // 1 * 2 * 0.5
-            exchange_account.append(CollateralEntry {
-                amount: 1 * 10u64.pow(8),
-                index: 1,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1 * 10u64.pow(8),
+                    index: 1,
+                    ..Default::default()
+                })
+                .unwrap();
             // 1 * 25 * 0.12
-            exchange_account.append(CollateralEntry {
-                amount: 1 * 10u64.pow(4),
-                index: 2,
-                ..Default::default()
-            });
-
-            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list);
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1 * 10u64.pow(4),
+                    index: 2,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list).unwrap();
             assert_eq!(result, Decimal::from_integer(25_004).to_usd());
         }
         // Small numbers
@@ -881,19 +1216,23 @@ mod tests {
                 ..Default::default()
             };
             // 1
-            exchange_account.append(CollateralEntry {
-                amount: 1,
-                index: 0,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1,
+                    index: 0,
+                    ..Default::default()
+                })
+                .unwrap();
             // 500
-            exchange_account.append(CollateralEntry {
-                amount: 1,
-                index: 2,
-                ..Default::default()
-            });
-
-            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list);
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1,
+                    index: 2,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list).unwrap();
             assert_eq!(result, Decimal::from_usd(301));
         }
         // Rounding down
@@ -901,13 +1240,15 @@ mod tests {
             let mut exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            exchange_account.append(CollateralEntry {
-                amount: 1,
-                index: 3,
-                ..Default::default()
-            });
-
-            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list);
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 1,
+                    index: 3,
+                    ..Default::default()
+                })
+                .unwrap();
+
+            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list).unwrap();
             // 0.9
             assert_eq!(result, Decimal::from_integer(0).to_usd());
         }
@@ -1427,16 +1768,239 @@ mod tests {
         let collateral_ratio = Decimal::from_percent(70);
 
         let borrow_limit = calculate_vault_borrow_limit(
-            btc_asset,
+            &[(btc_asset, collateral_amount, collateral_ratio)],
             xusd_asset,
             xusd_synthetic,
-            collateral_amount,
-            collateral_ratio,
         );
         let expected_borrow_limit = Decimal::new(698068, 1).to_usd();
         assert_eq!(borrow_limit, expected_borrow_limit);
     }
 
+    #[test]
+    fn test_calculate_vault_leverage() {
+        let usdc_asset = Asset {
+            price: Decimal::from_integer(1).to_price(),
+            ..Default::default()
+        };
+        let xusd_synthetic = Synthetic {
+            supply: Decimal::from_integer(0).to_usd(),
+            ..Default::default()
+        };
+        let collateral_ratio = Decimal::from_percent(80);
+        let collateral_amount = Decimal::from_integer(100).to_usd();
+        let synthetic_amount = Decimal::from_integer(0).to_usd();
+        let collaterals = [(usdc_asset, collateral_amount, collateral_ratio)];
+        // swapline route without fee
+        let swapline_out = |amount: Decimal| Ok(amount);
+
+        // single iteration borrows up to target ratio
+        {
+            let (borrowed, collateral_out) = calculate_vault_leverage(
+                &collaterals,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(50),
+                1,
+                swapline_out,
+            )
+            .unwrap();
+            assert_eq!(borrowed, Decimal::from_integer(50).to_usd());
+            assert_eq!(collateral_out, Decimal::from_integer(50).to_usd());
+        }
+        // more iterations increase leverage
+        {
+            let (borrowed, collateral_out) = calculate_vault_leverage(
+                &collaterals,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(50),
+                3,
+                swapline_out,
+            )
+            .unwrap();
+            // 50 + 25 + 12.5
+            assert_eq!(borrowed, Decimal::new(875, 1).to_usd());
+            assert_eq!(collateral_out, Decimal::new(875, 1).to_usd());
+        }
+        // swapline fee reduces received collateral
+        {
+            let fee = Decimal::from_percent(1);
+            let (borrowed, collateral_out) = calculate_vault_leverage(
+                &collaterals,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(50),
+                2,
+                |amount: Decimal| amount.sub(amount.mul(fee)),
+            )
+            .unwrap();
+            // 50 + (149.5 * 0.5 - 50)
+            assert_eq!(borrowed, Decimal::new(7475, 2).to_usd());
+            // 49.5 + 24.5025
+            assert_eq!(collateral_out, Decimal::new(740025, 4).to_usd());
+        }
+        // swap route at other price
+        {
+            let (borrowed, collateral_out) = calculate_vault_leverage(
+                &collaterals,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(50),
+                2,
+                |amount: Decimal| Ok(amount.mul(Decimal::from_percent(50))),
+            )
+            .unwrap();
+            // 50 + (125 * 0.5 - 50)
+            assert_eq!(borrowed, Decimal::new(625, 1).to_usd());
+            // 25 + 6.25
+            assert_eq!(collateral_out, Decimal::new(3125, 2).to_usd());
+        }
+        // basket collaterals count in target and limit
+        {
+            let eth_asset = Asset {
+                price: Decimal::from_integer(2000).to_price(),
+                ..Default::default()
+            };
+            let basket = [
+                (usdc_asset, collateral_amount, collateral_ratio),
+                (
+                    eth_asset,
+                    Decimal::new(1, 1).to_usd(),
+                    Decimal::from_percent(50),
+                ),
+            ];
+            let (borrowed, collateral_out) = calculate_vault_leverage(
+                &basket,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(50),
+                1,
+                swapline_out,
+            )
+            .unwrap();
+            // (100 + 0.1 * 2000) * 50%
+            assert_eq!(borrowed, Decimal::from_integer(150).to_usd());
+            assert_eq!(collateral_out, Decimal::from_integer(150).to_usd());
+
+            // 100 * 80% + 200 * 50% = 180 < 300 * 70%
+            let result = calculate_vault_leverage(
+                &basket,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(70),
+                1,
+                swapline_out,
+            );
+            assert!(result.is_err());
+        }
+        // debt already above target ratio
+        {
+            let (borrowed, collateral_out) = calculate_vault_leverage(
+                &collaterals,
+                usdc_asset,
+                xusd_synthetic,
+                Decimal::from_integer(60).to_usd(),
+                Decimal::from_percent(50),
+                3,
+                swapline_out,
+            )
+            .unwrap();
+            assert_eq!(borrowed, Decimal::from_integer(0).to_usd());
+            assert_eq!(collateral_out, Decimal::from_integer(0).to_usd());
+        }
+        // target ratio above collateral ratio
+        {
+            let result = calculate_vault_leverage(
+                &collaterals,
+                usdc_asset,
+                xusd_synthetic,
+                synthetic_amount,
+                Decimal::from_percent(90),
+                1,
+                swapline_out,
+            );
+            assert!(result.is_err());
+        }
+    }
+
+    #[test]
+    fn test_calculate_swapline_amount_in() {
+        let fee = Decimal::from_percent(1);
+        // same scale
+        {
+            let amount_out = Decimal::from_integer(100).to_usd();
+            let amount_in = calculate_swapline_amount_in(amount_out, fee, 6).unwrap();
+            // 100 / 0.99 = 101.0101010...
+            assert_eq!(amount_in, Decimal::new(101010102, 6));
+            let fee_amount = amount_in.mul(fee);
+            assert!(amount_in.sub(fee_amount).unwrap().gte(amount_out).unwrap());
+        }
+        // synthetic with more decimals than native token
+        {
+            let amount_out = Decimal::new(123456789, 8);
+            let amount_in = calculate_swapline_amount_in(amount_out, fee, 6).unwrap();
+            // 1.234568 / 0.99 = 1.2470383...
+            assert_eq!(amount_in, Decimal::new(1247039, 6));
+        }
+        // no fee
+        {
+            let amount_out = Decimal::from_integer(100).to_usd();
+            let amount_in =
+                calculate_swapline_amount_in(amount_out, Decimal::from_percent(0), 6).unwrap();
+            assert_eq!(amount_in, amount_out);
+        }
+    }
+    #[test]
+    fn test_calculate_swap_in_amount() {
+        let usd_decimal = 6;
+        let asset_usd = Asset {
+            price: Decimal::from_integer(1).to_price(),
+            ..Default::default()
+        };
+        let btc_decimal = 8;
+        let asset_btc = Asset {
+            price: Decimal::from_integer(50000).to_price(),
+            ..Default::default()
+        };
+        let fee = Decimal::from_unified_percent(300);
+        {
+            let amount_out = Decimal::new(99700000, btc_decimal);
+            let (amount_in, swap_fee) =
+                calculate_swap_in_amount(&asset_usd, &asset_btc, usd_decimal, amount_out, fee)
+                    .unwrap();
+            // 49850 / 0.997 = 50000 USD
+            assert_eq!(amount_in, Decimal::from_integer(50000).to_usd());
+            assert_eq!(swap_fee, Decimal::from_integer(150).to_usd());
+        }
+        // swapping amount in gives at least amount out
+        {
+            let amount_out = Decimal::new(123456789, btc_decimal);
+            let (amount_in, _) =
+                calculate_swap_in_amount(&asset_usd, &asset_btc, usd_decimal, amount_out, fee)
+                    .unwrap();
+            let (swapped, _) =
+                calculate_swap_out_amount(&asset_usd, &asset_btc, btc_decimal, amount_in, fee)
+                    .unwrap();
+            assert!(swapped.gte(amount_out).unwrap());
+        }
+        {
+            let amount_out = Decimal::new(777_777_777, usd_decimal);
+            let (amount_in, _) =
+                calculate_swap_in_amount(&asset_btc, &asset_usd, btc_decimal, amount_out, fee)
+                    .unwrap();
+            let (swapped, _) =
+                calculate_swap_out_amount(&asset_btc, &asset_usd, usd_decimal, amount_in, fee)
+                    .unwrap();
+            assert!(swapped.gte(amount_out).unwrap());
+        }
+    }
+
     #[test]
     fn test_calculate_vault_withdraw_limit() {
         let btc_asset = Asset {
@@ -1457,11 +2021,10 @@ mod tests {
             let xsol_amount = Decimal::from_integer(300).to_scale(xsol_decimal);
 
             let vault_withdraw_limit = calculate_vault_withdraw_limit(
-                btc_asset,
+                &[(btc_asset, btc_amount, btc_xsol_collateral_ratio)],
                 xsol_asset,
-                btc_amount,
                 xsol_amount,
-                btc_xsol_collateral_ratio,
+                0,
             )
             .unwrap();
 
@@ -1480,11 +2043,10 @@ mod tests {
             let xsol_amount = Decimal::from_integer(1000).to_scale(xsol_decimal);
 
             let vault_withdraw_limit = calculate_vault_withdraw_limit(
-                btc_asset,
+                &[(btc_asset, btc_amount, btc_xsol_collateral_ratio)],
                 xsol_asset,
-                btc_amount,
                 xsol_amount,
-                btc_xsol_collateral_ratio,
+                0,
             );
             // should contain error
             assert!(vault_withdraw_limit.is_err())
@@ -1494,11 +2056,10 @@ mod tests {
             let btc_amount = Decimal::from_integer(1).to_scale(btc_decimal);
             let xsol_amount = Decimal::new(0, xsol_decimal);
             let vault_withdraw_limit = calculate_vault_withdraw_limit(
-                btc_asset,
+                &[(btc_asset, btc_amount, btc_xsol_collateral_ratio)],
                 xsol_asset,
-                btc_amount,
                 xsol_amount,
-                btc_xsol_collateral_ratio,
+                0,
             )
             .unwrap();
 
@@ -1506,4 +2067,582 @@ mod tests {
             assert_eq!(vault_withdraw_limit, btc_amount);
         }
     }
+
+    #[test]
+    fn test_calculate_vault_borrow_limit_of_basket() {
+        let btc_asset = Asset {
+            price: Decimal::from_integer(47598).to_price(),
+            ..Default::default()
+        };
+        let usdc_asset = Asset {
+            price: Decimal::from_integer(1).to_price(),
+            ..Default::default()
+        };
+        let xsol_asset = Asset {
+            price: Decimal::from_integer(67).to_price(),
+            ..Default::default()
+        };
+        let xsol_synthetic = Synthetic {
+            supply: Decimal::new(0, 9),
+            ..Default::default()
+        };
+        let btc_amount = Decimal::new(12, 1).to_scale(8);
+        let usdc_amount = Decimal::from_integer(1000).to_scale(6);
+        let btc_ratio = Decimal::from_percent(65);
+        let usdc_ratio = Decimal::from_percent(80);
+
+        // BTC and USDC basket
+        {
+            let basket = [
+                (btc_asset, btc_amount, btc_ratio),
+                (usdc_asset, usdc_amount, usdc_ratio),
+            ];
+            let borrow_limit = calculate_vault_borrow_limit(&basket, xsol_asset, xsol_synthetic);
+
+            // BTC weighted value = 37126.44 USD
+            // USDC weighted value = 800 USD
+            // max borrow = 37926.44 / 67 = 566.066268656 xSOL
+            let expected_borrow_limit = Decimal::new(566066268656, 9);
+            assert_eq!(borrow_limit, expected_borrow_limit);
+        }
+        // empty basket
+        {
+            let borrow_limit = calculate_vault_borrow_limit(&[], xsol_asset, xsol_synthetic);
+            assert_eq!(borrow_limit, Decimal::new(0, 9));
+        }
+    }
+
+    #[test]
+    fn test_calculate_vault_withdraw_limit_of_basket() {
+        let btc_asset = Asset {
+            price: Decimal::from_integer(47598).to_price(),
+            ..Default::default()
+        };
+        let usdc_asset = Asset {
+            price: Decimal::from_integer(1).to_price(),
+            ..Default::default()
+        };
+        let xsol_asset = Asset {
+            price: Decimal::from_integer(67).to_price(),
+            ..Default::default()
+        };
+        let btc_amount = Decimal::new(12, 1).to_scale(8);
+        let usdc_amount = Decimal::from_integer(1000).to_scale(6);
+        let basket = [
+            (btc_asset, btc_amount, Decimal::from_percent(65)),
+            (usdc_asset, usdc_amount, Decimal::from_percent(80)),
+        ];
+
+        // withdraw BTC
+        {
+            let xsol_amount = Decimal::from_integer(300).to_scale(9);
+            let withdraw_limit =
+                calculate_vault_withdraw_limit(&basket, xsol_asset, xsol_amount, 0).unwrap();
+
+            // max debt value = 37926.44 USD
+            // current borrow value = 20100 USD
+            // max withdraw value = 17826.44 / 0.65 = 27425.292307 USD
+            // max withdraw amount = 0.57618581 BTC
+            let expected_withdraw_limit = Decimal::new(57618581, 8);
+            assert_eq!(withdraw_limit, expected_withdraw_limit);
+        }
+        // withdraw limit capped by deposited USDC
+        {
+            let xsol_amount = Decimal::from_integer(300).to_scale(9);
+            let withdraw_limit =
+                calculate_vault_withdraw_limit(&basket, xsol_asset, xsol_amount, 1).unwrap();
+            assert_eq!(withdraw_limit, usdc_amount);
+        }
+        // value withdraw limit error
+        {
+            let xsol_amount = Decimal::from_integer(1000).to_scale(9);
+            let withdraw_limit =
+                calculate_vault_withdraw_limit(&basket, xsol_asset, xsol_amount, 0);
+            assert!(withdraw_limit.is_err());
+        }
+        // collateral without weight is always withdrawable
+        {
+            let basket = [
+                (btc_asset, btc_amount, Decimal::from_percent(65)),
+                (usdc_asset, usdc_amount, Decimal::from_percent(0)),
+            ];
+            let xsol_amount = Decimal::from_integer(500).to_scale(9);
+            let withdraw_limit =
+                calculate_vault_withdraw_limit(&basket, xsol_asset, xsol_amount, 1).unwrap();
+            assert_eq!(withdraw_limit, usdc_amount);
+        }
+    }
+
+    #[test]
+    fn test_calculate_liquidation_seized_collateral() {
+        let asset = Asset {
+            price: Decimal::from_integer(2).to_price(),
+            ..Default::default()
+        };
+        let penalty = Decimal::from_percent(10);
+        // collateral covers liquidation
+        {
+            let collateral_amount = Decimal::from_integer(100).to_scale(6);
+            let liquidation_amount = Decimal::from_integer(50).to_usd();
+            let (seized_collateral, liquidated_amount) = calculate_liquidation_seized_collateral(
+                &asset,
+                collateral_amount,
+                liquidation_amount,
+                penalty,
+            );
+            // 50 USD + 10% = 55 USD -> 27.5 tokens
+            assert_eq!(seized_collateral, Decimal::new(275, 1).to_scale(6));
+            assert_eq!(liquidated_amount, liquidation_amount);
+        }
+        // collateral too small
+        {
+            let collateral_amount = Decimal::from_integer(20).to_scale(6);
+            let liquidation_amount = Decimal::from_integer(50).to_usd();
+            let (seized_collateral, liquidated_amount) = calculate_liquidation_seized_collateral(
+                &asset,
+                collateral_amount,
+                liquidation_amount,
+                penalty,
+            );
+            // whole collateral is seized for its value
+            assert_eq!(seized_collateral, collateral_amount);
+            assert_eq!(liquidated_amount, Decimal::from_integer(40).to_usd());
+        }
+    }
+    #[test]
+    fn test_calculate_deprecated_ratio() {
+        let initial_ratio = Decimal::from_percent(80);
+        let deprecation_start = 100;
+        let deprecation_length = 1000;
+        // deprecation just started
+        {
+            let ratio = calculate_deprecated_ratio(
+                initial_ratio,
+                deprecation_start,
+                deprecation_length,
+                100,
+            );
+            assert_eq!(ratio, initial_ratio);
+        }
+        // half of deprecation
+        {
+            let ratio = calculate_deprecated_ratio(
+                initial_ratio,
+                deprecation_start,
+                deprecation_length,
+                600,
+            );
+            assert_eq!(ratio, Decimal::from_percent(40));
+        }
+        // rounding down
+        {
+            let ratio = calculate_deprecated_ratio(
+                initial_ratio,
+                deprecation_start,
+                deprecation_length,
+                433,
+            );
+            // 80% * 0.667 = 53.36%
+            assert_eq!(ratio, Decimal::from_unified_percent(53360));
+        }
+        // deprecation finished
+        {
+            let ratio = calculate_deprecated_ratio(
+                initial_ratio,
+                deprecation_start,
+                deprecation_length,
+                1100,
+            );
+            assert_eq!(ratio, Decimal::from_percent(0));
+        }
+        // slot before deprecation start
+        {
+            let ratio =
+                calculate_deprecated_ratio(initial_ratio, deprecation_start, deprecation_length, 0);
+            assert_eq!(ratio, initial_ratio);
+        }
+        // zero length
+        {
+            let ratio = calculate_deprecated_ratio(initial_ratio, deprecation_start, 0, 100);
+            assert_eq!(ratio, Decimal::from_percent(0));
+        }
+    }
+    #[test]
+    fn test_calculate_liquidation_auction_discount() {
+        let start_discount = Decimal::from_percent(1);
+        let max_discount = Decimal::from_percent(10);
+        let auction_length = 1000;
+        // auction just started
+        {
+            let discount = calculate_liquidation_auction_discount(
+                start_discount,
+                max_discount,
+                auction_length,
+                0,
+            );
+            assert_eq!(discount, start_discount);
+        }
+        // half of auction
+        {
+            let discount = calculate_liquidation_auction_discount(
+                start_discount,
+                max_discount,
+                auction_length,
+                500,
+            );
+            // 1% + 9% * 0.5 = 5.5%
+            assert_eq!(discount, Decimal::new(55, 3).to_percent());
+        }
+        // rounding down
+        {
+            let discount = calculate_liquidation_auction_discount(
+                start_discount,
+                max_discount,
+                auction_length,
+                333,
+            );
+            // 1% + 9% * 0.333 = 3.997%
+            assert_eq!(discount, Decimal::from_unified_percent(3997));
+        }
+        // auction finished
+        {
+            let discount = calculate_liquidation_auction_discount(
+                start_discount,
+                max_discount,
+                auction_length,
+                2000,
+            );
+            assert_eq!(discount, max_discount);
+        }
+        // zero length auction
+        {
+            let discount =
+                calculate_liquidation_auction_discount(start_discount, max_discount, 0, 0);
+            assert_eq!(discount, max_discount);
+        }
+    }
+
+    #[test]
+    fn test_calculate_max_debt_in_usd_for_liquidation() {
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        // SNY without liquidation threshold
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(2).to_price(),
+            twap: Decimal::from_integer(2).to_price(),
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            reserve_balance: Decimal::from_integer(0).to_scale(6),
+            collateral_ratio: Decimal::from_percent(50),
+            asset_index: assets_list.head_assets as u8 - 1,
+            ..Default::default()
+        });
+        // BTC
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(50_000).to_price(),
+            twap: Decimal::from_integer(40_000).to_price(),
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            reserve_balance: Decimal::from_integer(0).to_scale(8),
+            collateral_ratio: Decimal::from_percent(50),
+            liquidation_threshold: Decimal::from_percent(80),
+            asset_index: assets_list.head_assets as u8 - 1,
+            ..Default::default()
+        });
+
+        // No collaterals
+        {
+            let exchange_account = ExchangeAccount {
+                ..Default::default()
+            };
+            let result = calculate_max_debt_in_usd_for_liquidation(
+                &exchange_account,
+                &assets_list,
+                PriceSource::Spot as u8,
+            )
+            .unwrap();
+            assert_eq!(result, Decimal::from_integer(0).to_usd());
+        }
+        // Threshold falls back to collateral ratio
+        {
+            let mut exchange_account = ExchangeAccount {
+                ..Default::default()
+            };
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 100 * 10u64.pow(6),
+                    index: 0,
+                    ..Default::default()
+                })
+                .unwrap();
+            // 100 * 2 * 0.5
+            let result = calculate_max_debt_in_usd_for_liquidation(
+                &exchange_account,
+                &assets_list,
+                PriceSource::Spot as u8,
+            )
+            .unwrap();
+            assert_eq!(result, Decimal::from_integer(100).to_usd());
+        }
+        // Multiple collaterals
+        {
+            let mut exchange_account = ExchangeAccount {
+                ..Default::default()
+            };
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 100 * 10u64.pow(6),
+                    index: 0,
+                    ..Default::default()
+                })
+                .unwrap();
+            exchange_account
+                .append(CollateralEntry {
+                    amount: 10u64.pow(6),
+                    index: 1,
+                    ..Default::default()
+                })
+                .unwrap();
+            // 100 * 2 * 0.5 + 0.01 * 50000 * 0.8
+            let result = calculate_max_debt_in_usd_for_liquidation(
+                &exchange_account,
+                &assets_list,
+                PriceSource::Spot as u8,
+            )
+            .unwrap();
+            assert_eq!(result, Decimal::from_integer(500).to_usd());
+            // minting still uses collateral ratio
+            let result = calculate_max_debt_in_usd(&exchange_account, &assets_list).unwrap();
+            assert_eq!(result, Decimal::from_integer(350).to_usd());
+            // 100 * 2 * 0.5 + 0.01 * 40000 * 0.8
+            let result = calculate_max_debt_in_usd_for_liquidation(
+                &exchange_account,
+                &assets_list,
+                PriceSource::Twap as u8,
+            )
+            .unwrap();
+            assert_eq!(result, Decimal::from_integer(420).to_usd());
+            // lower of price and twap
+            let result = calculate_max_debt_in_usd_for_liquidation(
+                &exchange_account,
+                &assets_list,
+                PriceSource::Conservative as u8,
+            )
+            .unwrap();
+            assert_eq!(result, Decimal::from_integer(420).to_usd());
+        }
+    }
+    #[test]
+    fn test_get_price_by_source() {
+        let asset = Asset {
+            price: Decimal::from_integer(100).to_price(),
+            twap: Decimal::from_integer(90).to_price(),
+            ..Default::default()
+        };
+        let spot = PriceSource::Spot as u8;
+        let twap = PriceSource::Twap as u8;
+        let conservative = PriceSource::Conservative as u8;
+
+        assert_eq!(get_price_by_source(&asset, spot, false), asset.price);
+        assert_eq!(get_price_by_source(&asset, spot, true), asset.price);
+        assert_eq!(get_price_by_source(&asset, twap, false), asset.twap);
+        assert_eq!(get_price_by_source(&asset, twap, true), asset.twap);
+        // collateral valued lower, debt higher
+        assert_eq!(get_price_by_source(&asset, conservative, false), asset.twap);
+        assert_eq!(get_price_by_source(&asset, conservative, true), asset.price);
+        // twap above price
+        {
+            let asset = Asset {
+                twap: Decimal::from_integer(110).to_price(),
+                ..asset
+            };
+            assert_eq!(
+                get_price_by_source(&asset, conservative, false),
+                asset.price
+            );
+            assert_eq!(get_price_by_source(&asset, conservative, true), asset.twap);
+        }
+        // empty twap
+        {
+            let asset = Asset {
+                twap: Decimal::from_price(0),
+                ..asset
+            };
+            assert_eq!(get_price_by_source(&asset, twap, false), asset.price);
+            assert_eq!(get_price_by_source(&asset, twap, true), asset.price);
+            assert_eq!(
+                get_price_by_source(&asset, conservative, false),
+                asset.price
+            );
+            assert_eq!(get_price_by_source(&asset, conservative, true), asset.price);
+        }
+    }
+    #[test]
+    fn test_calculate_liquidation_preview() {
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        // SNY
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(2).to_price(),
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            reserve_balance: Decimal::from_integer(0).to_scale(6),
+            collateral_ratio: Decimal::from_percent(50),
+            asset_index: assets_list.head_assets as u8 - 1,
+            ..Default::default()
+        });
+        // BTC
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(50_000).to_price(),
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            reserve_balance: Decimal::from_integer(0).to_scale(8),
+            collateral_ratio: Decimal::from_percent(50),
+            asset_index: assets_list.head_assets as u8 - 1,
+            ..Default::default()
+        });
+
+        let mut exchange_account = ExchangeAccount {
+            ..Default::default()
+        };
+        // 100 SNY worth 200 USD
+        exchange_account
+            .append(CollateralEntry {
+                amount: 100 * 10u64.pow(6),
+                index: 0,
+                ..Default::default()
+            })
+            .unwrap();
+        // 0.01 BTC worth 500 USD
+        exchange_account
+            .append(CollateralEntry {
+                amount: 10u64.pow(6),
+                index: 1,
+                ..Default::default()
+            })
+            .unwrap();
+        let health_factor = Decimal::from_percent(50);
+
+        // No debt
+        {
+            let user_debt = Decimal::from_usd(0);
+            let result = calculate_liquidation_preview(
+                &exchange_account,
+                &assets_list,
+                user_debt,
+                health_factor,
+            )
+            .unwrap();
+            assert_eq!(result.collateral_ratio, Decimal::from_percent(0));
+            // 350 * 0.5
+            assert_eq!(result.max_mintable, Decimal::from_integer(175).to_usd());
+            assert_eq!(result.collaterals.len(), 2);
+            assert_eq!(result.collaterals[0].liquidation_price.val, 0);
+            assert_eq!(result.collaterals[1].liquidation_price.val, 0);
+            assert_eq!(
+                result.collaterals[0].max_withdrawable.val,
+                100 * 10u128.pow(6)
+            );
+            assert_eq!(result.collaterals[1].max_withdrawable.val, 10u128.pow(6));
+        }
+        // Safe account
+        {
+            let user_debt = Decimal::from_integer(150).to_usd();
+            let result = calculate_liquidation_preview(
+                &exchange_account,
+                &assets_list,
+                user_debt,
+                health_factor,
+            )
+            .unwrap();
+            // 700 / 150
+            assert_eq!(
+                result.collateral_ratio,
+                Decimal::new(466666, UNIFIED_PERCENT_SCALE)
+            );
+            // 175 - 150
+            assert_eq!(result.max_mintable, Decimal::from_integer(25).to_usd());
+            // BTC alone covers debt
+            assert_eq!(result.collaterals[0].liquidation_price.val, 0);
+            // (150 - 100) / (0.01 * 0.5)
+            assert_eq!(
+                result.collaterals[1].liquidation_price,
+                Decimal::from_integer(10_000).to_price()
+            );
+            // 25 / 0.5 / 0.5 = 100 USD
+            assert_eq!(
+                result.collaterals[0].max_withdrawable,
+                Decimal::from_integer(50).to_scale(6)
+            );
+            assert_eq!(
+                result.collaterals[1].max_withdrawable,
+                Decimal::new(2, 3).to_scale(8)
+            );
+        }
+        // Debt above max borrow
+        {
+            let user_debt = Decimal::from_integer(200).to_usd();
+            let result = calculate_liquidation_preview(
+                &exchange_account,
+                &assets_list,
+                user_debt,
+                health_factor,
+            )
+            .unwrap();
+            // 700 / 200
+            assert_eq!(result.collateral_ratio, Decimal::from_percent(350));
+            assert_eq!(result.max_mintable, Decimal::from_usd(0));
+            // (200 - 100) / (0.01 * 0.5)
+            assert_eq!(
+                result.collaterals[1].liquidation_price,
+                Decimal::from_integer(20_000).to_price()
+            );
+            assert_eq!(result.collaterals[0].max_withdrawable.val, 0);
+            assert_eq!(result.collaterals[1].max_withdrawable.val, 0);
+        }
+    }
+    #[test]
+    fn test_is_liquidation_critical() {
+        let user_debt = Decimal::from_usd(1_000_000_000);
+        let critical_ratio = Decimal::from_percent(50);
+        // above critical ratio
+        {
+            let max_debt = Decimal::from_usd(800_000_000);
+            assert!(!is_liquidation_critical(
+                max_debt,
+                user_debt,
+                critical_ratio
+            ));
+        }
+        // exactly critical ratio
+        {
+            let max_debt = Decimal::from_usd(500_000_000);
+            assert!(!is_liquidation_critical(
+                max_debt,
+                user_debt,
+                critical_ratio
+            ));
+        }
+        // below critical ratio
+        {
+            let max_debt = Decimal::from_usd(499_999_999);
+            assert!(is_liquidation_critical(max_debt, user_debt, critical_ratio));
+        }
+        // disabled
+        {
+            let max_debt = Decimal::from_usd(0);
+            let critical_ratio = Decimal::from_percent(0);
+            assert!(!is_liquidation_critical(
+                max_debt,
+                user_debt,
+                critical_ratio
+            ));
+        }
+    }
 }
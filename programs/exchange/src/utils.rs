@@ -1,8 +1,17 @@
 use std::borrow::BorrowMut;
 use std::cell::RefMut;
-
-use crate::decimal::{Add, Compare, Div, Mul, MulUp, PowAccuracy, Sub};
-use crate::math::{calculate_compounded_interest, calculate_debt, calculate_minute_interest_rate};
+use std::mem::size_of;
+
+use crate::decimal::{
+    Add, Compare, Div, DivScale, DivUp, Mul, MulUp, PowAccuracy, Sub, XUSD_SCALE,
+};
+use crate::math::{
+    amount_to_shares_by_rounding_down, calculate_compounded_interest, calculate_debt,
+    calculate_debt_by_price_source, calculate_liquidation_auction_discount,
+    calculate_liquidation_seized_collateral, calculate_max_debt_in_usd_for_liquidation,
+    calculate_minute_interest_rate, calculate_user_debt_in_usd, calculate_value_in_usd,
+    get_price_by_source, is_liquidation_critical,
+};
 use crate::*;
 use account::*;
 
@@ -23,6 +32,30 @@ pub fn check_feed_update(
     }
     return Ok(());
 }
+pub fn check_vault_oracle(asset: &Asset, settings: &OracleSettings, slot: u64) -> Result<()> {
+    // Check staleness
+    if asset.last_update < slot.saturating_sub(settings.max_delay as u64) {
+        return Err(ErrorCode::OutdatedOracle.into());
+    }
+    // Check status
+    if settings.require_trading && asset.status != PriceStatus::Trading.into() {
+        return Err(ErrorCode::UnreliablePrice.into());
+    }
+    // Check deviation between price and twap
+    if settings.max_price_deviation.val != 0 {
+        require!(asset.twap.val != 0, UnreliablePrice);
+        let difference = match asset.price.gt(asset.twap)? {
+            true => asset.price.sub(asset.twap)?,
+            false => asset.twap.sub(asset.price)?,
+        };
+        let deviation = difference.div_to_scale(asset.twap, settings.max_price_deviation.scale);
+        require!(
+            deviation.lte(settings.max_price_deviation)?,
+            UnreliablePrice
+        );
+    }
+    Ok(())
+}
 
 pub fn div_up(a: u128, b: u128) -> u128 {
     return a
@@ -153,6 +186,29 @@ pub fn calculate_debt_with_adjustment(
     adjust_interest_debt(state, assets_list, slot, timestamp);
     Ok(calculate_debt(assets_list, slot, state.max_delay, false).unwrap())
 }
+pub fn calculate_user_debt_for_liquidation(
+    exchange_account: &ExchangeAccount,
+    assets_list: &AssetsList,
+    state: &State,
+    user_debt: Decimal,
+    slot: u64,
+) -> Result<Decimal> {
+    // spot debt is already calculated
+    if state.liquidation_price_source == PriceSource::Spot as u8 {
+        return Ok(user_debt);
+    }
+    let total_debt = calculate_debt_by_price_source(
+        assets_list,
+        slot,
+        state.max_delay,
+        state.liquidation_price_source,
+    )?;
+    Ok(calculate_user_debt_in_usd(
+        exchange_account,
+        total_debt,
+        state.debt_shares,
+    ))
+}
 
 pub fn adjust_interest_debt(
     state: &mut State,
@@ -283,6 +339,19 @@ impl VaultEntry {
         synthetic.supply = synthetic.supply.sub(burn_amount).unwrap();
         Ok(())
     }
+    pub fn release_shortfall_cascade(
+        self: &mut Self,
+        vault: &mut Vault,
+        synthetic: &mut Synthetic,
+    ) -> Decimal {
+        // Synthetic stays in circulation but stops being borrowed,
+        // so its value is moved to debt pool
+        let shortfall = self.synthetic_amount;
+        self.synthetic_amount = self.synthetic_amount.sub(shortfall).unwrap();
+        vault.mint_amount = vault.mint_amount.sub(shortfall).unwrap();
+        synthetic.borrowed_supply = synthetic.borrowed_supply.sub(shortfall).unwrap();
+        shortfall
+    }
     pub fn increase_supply_cascade(
         self: &mut Self,
         vault: &mut Vault,
@@ -303,6 +372,49 @@ impl VaultEntry {
         Ok(())
     }
 }
+pub fn get_vault_entry_collaterals(
+    vault: &Vault,
+    vault_entry: &VaultEntry,
+    assets: &[Asset],
+    collaterals: &[Collateral],
+    liquidation: bool,
+) -> Vec<(Asset, Decimal, Decimal)> {
+    // collaterals of entry as (collateral_asset, collateral_amount, collateral_ratio)
+    (0..vault.collaterals_count())
+        .map(|index| {
+            let vault_collateral = vault.get_collateral(index);
+            let collateral = collaterals
+                .iter()
+                .find(|x| x.collateral_address.eq(&vault_collateral.collateral))
+                .unwrap();
+            let collateral_asset = assets[collateral.asset_index as usize];
+            // entry could be created before collateral was added to basket
+            let collateral_amount = Decimal::new(
+                vault_entry.get_collateral_amount(index).val,
+                vault_collateral.collateral_amount.scale,
+            );
+            match liquidation {
+                true => (
+                    Asset {
+                        price: get_price_by_source(
+                            &collateral_asset,
+                            vault.liquidation_price_source,
+                            false,
+                        ),
+                        ..collateral_asset
+                    },
+                    collateral_amount,
+                    vault_collateral.liquidation_threshold,
+                ),
+                false => (
+                    collateral_asset,
+                    collateral_amount,
+                    vault_collateral.collateral_ratio,
+                ),
+            }
+        })
+        .collect()
+}
 pub fn get_user_sny_collateral_balance(
     exchange_account: &ExchangeAccount,
     sny_asset: &Collateral,
@@ -316,6 +428,508 @@ pub fn get_user_sny_collateral_balance(
         None => return Decimal::from_sny(0),
     }
 }
+pub fn cover_with_insurance_fund(
+    state: &mut State,
+    usd_synthetic: &mut Synthetic,
+    debt: Decimal,
+) -> Decimal {
+    // Insurance fund xUSD is part of supply, burning it lowers debt of all accounts
+    let covered_debt = match debt.gt(state.insurance_fund).unwrap() {
+        true => state.insurance_fund,
+        false => debt,
+    };
+    state.insurance_fund = state.insurance_fund.sub(covered_debt).unwrap();
+    usd_synthetic.supply = usd_synthetic.supply.sub(covered_debt).unwrap();
+
+    // Return debt left to socialize
+    debt.sub(covered_debt).unwrap()
+}
+pub fn get_insurance_fund_excess(state: &State) -> Decimal {
+    match state
+        .insurance_fund
+        .gt(state.insurance_fund_target)
+        .unwrap()
+    {
+        true => state
+            .insurance_fund
+            .sub(state.insurance_fund_target)
+            .unwrap(),
+        false => Decimal::from_usd(0),
+    }
+}
+pub fn withdraw_from_insurance_fund(state: &mut State, slot: u64) -> Result<u64> {
+    require!(
+        slot >= state.insurance_withdrawal_slot,
+        InsuranceWithdrawalLocked
+    );
+    // Fund could be used to cover debt since request, excess is checked again
+    let amount = state.insurance_withdrawal_amount;
+    if amount.val == 0 || amount.gt(get_insurance_fund_excess(state))? {
+        return Err(ErrorCode::InsufficientAmountAdminWithdraw.into());
+    }
+    state.insurance_fund = state.insurance_fund.sub(amount)?;
+    state.insurance_withdrawal_amount = Decimal::from_usd(0);
+
+    // Amount of xUSD tokens to mint
+    Ok(amount.to_usd().to_u64())
+}
+pub fn socialize_bad_debt(
+    state: &mut State,
+    exchange_account: &mut ExchangeAccount,
+    user_debt: Decimal,
+) {
+    // Burning shares of account spreads its debt over all remaining debt shares
+    state.debt_shares = state
+        .debt_shares
+        .checked_sub(exchange_account.debt_shares)
+        .unwrap();
+    exchange_account.debt_shares = 0;
+    state.bad_debt = state.bad_debt.add(user_debt).unwrap();
+
+    // Remove staking of account
+    state.staking.next_round.all_points = state.debt_shares;
+    state.staking.current_round.all_points = state
+        .staking
+        .current_round
+        .all_points
+        .checked_sub(exchange_account.user_staking_data.current_round_points)
+        .unwrap();
+    state.staking.finished_round.all_points = state
+        .staking
+        .finished_round
+        .all_points
+        .checked_sub(exchange_account.user_staking_data.finished_round_points)
+        .unwrap();
+    exchange_account.user_staking_data.finished_round_points = 0u64;
+    exchange_account.user_staking_data.current_round_points = 0u64;
+    exchange_account.user_staking_data.next_round_points = 0u64;
+}
+pub fn migrate_state_layout(state: &mut State) {
+    // Version 0 -> 1
+    // fields carved from padding are zeroed with scale 0, set them as in init
+    if state.version == 0 {
+        state.liquidation_auction_start_discount = Decimal::from_percent(1);
+        state.liquidation_auction_max_discount = Decimal::from_percent(10);
+        state.liquidation_auction_length = 7200;
+        state.bad_debt = Decimal::from_usd(0);
+        state.insurance_fund = Decimal::from_usd(0);
+        state.insurance_fund_target = Decimal::from_usd(0);
+        state.insurance_swap_tax_ratio = Decimal::from_percent(0);
+        state.insurance_penalty_ratio = Decimal::from_percent(0);
+        state.liquidation_critical_ratio = Decimal::from_percent(0);
+        state.liquidation_bounty = Decimal::from_sny(0);
+        state.liquidation_bounty_cooldown = 172800;
+        state.liquidation_price_source = PriceSource::Spot as u8;
+        state.version = 1;
+    }
+    // Version 1 -> 2
+    // insurance withdrawal timelock carved from padding
+    if state.version == 1 {
+        state.insurance_withdrawal_delay = 172800;
+        state.insurance_withdrawal_amount = Decimal::from_usd(0);
+        state.insurance_withdrawal_slot = 0;
+        state.version = 2;
+    }
+}
+pub fn migrate_assets_list_layout(assets_list: &mut AssetsList, legacy_data: &[u8]) -> Result<()> {
+    // legacy_data is content of assets list with LegacyCollateral layout without discriminator
+    // whole list does not fit on stack, entries are copied one by one
+    let assets_offset = 3;
+    let collaterals_offset = assets_offset + 255 * size_of::<Asset>();
+    let synthetics_offset = collaterals_offset + 255 * size_of::<LegacyCollateral>();
+    require!(
+        legacy_data.len() == synthetics_offset + 255 * size_of::<Synthetic>(),
+        InvalidAccount
+    );
+
+    assets_list.head_assets = legacy_data[0];
+    assets_list.head_collaterals = legacy_data[1];
+    assets_list.head_synthetics = legacy_data[2];
+    for i in 0..assets_list.head_assets as usize {
+        let offset = assets_offset + i * size_of::<Asset>();
+        assets_list.assets[i] =
+            unsafe { std::ptr::read_unaligned(legacy_data[offset..].as_ptr() as *const Asset) };
+    }
+    for i in 0..assets_list.head_synthetics as usize {
+        let offset = synthetics_offset + i * size_of::<Synthetic>();
+        assets_list.synthetics[i] =
+            unsafe { std::ptr::read_unaligned(legacy_data[offset..].as_ptr() as *const Synthetic) };
+    }
+    for i in 0..assets_list.head_collaterals as usize {
+        let offset = collaterals_offset + i * size_of::<LegacyCollateral>();
+        let legacy_collateral = unsafe {
+            std::ptr::read_unaligned(legacy_data[offset..].as_ptr() as *const LegacyCollateral)
+        };
+        // Fields added after legacy layout are set as in add_collateral
+        assets_list.collaterals[i] = Collateral {
+            asset_index: legacy_collateral.asset_index,
+            collateral_address: legacy_collateral.collateral_address,
+            reserve_address: legacy_collateral.reserve_address,
+            liquidation_fund: legacy_collateral.liquidation_fund,
+            reserve_balance: legacy_collateral.reserve_balance,
+            collateral_ratio: legacy_collateral.collateral_ratio,
+            max_collateral: legacy_collateral.max_collateral,
+            liquidation_mode: LiquidationMode::Fixed as u8,
+            liquidation_threshold: legacy_collateral.collateral_ratio,
+            deprecated: false,
+            deprecation_start: 0,
+            deprecation_length: 0,
+            initial_collateral_ratio: Decimal::from_percent(0),
+            initial_liquidation_threshold: Decimal::from_percent(0),
+            // legacy vaults are counted by migrate_vault
+            vault_count: 0,
+        };
+    }
+    Ok(())
+}
+pub fn migrate_exchange_account_layout(
+    exchange_account: &mut ExchangeAccount,
+    legacy_data: &[u8],
+    assets_list: &AssetsList,
+) -> Result<()> {
+    // Version 0 -> 1
+    // legacy_data is content of account stored at old seeds without discriminator
+    require!(
+        legacy_data.len() == size_of::<LegacyExchangeAccount>(),
+        InvalidAccount
+    );
+    let legacy_account =
+        unsafe { std::ptr::read_unaligned(legacy_data.as_ptr() as *const LegacyExchangeAccount) };
+    require!(legacy_account.version == 0, InvalidAccount);
+
+    exchange_account.owner = legacy_account.owner;
+    exchange_account.debt_shares = legacy_account.debt_shares;
+    exchange_account.liquidation_deadline = legacy_account.liquidation_deadline;
+    exchange_account.user_staking_data = legacy_account.user_staking_data;
+    exchange_account.head = legacy_account.head;
+    exchange_account.collaterals = legacy_account.collaterals;
+    exchange_account.delegate = Pubkey::default();
+    exchange_account.delegate_permissions = 0;
+    exchange_account.liquidation_bounty_slot = 0;
+
+    // refresh collateral indexes outdated by removal of collaterals
+    let head = exchange_account.head as usize;
+    for entry in exchange_account.collaterals[..head].iter_mut() {
+        entry.index = assets_list.get_collateral_index(entry)? as u8;
+    }
+    exchange_account.version = EXCHANGE_ACCOUNT_VERSION;
+    Ok(())
+}
+pub fn migrate_vault_layout(vault: &mut Vault, legacy_data: &[u8], state: &State) -> Result<()> {
+    // Legacy vault becomes vault of type 0
+    // legacy_data is content of account stored at old seeds without discriminator
+    require!(
+        legacy_data.len() == size_of::<LegacyVault>(),
+        InvalidAccount
+    );
+    let legacy_vault =
+        unsafe { std::ptr::read_unaligned(legacy_data.as_ptr() as *const LegacyVault) };
+
+    vault.halted = legacy_vault.halted;
+    vault.synthetic = legacy_vault.synthetic;
+    vault.collateral = legacy_vault.collateral;
+    vault.debt_interest_rate = legacy_vault.debt_interest_rate;
+    vault.collateral_ratio = legacy_vault.collateral_ratio;
+    vault.liquidation_threshold = legacy_vault.liquidation_threshold;
+    vault.liquidation_ratio = legacy_vault.liquidation_ratio;
+    vault.liquidation_penalty_liquidator = legacy_vault.liquidation_penalty_liquidator;
+    vault.liquidation_penalty_exchange = legacy_vault.liquidation_penalty_exchange;
+    vault.accumulated_interest = legacy_vault.accumulated_interest;
+    vault.accumulated_interest_rate = legacy_vault.accumulated_interest_rate;
+    vault.collateral_reserve = legacy_vault.collateral_reserve;
+    vault.mint_amount = legacy_vault.mint_amount;
+    vault.collateral_amount = legacy_vault.collateral_amount;
+    vault.max_borrow = legacy_vault.max_borrow;
+    vault.last_update = legacy_vault.last_update;
+    vault.vault_type = 0;
+    // Fields added after legacy layout are set as in create_vault
+    vault.oracle_settings = OracleSettings {
+        max_delay: state.max_delay,
+        max_price_deviation: Decimal::from_percent(0),
+        require_trading: false,
+    };
+    vault.liquidation_price_source = PriceSource::Spot as u8;
+    vault.basket_head = 0;
+    Ok(())
+}
+pub fn migrate_vault_entry_layout(
+    vault_entry: &mut VaultEntry,
+    legacy_data: &[u8],
+    legacy_vault: &Pubkey,
+) -> Result<()> {
+    // legacy_data is content of account stored at old seeds without discriminator
+    require!(
+        legacy_data.len() == size_of::<LegacyVaultEntry>(),
+        InvalidAccount
+    );
+    let legacy_entry =
+        unsafe { std::ptr::read_unaligned(legacy_data.as_ptr() as *const LegacyVaultEntry) };
+    require!(legacy_entry.vault == *legacy_vault, InvalidAccount);
+
+    vault_entry.owner = legacy_entry.owner;
+    vault_entry.last_accumulated_interest_rate = legacy_entry.last_accumulated_interest_rate;
+    vault_entry.synthetic_amount = legacy_entry.synthetic_amount;
+    vault_entry.collateral_amount = legacy_entry.collateral_amount;
+    Ok(())
+}
+pub fn update_liquidation_deadline(
+    exchange_account: &mut ExchangeAccount,
+    max_debt: Decimal,
+    user_debt: Decimal,
+    liquidation_buffer: u32,
+    slot: u64,
+) -> bool {
+    // If account is undercollateralized set liquidation_deadline
+    // After liquidation_deadline slot account can be liquidated
+    if max_debt.gt(user_debt).unwrap() {
+        exchange_account.liquidation_deadline = u64::MAX;
+        return false;
+    }
+    // Return true only if account was marked in this call
+    if exchange_account.liquidation_deadline == u64::MAX {
+        exchange_account.liquidation_deadline =
+            slot.checked_add(liquidation_buffer.into()).unwrap();
+        return true;
+    }
+    false
+}
+pub fn update_account_collateralization(
+    state: &mut State,
+    exchange_account: &mut ExchangeAccount,
+    assets_list: &mut RefMut<AssetsList>,
+    slot: u64,
+    timestamp: i64,
+) -> Result<bool> {
+    // Adjust staking round
+    adjust_staking_rounds(state, slot);
+    // adjust current staking points for exchange account
+    adjust_staking_account(exchange_account, &state.staking);
+
+    let total_debt = calculate_debt_with_adjustment(state, assets_list, slot, timestamp)?;
+    let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
+    let liquidation_debt =
+        calculate_user_debt_for_liquidation(exchange_account, assets_list, state, user_debt, slot)?;
+    let max_debt = calculate_max_debt_in_usd_for_liquidation(
+        exchange_account,
+        assets_list,
+        state.liquidation_price_source,
+    )?;
+
+    // Return true only if account was marked in this call
+    Ok(update_liquidation_deadline(
+        exchange_account,
+        max_debt,
+        liquidation_debt,
+        state.liquidation_buffer,
+        slot,
+    ))
+}
+pub fn get_penalty_to_liquidator(
+    state: &State,
+    collateral: &Collateral,
+    liquidation_deadline: u64,
+    slot: u64,
+) -> Decimal {
+    // In auction mode liquidator discount grows with every slot after deadline
+    match collateral.liquidation_mode == LiquidationMode::Auction as u8 {
+        true => calculate_liquidation_auction_discount(
+            state.liquidation_auction_start_discount,
+            state.liquidation_auction_max_discount,
+            state.liquidation_auction_length,
+            // critical account can be liquidated before deadline
+            slot.saturating_sub(liquidation_deadline),
+        ),
+        false => state.penalty_to_liquidator,
+    }
+}
+
+pub fn check_liquidation(
+    exchange_account: &ExchangeAccount,
+    assets_list: &AssetsList,
+    state: &State,
+    total_debt: Decimal,
+    amount: u64,
+    slot: u64,
+) -> Result<Decimal> {
+    let user_debt = calculate_user_debt_in_usd(exchange_account, total_debt, state.debt_shares);
+    let liquidation_debt =
+        calculate_user_debt_for_liquidation(exchange_account, assets_list, state, user_debt, slot)?;
+    let max_debt = calculate_max_debt_in_usd_for_liquidation(
+        exchange_account,
+        assets_list,
+        state.liquidation_price_source,
+    )?;
+
+    // Check collateral ratio
+    if max_debt.gt(liquidation_debt).unwrap() {
+        return Err(ErrorCode::InvalidLiquidation.into());
+    }
+    // Time given user to adjust collateral ratio passed
+    // Deeply undercollateralized account can be liquidated immediately
+    let critical =
+        is_liquidation_critical(max_debt, liquidation_debt, state.liquidation_critical_ratio);
+    if !critical && exchange_account.liquidation_deadline > slot {
+        return Err(ErrorCode::LiquidationDeadline.into());
+    }
+    // Cannot payback more than liquidation_rate of user debt
+    let max_repay = user_debt.mul(state.liquidation_rate).to_usd().to_u64();
+
+    let amount: u64 = match amount {
+        u64::MAX => max_repay,
+        _ => amount,
+    };
+
+    if amount.gt(&max_repay) {
+        return Err(ErrorCode::InvalidLiquidation.into());
+    }
+    Ok(Decimal {
+        val: amount.into(),
+        scale: XUSD_SCALE,
+    })
+}
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct SeizedCollateral {
+    pub to_liquidator: Decimal,    // collateral transferred to liquidator
+    pub to_exchange: Decimal,      // collateral transferred to liquidation_fund
+    pub repaid_amount: Decimal,    // xUSD of debt covered by seized collateral
+    pub insurance_amount: Decimal, // xUSD paid by liquidator for insurance part of penalty
+}
+pub fn seize_collateral(
+    state: &State,
+    exchange_account: &mut ExchangeAccount,
+    liquidated_asset: &Asset,
+    liquidated_collateral: &mut Collateral,
+    repay_amount: Decimal,
+    slot: u64,
+) -> Result<SeizedCollateral> {
+    let exchange_account_collateral_index =
+        match exchange_account.collaterals.iter().position(|x| {
+            x.collateral_address
+                .eq(&liquidated_collateral.collateral_address)
+        }) {
+            Some(v) => v,
+            None => return Err(ErrorCode::NoAssetFound.into()),
+        };
+    let exchange_account_collateral_amount = Decimal {
+        val: exchange_account.collaterals[exchange_account_collateral_index]
+            .amount
+            .into(),
+        scale: liquidated_collateral.reserve_balance.scale,
+    };
+
+    let penalty_to_liquidator = get_penalty_to_liquidator(
+        state,
+        liquidated_collateral,
+        exchange_account.liquidation_deadline,
+        slot,
+    );
+    let (seized_collateral_in_token, repaid_amount) = calculate_liquidation_seized_collateral(
+        liquidated_asset,
+        exchange_account_collateral_amount,
+        repay_amount,
+        penalty_to_liquidator
+            .add(state.penalty_to_exchange)
+            .unwrap(),
+    );
+
+    let exchange_account_collateral =
+        &mut exchange_account.collaterals[exchange_account_collateral_index];
+    exchange_account_collateral.amount = exchange_account_collateral
+        .amount
+        .checked_sub(seized_collateral_in_token.to_u64())
+        .unwrap();
+    liquidated_collateral.reserve_balance = liquidated_collateral
+        .reserve_balance
+        .sub(seized_collateral_in_token)
+        .unwrap();
+
+    let collateral_to_exchange = seized_collateral_in_token
+        .mul(state.penalty_to_exchange)
+        .div_up(
+            Decimal::from_percent(100)
+                .add(penalty_to_liquidator)
+                .unwrap()
+                .add(state.penalty_to_exchange)
+                .unwrap(),
+        );
+    // Part of exchange penalty is sold to liquidator for xUSD kept by insurance fund
+    let collateral_to_insurance = collateral_to_exchange.mul(state.insurance_penalty_ratio);
+    let collateral_to_exchange = collateral_to_exchange.sub(collateral_to_insurance).unwrap();
+    let insurance_amount = calculate_value_in_usd(liquidated_asset.price, collateral_to_insurance);
+
+    let collateral_to_liquidator = seized_collateral_in_token
+        .sub(collateral_to_exchange)
+        .unwrap();
+
+    // Clean user collateral if empty
+    if exchange_account.collaterals[exchange_account_collateral_index].amount == 0 {
+        exchange_account.remove(exchange_account_collateral_index);
+    }
+
+    Ok(SeizedCollateral {
+        to_liquidator: collateral_to_liquidator,
+        to_exchange: collateral_to_exchange,
+        repaid_amount,
+        insurance_amount,
+    })
+}
+pub fn settle_liquidation(
+    state: &mut State,
+    exchange_account: &mut ExchangeAccount,
+    usd_synthetic: &mut Synthetic,
+    total_debt: Decimal,
+    liquidation_amount: Decimal,
+    insurance_amount: Decimal,
+) -> Result<Decimal> {
+    // Rounding down - debt is burned in favor of the system
+    let burned_debt_shares = amount_to_shares_by_rounding_down(
+        state.debt_shares,
+        total_debt.to_u64(),
+        liquidation_amount.to_u64(),
+    );
+    state.debt_shares = state.debt_shares.checked_sub(burned_debt_shares).unwrap();
+    exchange_account.debt_shares = exchange_account
+        .debt_shares
+        .checked_sub(burned_debt_shares)
+        .unwrap();
+
+    // Remove staking for liquidation
+    state.staking.next_round.all_points = state.debt_shares;
+    state.staking.current_round.all_points = state
+        .staking
+        .current_round
+        .all_points
+        .checked_sub(exchange_account.user_staking_data.current_round_points)
+        .unwrap();
+    state.staking.finished_round.all_points = state
+        .staking
+        .finished_round
+        .all_points
+        .checked_sub(exchange_account.user_staking_data.finished_round_points)
+        .unwrap();
+    exchange_account.user_staking_data.finished_round_points = 0u64;
+    exchange_account.user_staking_data.current_round_points = 0u64;
+    exchange_account.user_staking_data.next_round_points = exchange_account.debt_shares;
+
+    let new_supply = usd_synthetic.supply.sub(liquidation_amount).unwrap();
+    usd_synthetic.set_supply_safely(new_supply)?;
+    state.insurance_fund = state.insurance_fund.add(insurance_amount).unwrap();
+
+    // Debt left on account without collateral is covered by insurance fund or socialized
+    if exchange_account.head == 0 && exchange_account.debt_shares > 0 {
+        let user_debt = calculate_user_debt_in_usd(
+            exchange_account,
+            total_debt.sub(liquidation_amount).unwrap(),
+            state.debt_shares,
+        );
+        let uncovered_debt = cover_with_insurance_fund(state, usd_synthetic, user_debt);
+        socialize_bad_debt(state, exchange_account, uncovered_debt);
+        exchange_account.liquidation_deadline = u64::MAX;
+    }
+
+    // xUSD paid for insurance part of penalty stays in supply
+    Ok(liquidation_amount.add(insurance_amount).unwrap())
+}
 
 #[cfg(test)]
 mod tests {
@@ -729,6 +1343,79 @@ mod tests {
         assert!(check_feed_update(&list.assets, 0, 1, 0, 10).is_ok());
     }
     #[test]
+    fn test_check_vault_oracle() {
+        let asset = Asset {
+            price: Decimal::from_integer(100).to_price(),
+            twap: Decimal::from_integer(80).to_price(),
+            status: PriceStatus::Trading.into(),
+            last_update: 10,
+            ..Default::default()
+        };
+        let settings = OracleSettings {
+            max_delay: 10,
+            max_price_deviation: Decimal::from_percent(25),
+            require_trading: true,
+        };
+
+        // Within limits
+        assert!(check_vault_oracle(&asset, &settings, 20).is_ok());
+        // Outdated
+        assert!(check_vault_oracle(&asset, &settings, 21).is_err());
+        // Max delay longer than slot
+        {
+            let early_asset = Asset {
+                last_update: 0,
+                ..asset
+            };
+            assert!(check_vault_oracle(&early_asset, &settings, 5).is_ok());
+        }
+        // Not trading
+        {
+            let halted_asset = Asset {
+                status: PriceStatus::Halted.into(),
+                ..asset
+            };
+            assert!(check_vault_oracle(&halted_asset, &settings, 20).is_err());
+
+            // status not required
+            let settings = OracleSettings {
+                require_trading: false,
+                ..settings
+            };
+            assert!(check_vault_oracle(&halted_asset, &settings, 20).is_ok());
+        }
+        // Deviation
+        {
+            // (100 - 80) / 80 = 25%
+            let settings = OracleSettings {
+                max_price_deviation: Decimal::from_percent(24),
+                ..settings
+            };
+            assert!(check_vault_oracle(&asset, &settings, 20).is_err());
+
+            // price below twap (80 - 64) / 80 = 20%
+            let lower_asset = Asset {
+                price: Decimal::from_integer(64).to_price(),
+                ..asset
+            };
+            assert!(check_vault_oracle(&lower_asset, &settings, 20).is_ok());
+
+            // missing twap
+            let no_twap_asset = Asset {
+                twap: Decimal::from_integer(0).to_price(),
+                ..asset
+            };
+            assert!(check_vault_oracle(&no_twap_asset, &settings, 20).is_err());
+
+            // zero disables check
+            let settings = OracleSettings {
+                max_price_deviation: Decimal::from_percent(0),
+                ..settings
+            };
+            assert!(check_vault_oracle(&no_twap_asset, &settings, 20).is_ok());
+        }
+    }
+    #[test]
     fn test_get_user_sny_collateral_balance() {
         let sny_address = Pubkey::new_unique();
         let sny_asset = Collateral {
@@ -750,21 +1437,27 @@ mod tests {
             let mut exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            exchange_account.append(CollateralEntry {
-                collateral_address: Pubkey::new_unique(),
-                amount: 100,
-                ..Default::default()
-            });
-            exchange_account.append(CollateralEntry {
-                collateral_address: sny_address,
-                amount: 100,
-                ..Default::default()
-            });
-            exchange_account.append(CollateralEntry {
-                collateral_address: Pubkey::new_unique(),
-                amount: 100,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    collateral_address: Pubkey::new_unique(),
+                    amount: 100,
+                    ..Default::default()
+                })
+                .unwrap();
+            exchange_account
+                .append(CollateralEntry {
+                    collateral_address: sny_address,
+                    amount: 100,
+                    ..Default::default()
+                })
+                .unwrap();
+            exchange_account
+                .append(CollateralEntry {
+                    collateral_address: Pubkey::new_unique(),
+                    amount: 100,
+                    ..Default::default()
+                })
+                .unwrap();
 
             let amount = get_user_sny_collateral_balance(&exchange_account, &sny_asset);
             assert_eq!(amount, Decimal::from_sny(100))
@@ -774,16 +1467,20 @@ mod tests {
             let mut exchange_account = ExchangeAccount {
                 ..Default::default()
             };
-            exchange_account.append(CollateralEntry {
-                collateral_address: Pubkey::new_unique(),
-                amount: 100,
-                ..Default::default()
-            });
-            exchange_account.append(CollateralEntry {
-                collateral_address: Pubkey::new_unique(),
-                amount: 100,
-                ..Default::default()
-            });
+            exchange_account
+                .append(CollateralEntry {
+                    collateral_address: Pubkey::new_unique(),
+                    amount: 100,
+                    ..Default::default()
+                })
+                .unwrap();
+            exchange_account
+                .append(CollateralEntry {
+                    collateral_address: Pubkey::new_unique(),
+                    amount: 100,
+                    ..Default::default()
+                })
+                .unwrap();
 
             let amount = get_user_sny_collateral_balance(&exchange_account, &sny_asset);
             assert_eq!(amount, Decimal::from_sny(0))
@@ -1040,6 +1737,106 @@ mod tests {
         }
     }
     #[test]
+    fn test_calculate_user_debt_for_liquidation() {
+        let slot = 100;
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        // xUSD 1000 USD
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(1).to_price(),
+            twap: Decimal::from_integer(1).to_price(),
+            last_update: slot,
+            ..Default::default()
+        });
+        assets_list.append_synthetic(Synthetic {
+            supply: Decimal::from_integer(1000).to_usd(),
+            swapline_supply: Decimal::from_integer(0).to_usd(),
+            borrowed_supply: Decimal::from_integer(0).to_usd(),
+            asset_index: assets_list.head_assets - 1,
+            ..Default::default()
+        });
+        // xBTC supply 0.1, debt 5000 USD by price, 6000 USD by twap
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(50_000).to_price(),
+            twap: Decimal::from_integer(60_000).to_price(),
+            last_update: slot,
+            ..Default::default()
+        });
+        assets_list.append_synthetic(Synthetic {
+            supply: Decimal::new(10_000_000, 8),
+            swapline_supply: Decimal::new(0, 8),
+            borrowed_supply: Decimal::new(0, 8),
+            asset_index: assets_list.head_assets - 1,
+            ..Default::default()
+        });
+        // xETH supply 0.5, debt 2000 USD by price, 1500 USD by twap
+        assets_list.append_asset(Asset {
+            price: Decimal::from_integer(4_000).to_price(),
+            twap: Decimal::from_integer(3_000).to_price(),
+            last_update: slot,
+            ..Default::default()
+        });
+        assets_list.append_synthetic(Synthetic {
+            supply: Decimal::new(500_000, 6),
+            swapline_supply: Decimal::new(0, 6),
+            borrowed_supply: Decimal::new(0, 6),
+            asset_index: assets_list.head_assets - 1,
+            ..Default::default()
+        });
+
+        // account owns 10% of debt
+        let exchange_account = ExchangeAccount {
+            debt_shares: 10,
+            ..Default::default()
+        };
+        let mut state = State {
+            debt_shares: 100,
+            max_delay: 0,
+            ..Default::default()
+        };
+        let user_debt = Decimal::from_integer(800).to_usd();
+
+        // Spot debt is not recalculated
+        let result = calculate_user_debt_for_liquidation(
+            &exchange_account,
+            &assets_list,
+            &state,
+            user_debt,
+            slot,
+        );
+        assert_eq!(result.unwrap(), user_debt);
+        // Twap (1000 + 6000 + 1500) * 10%
+        state.liquidation_price_source = PriceSource::Twap as u8;
+        let result = calculate_user_debt_for_liquidation(
+            &exchange_account,
+            &assets_list,
+            &state,
+            user_debt,
+            slot,
+        );
+        assert_eq!(result.unwrap(), Decimal::from_integer(850).to_usd());
+        // Conservative (1000 + 6000 + 2000) * 10%
+        state.liquidation_price_source = PriceSource::Conservative as u8;
+        let result = calculate_user_debt_for_liquidation(
+            &exchange_account,
+            &assets_list,
+            &state,
+            user_debt,
+            slot,
+        );
+        assert_eq!(result.unwrap(), Decimal::from_integer(900).to_usd());
+        // Outdated oracle
+        let result = calculate_user_debt_for_liquidation(
+            &exchange_account,
+            &assets_list,
+            &state,
+            user_debt,
+            slot + 1,
+        );
+        assert!(result.is_err());
+    }
+    #[test]
     fn test_adjust_vault_entry_interest_debt() {
         let mut assets_list = AssetsList {
             ..Default::default()
@@ -1605,4 +2402,788 @@ mod tests {
             );
         }
     }
+
+    #[test]
+    fn test_get_penalty_to_liquidator() {
+        let state = State {
+            penalty_to_liquidator: Decimal::from_percent(5),
+            liquidation_auction_start_discount: Decimal::from_percent(2),
+            liquidation_auction_max_discount: Decimal::from_percent(12),
+            liquidation_auction_length: 100,
+            ..Default::default()
+        };
+        let liquidation_deadline = 1000;
+        // fixed penalty
+        {
+            let collateral = Collateral {
+                liquidation_mode: LiquidationMode::Fixed as u8,
+                ..Default::default()
+            };
+            let penalty =
+                get_penalty_to_liquidator(&state, &collateral, liquidation_deadline, 1050);
+            assert_eq!(penalty, Decimal::from_percent(5));
+        }
+        // auction
+        {
+            let collateral = Collateral {
+                liquidation_mode: LiquidationMode::Auction as u8,
+                ..Default::default()
+            };
+            let penalty =
+                get_penalty_to_liquidator(&state, &collateral, liquidation_deadline, 1000);
+            assert_eq!(penalty, Decimal::from_percent(2));
+            let penalty =
+                get_penalty_to_liquidator(&state, &collateral, liquidation_deadline, 1050);
+            assert_eq!(penalty, Decimal::from_percent(7));
+            let penalty =
+                get_penalty_to_liquidator(&state, &collateral, liquidation_deadline, 1500);
+            assert_eq!(penalty, Decimal::from_percent(12));
+        }
+    }
+
+    #[test]
+    fn test_socialize_bad_debt() {
+        let mut state = State {
+            debt_shares: 1_000,
+            bad_debt: Decimal::from_usd(0),
+            staking: Staking {
+                finished_round: StakingRound {
+                    all_points: 900,
+                    ..Default::default()
+                },
+                current_round: StakingRound {
+                    all_points: 1_000,
+                    ..Default::default()
+                },
+                next_round: StakingRound {
+                    all_points: 1_000,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let mut exchange_account = ExchangeAccount {
+            debt_shares: 100,
+            user_staking_data: UserStaking {
+                finished_round_points: 90,
+                current_round_points: 100,
+                next_round_points: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let user_debt = Decimal::from_integer(25).to_usd();
+
+        socialize_bad_debt(&mut state, &mut exchange_account, user_debt);
+
+        assert_eq!(state.debt_shares, 900);
+        assert_eq!(exchange_account.debt_shares, 0);
+        assert_eq!(state.bad_debt, user_debt);
+        assert_eq!(state.staking.finished_round.all_points, 810);
+        assert_eq!(state.staking.current_round.all_points, 900);
+        assert_eq!(state.staking.next_round.all_points, 900);
+        assert_eq!(exchange_account.user_staking_data.finished_round_points, 0);
+        assert_eq!(exchange_account.user_staking_data.current_round_points, 0);
+        assert_eq!(exchange_account.user_staking_data.next_round_points, 0);
+    }
+
+    #[test]
+    fn test_migrate_state_layout() {
+        // State of version 0 has fields carved from padding zeroed
+        let mut state = State {
+            max_delay: 10,
+            ..Default::default()
+        };
+        assert_eq!(state.insurance_fund.scale, 0);
+        assert!(state.insurance_fund.add(Decimal::from_usd(1)).is_err());
+
+        migrate_state_layout(&mut state);
+        assert_eq!(state.version, STATE_VERSION);
+        assert_eq!({ state.max_delay }, 10);
+        assert_eq!(
+            state.liquidation_auction_start_discount,
+            Decimal::from_percent(1)
+        );
+        assert_eq!(
+            state.liquidation_auction_max_discount,
+            Decimal::from_percent(10)
+        );
+        assert_eq!({ state.liquidation_auction_length }, 7200);
+        assert_eq!(state.bad_debt, Decimal::from_usd(0));
+        assert_eq!(state.insurance_fund, Decimal::from_usd(0));
+        assert_eq!(state.insurance_fund_target, Decimal::from_usd(0));
+        assert_eq!(state.insurance_swap_tax_ratio, Decimal::from_percent(0));
+        assert_eq!(state.insurance_penalty_ratio, Decimal::from_percent(0));
+        assert_eq!(state.liquidation_critical_ratio, Decimal::from_percent(0));
+        assert_eq!(state.liquidation_bounty, Decimal::from_sny(0));
+        assert_eq!({ state.liquidation_bounty_cooldown }, 172800);
+        assert_eq!({ state.insurance_withdrawal_delay }, 172800);
+        assert_eq!(state.insurance_withdrawal_amount, Decimal::from_usd(0));
+        assert_eq!(
+            state.insurance_fund.add(Decimal::from_usd(1)).unwrap(),
+            Decimal::from_usd(1)
+        );
+
+        // Current version is not changed
+        state.insurance_fund = Decimal::from_usd(100);
+        migrate_state_layout(&mut state);
+        assert_eq!(state.insurance_fund, Decimal::from_usd(100));
+    }
+    #[test]
+    fn test_migrate_assets_list_layout() {
+        assert_eq!(std::mem::size_of::<LegacyCollateral>(), 148);
+        // 3 + 255 * 109 + 255 * 148 + 255 * 109
+        let size = 93333;
+        let mut bytes = vec![0u8; size];
+        bytes[0] = 2;
+        bytes[1] = 1;
+        bytes[2] = 1;
+
+        let feed_address = Pubkey::new_unique();
+        let collateral_address = Pubkey::new_unique();
+        let asset = Asset {
+            feed_address,
+            price: Decimal::from_integer(2).to_price(),
+            ..Default::default()
+        };
+        let synthetic = Synthetic {
+            asset_index: 0,
+            supply: Decimal::from_usd(100),
+            ..Default::default()
+        };
+        let legacy_collateral = LegacyCollateral {
+            asset_index: 1,
+            collateral_address,
+            reserve_balance: Decimal::from_sny(300),
+            collateral_ratio: Decimal::from_percent(10),
+            ..Default::default()
+        };
+        let write = |bytes: &mut Vec<u8>, offset: usize, ptr: *const u8, len: usize| {
+            bytes[offset..offset + len]
+                .copy_from_slice(unsafe { std::slice::from_raw_parts(ptr, len) });
+        };
+        // Offsets of baseline layout
+        write(
+            &mut bytes,
+            3 + 109,
+            &asset as *const Asset as *const u8,
+            109,
+        );
+        write(
+            &mut bytes,
+            27798,
+            &legacy_collateral as *const LegacyCollateral as *const u8,
+            148,
+        );
+        write(
+            &mut bytes,
+            65538,
+            &synthetic as *const Synthetic as *const u8,
+            109,
+        );
+        assert_eq!(&bytes[27799..27831], collateral_address.as_ref());
+
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        migrate_assets_list_layout(&mut assets_list, &bytes).unwrap();
+        assert_eq!(assets_list.head_assets, 2);
+        assert_eq!(assets_list.head_collaterals, 1);
+        assert_eq!(assets_list.head_synthetics, 1);
+        assert_eq!(assets_list.assets[0], Asset::default());
+        assert_eq!(assets_list.assets[1], asset);
+        assert_eq!(assets_list.synthetics[0], synthetic);
+
+        let collateral = assets_list.collaterals[0];
+        assert_eq!(collateral.asset_index, 1);
+        assert_eq!(collateral.collateral_address, collateral_address);
+        assert_eq!({ collateral.reserve_balance }, Decimal::from_sny(300));
+        assert_eq!({ collateral.collateral_ratio }, Decimal::from_percent(10));
+        assert_eq!(
+            { collateral.liquidation_threshold },
+            Decimal::from_percent(10)
+        );
+        assert_eq!(collateral.liquidation_mode, LiquidationMode::Fixed as u8);
+        assert_eq!(collateral.deprecated, false);
+        assert_eq!({ collateral.vault_count }, 0);
+
+        // Wrong size
+        let result = migrate_assets_list_layout(&mut assets_list, &bytes[..size - 1]);
+        assert!(result.is_err());
+    }
+    #[test]
+    fn test_migrate_exchange_account_layout() {
+        let size = std::mem::size_of::<LegacyExchangeAccount>();
+        assert_eq!(size, 1412);
+
+        let owner = Pubkey::new_unique();
+        let first_address = Pubkey::new_unique();
+        let second_address = Pubkey::new_unique();
+        let mut assets_list = AssetsList {
+            ..Default::default()
+        };
+        assets_list.append_collateral(Collateral {
+            collateral_address: first_address,
+            ..Default::default()
+        });
+        assets_list.append_collateral(Collateral {
+            collateral_address: second_address,
+            ..Default::default()
+        });
+
+        // Serialize account of version 0 with outdated index
+        let mut legacy_account = LegacyExchangeAccount {
+            owner,
+            version: 0,
+            debt_shares: 10,
+            liquidation_deadline: 1000,
+            head: 1,
+            bump: 255,
+            ..Default::default()
+        };
+        legacy_account.user_staking_data.amount_to_claim = Decimal::from_sny(20);
+        legacy_account.collaterals[0] = CollateralEntry {
+            amount: 100,
+            collateral_address: second_address,
+            index: 2,
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(
+                &legacy_account as *const LegacyExchangeAccount as *const u8,
+                size,
+            )
+        }
+        .to_vec();
+        // Offsets of baseline layout
+        assert_eq!(&bytes[0..32], owner.as_ref());
+        assert_eq!(bytes[32], 0);
+        assert_eq!(bytes[33..41], 10u64.to_le_bytes());
+        assert_eq!(bytes[41..49], 1000u64.to_le_bytes());
+        assert_eq!(bytes[98], 1);
+        assert_eq!(bytes[99], 255);
+        assert_eq!(bytes[100..108], 100u64.to_le_bytes());
+        assert_eq!(&bytes[108..140], second_address.as_ref());
+        assert_eq!(bytes[140], 2);
+
+        // Migrate to current version
+        let mut exchange_account = ExchangeAccount {
+            ..Default::default()
+        };
+        migrate_exchange_account_layout(&mut exchange_account, &bytes, &assets_list).unwrap();
+        assert_eq!(exchange_account.version, EXCHANGE_ACCOUNT_VERSION);
+        assert_eq!(exchange_account.owner, owner);
+        assert_eq!({ exchange_account.debt_shares }, 10);
+        assert_eq!({ exchange_account.liquidation_deadline }, 1000);
+        assert_eq!(
+            { exchange_account.user_staking_data.amount_to_claim },
+            Decimal::from_sny(20)
+        );
+        assert_eq!(exchange_account.head, 1);
+        assert_eq!({ exchange_account.collaterals[0].amount }, 100);
+        assert_eq!(
+            exchange_account.collaterals[0].collateral_address,
+            second_address
+        );
+        assert_eq!(exchange_account.collaterals[0].index, 1);
+        assert_eq!(exchange_account.delegate, Pubkey::default());
+
+        // Wrong size
+        let result = migrate_exchange_account_layout(
+            &mut exchange_account,
+            &bytes[..size - 1],
+            &assets_list,
+        );
+        assert!(result.is_err());
+
+        // Already migrated
+        let mut migrated_bytes = bytes.clone();
+        migrated_bytes[32] = 1;
+        let result =
+            migrate_exchange_account_layout(&mut exchange_account, &migrated_bytes, &assets_list);
+        assert!(result.is_err());
+    }
+    #[test]
+    fn test_migrate_vault_layout() {
+        let size = std::mem::size_of::<LegacyVault>();
+        assert_eq!(size, 293);
+
+        let synthetic = Pubkey::new_unique();
+        let collateral = Pubkey::new_unique();
+        let collateral_reserve = Pubkey::new_unique();
+        let state = State {
+            max_delay: 1000,
+            ..Default::default()
+        };
+        let legacy_vault = LegacyVault {
+            halted: true,
+            synthetic,
+            collateral,
+            collateral_ratio: Decimal::from_percent(80),
+            accumulated_interest_rate: Decimal::from_integer(1).to_interest_rate(),
+            collateral_reserve,
+            mint_amount: Decimal::from_usd(200),
+            collateral_amount: Decimal::new(300, 6),
+            last_update: 1234,
+            bump: 255,
+            ..Default::default()
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(&legacy_vault as *const LegacyVault as *const u8, size)
+        }
+        .to_vec();
+        // Offsets of baseline layout
+        assert_eq!(bytes[0], 1);
+        assert_eq!(&bytes[1..33], synthetic.as_ref());
+        assert_eq!(&bytes[33..65], collateral.as_ref());
+        assert_eq!(&bytes[201..233], collateral_reserve.as_ref());
+        assert_eq!(bytes[250..266], 300u128.to_le_bytes());
+        assert_eq!(bytes[266], 6);
+        assert_eq!(bytes[284..292], 1234i64.to_le_bytes());
+        assert_eq!(bytes[292], 255);
+
+        let mut vault = Vault {
+            ..Default::default()
+        };
+        migrate_vault_layout(&mut vault, &bytes, &state).unwrap();
+        assert_eq!(vault.halted, true);
+        assert_eq!(vault.synthetic, synthetic);
+        assert_eq!(vault.collateral, collateral);
+        assert_eq!(vault.collateral_reserve, collateral_reserve);
+        assert_eq!({ vault.collateral_ratio }, Decimal::from_percent(80));
+        assert_eq!(
+            { vault.accumulated_interest_rate },
+            Decimal::from_integer(1).to_interest_rate()
+        );
+        assert_eq!({ vault.mint_amount }, Decimal::from_usd(200));
+        assert_eq!({ vault.collateral_amount }, Decimal::new(300, 6));
+        assert_eq!({ vault.last_update }, 1234);
+        assert_eq!(vault.vault_type, 0);
+        assert_eq!({ vault.oracle_settings.max_delay }, 1000);
+        assert_eq!(
+            { vault.oracle_settings.max_price_deviation },
+            Decimal::from_percent(0)
+        );
+        assert_eq!(vault.liquidation_price_source, PriceSource::Spot as u8);
+        assert_eq!(vault.basket_head, 0);
+
+        // Wrong size
+        let result = migrate_vault_layout(&mut vault, &bytes[..size - 1], &state);
+        assert!(result.is_err());
+    }
+    #[test]
+    fn test_migrate_vault_entry_layout() {
+        let size = std::mem::size_of::<LegacyVaultEntry>();
+        assert_eq!(size, 116);
+
+        let owner = Pubkey::new_unique();
+        let legacy_vault = Pubkey::new_unique();
+        let legacy_entry = LegacyVaultEntry {
+            owner,
+            vault: legacy_vault,
+            last_accumulated_interest_rate: Decimal::from_integer(1).to_interest_rate(),
+            synthetic_amount: Decimal::from_usd(100),
+            collateral_amount: Decimal::new(200, 6),
+            bump: 255,
+        };
+        let bytes = unsafe {
+            std::slice::from_raw_parts(&legacy_entry as *const LegacyVaultEntry as *const u8, size)
+        }
+        .to_vec();
+        // Offsets of baseline layout
+        assert_eq!(&bytes[0..32], owner.as_ref());
+        assert_eq!(&bytes[32..64], legacy_vault.as_ref());
+        assert_eq!(bytes[98..114], 200u128.to_le_bytes());
+        assert_eq!(bytes[115], 255);
+
+        let mut vault_entry = VaultEntry {
+            ..Default::default()
+        };
+        migrate_vault_entry_layout(&mut vault_entry, &bytes, &legacy_vault).unwrap();
+        assert_eq!(vault_entry.owner, owner);
+        assert_eq!(
+            { vault_entry.last_accumulated_interest_rate },
+            Decimal::from_integer(1).to_interest_rate()
+        );
+        assert_eq!({ vault_entry.synthetic_amount }, Decimal::from_usd(100));
+        assert_eq!({ vault_entry.collateral_amount }, Decimal::new(200, 6));
+
+        // Entry of other vault
+        let result = migrate_vault_entry_layout(&mut vault_entry, &bytes, &Pubkey::new_unique());
+        assert!(result.is_err());
+        // Wrong size
+        let result =
+            migrate_vault_entry_layout(&mut vault_entry, &bytes[..size - 1], &legacy_vault);
+        assert!(result.is_err());
+    }
+    #[test]
+    fn test_update_liquidation_deadline() {
+        let slot = 100;
+        let liquidation_buffer = 10;
+        let user_debt = Decimal::from_integer(100).to_usd();
+        // safe account
+        {
+            let mut exchange_account = ExchangeAccount {
+                liquidation_deadline: 50,
+                ..Default::default()
+            };
+            let max_debt = Decimal::from_integer(101).to_usd();
+            let marked = update_liquidation_deadline(
+                &mut exchange_account,
+                max_debt,
+                user_debt,
+                liquidation_buffer,
+                slot,
+            );
+            assert!(!marked);
+            assert_eq!(exchange_account.liquidation_deadline, u64::MAX);
+        }
+        // undercollateralized account
+        {
+            let mut exchange_account = ExchangeAccount {
+                liquidation_deadline: u64::MAX,
+                ..Default::default()
+            };
+            let max_debt = Decimal::from_integer(100).to_usd();
+            let marked = update_liquidation_deadline(
+                &mut exchange_account,
+                max_debt,
+                user_debt,
+                liquidation_buffer,
+                slot,
+            );
+            assert!(marked);
+            assert_eq!(exchange_account.liquidation_deadline, 110);
+        }
+        // already marked account
+        {
+            let mut exchange_account = ExchangeAccount {
+                liquidation_deadline: 105,
+                ..Default::default()
+            };
+            let max_debt = Decimal::from_integer(99).to_usd();
+            let marked = update_liquidation_deadline(
+                &mut exchange_account,
+                max_debt,
+                user_debt,
+                liquidation_buffer,
+                slot,
+            );
+            assert!(!marked);
+            assert_eq!(exchange_account.liquidation_deadline, 105);
+        }
+    }
+    #[test]
+    fn test_cover_with_insurance_fund() {
+        let base_synthetic = Synthetic {
+            supply: Decimal::from_integer(1_000).to_usd(),
+            ..Default::default()
+        };
+        // fund covers whole debt
+        {
+            let mut state = State {
+                insurance_fund: Decimal::from_integer(100).to_usd(),
+                ..Default::default()
+            };
+            let mut usd_synthetic = base_synthetic.clone();
+            let debt = Decimal::from_integer(40).to_usd();
+
+            let uncovered_debt = cover_with_insurance_fund(&mut state, &mut usd_synthetic, debt);
+
+            assert_eq!(uncovered_debt, Decimal::from_usd(0));
+            assert_eq!(state.insurance_fund, Decimal::from_integer(60).to_usd());
+            assert_eq!(usd_synthetic.supply, Decimal::from_integer(960).to_usd());
+        }
+        // fund covers part of debt
+        {
+            let mut state = State {
+                insurance_fund: Decimal::from_integer(100).to_usd(),
+                ..Default::default()
+            };
+            let mut usd_synthetic = base_synthetic.clone();
+            let debt = Decimal::from_integer(150).to_usd();
+
+            let uncovered_debt = cover_with_insurance_fund(&mut state, &mut usd_synthetic, debt);
+
+            assert_eq!(uncovered_debt, Decimal::from_integer(50).to_usd());
+            assert_eq!(state.insurance_fund, Decimal::from_usd(0));
+            assert_eq!(usd_synthetic.supply, Decimal::from_integer(900).to_usd());
+        }
+    }
+    #[test]
+    fn test_withdraw_from_insurance_fund() {
+        let base_state = State {
+            insurance_fund: Decimal::from_integer(100).to_usd(),
+            insurance_fund_target: Decimal::from_integer(60).to_usd(),
+            insurance_withdrawal_amount: Decimal::from_integer(30).to_usd(),
+            insurance_withdrawal_slot: 100,
+            ..Default::default()
+        };
+        // minted amount matches decremented fund
+        {
+            let mut state = base_state.clone();
+            let fund_before = state.insurance_fund;
+
+            let minted = withdraw_from_insurance_fund(&mut state, 100).unwrap();
+
+            let withdrawn = fund_before.sub(state.insurance_fund).unwrap();
+            assert_eq!(minted, withdrawn.to_u64());
+            assert_eq!(minted, 30_000_000);
+            assert_eq!(state.insurance_fund, Decimal::from_integer(70).to_usd());
+            assert_eq!(state.insurance_withdrawal_amount, Decimal::from_usd(0));
+            // request is consumed
+            assert!(withdraw_from_insurance_fund(&mut state, 100).is_err());
+        }
+        // before timelock ends
+        {
+            let mut state = base_state.clone();
+            let result = withdraw_from_insurance_fund(&mut state, 99);
+            assert!(result.is_err());
+            assert_eq!(state.insurance_fund, Decimal::from_integer(100).to_usd());
+        }
+        // fund dropped below requested excess
+        {
+            let mut state = State {
+                insurance_fund: Decimal::from_integer(80).to_usd(),
+                ..base_state.clone()
+            };
+            let result = withdraw_from_insurance_fund(&mut state, 200);
+            assert!(result.is_err());
+            assert_eq!(state.insurance_fund, Decimal::from_integer(80).to_usd());
+        }
+    }
+    #[test]
+    fn test_seize_collateral() {
+        let collateral_address = Pubkey::new_unique();
+        let state = State {
+            penalty_to_liquidator: Decimal::from_percent(5),
+            penalty_to_exchange: Decimal::from_percent(5),
+            insurance_penalty_ratio: Decimal::from_percent(50),
+            ..Default::default()
+        };
+        let asset = Asset {
+            price: Decimal::from_integer(2).to_price(),
+            ..Default::default()
+        };
+        let base_collateral = Collateral {
+            collateral_address,
+            reserve_balance: Decimal::new(1_000_000_000, 6),
+            liquidation_mode: LiquidationMode::Fixed as u8,
+            ..Default::default()
+        };
+        let mut base_exchange_account = ExchangeAccount {
+            ..Default::default()
+        };
+        base_exchange_account
+            .append(CollateralEntry {
+                amount: 100_000_000,
+                collateral_address,
+                index: 0,
+            })
+            .unwrap();
+        // part of collateral
+        {
+            let mut collateral = base_collateral.clone();
+            let mut exchange_account = base_exchange_account.clone();
+            let repay_amount = Decimal::from_integer(50).to_usd();
+
+            let seized = seize_collateral(
+                &state,
+                &mut exchange_account,
+                &asset,
+                &mut collateral,
+                repay_amount,
+                0,
+            )
+            .unwrap();
+
+            // 55 USD of collateral seized, 2.5 USD of it as exchange penalty
+            assert_eq!(seized.repaid_amount, repay_amount);
+            assert_eq!(seized.to_exchange, Decimal::new(625_000, 6));
+            assert_eq!(seized.to_liquidator, Decimal::new(26_875_000, 6));
+            assert_eq!(seized.insurance_amount, Decimal::new(1_250_000, 6));
+            assert_eq!({ exchange_account.collaterals[0].amount }, 72_500_000);
+            assert_eq!(collateral.reserve_balance, Decimal::new(972_500_000, 6));
+            assert_eq!(exchange_account.head, 1);
+        }
+        // whole collateral
+        {
+            let mut collateral = base_collateral.clone();
+            let mut exchange_account = base_exchange_account.clone();
+            exchange_account.collaterals[0].amount = 10_000_000;
+
+            let seized = seize_collateral(
+                &state,
+                &mut exchange_account,
+                &asset,
+                &mut collateral,
+                Decimal::from_integer(50).to_usd(),
+                0,
+            )
+            .unwrap();
+
+            assert_eq!(seized.repaid_amount, Decimal::from_integer(20).to_usd());
+            assert_eq!(
+                seized.to_liquidator.add(seized.to_exchange).unwrap(),
+                Decimal::new(10_000_000, 6)
+            );
+            assert_eq!(collateral.reserve_balance, Decimal::new(990_000_000, 6));
+            assert_eq!(exchange_account.head, 0);
+        }
+        // collateral not deposited
+        {
+            let mut collateral = base_collateral.clone();
+            let mut exchange_account = ExchangeAccount {
+                ..Default::default()
+            };
+
+            let result = seize_collateral(
+                &state,
+                &mut exchange_account,
+                &asset,
+                &mut collateral,
+                Decimal::from_integer(50).to_usd(),
+                0,
+            );
+            assert!(result.is_err());
+        }
+    }
+    #[test]
+    fn test_settle_liquidation() {
+        let base_state = State {
+            debt_shares: 1_000,
+            insurance_fund: Decimal::from_integer(10).to_usd(),
+            bad_debt: Decimal::from_usd(0),
+            staking: Staking {
+                finished_round: StakingRound {
+                    all_points: 1_000,
+                    ..Default::default()
+                },
+                current_round: StakingRound {
+                    all_points: 1_000,
+                    ..Default::default()
+                },
+                next_round: StakingRound {
+                    all_points: 1_000,
+                    ..Default::default()
+                },
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let base_exchange_account = ExchangeAccount {
+            debt_shares: 100,
+            head: 1,
+            user_staking_data: UserStaking {
+                finished_round_points: 100,
+                current_round_points: 100,
+                next_round_points: 100,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        let base_synthetic = Synthetic {
+            supply: Decimal::from_integer(1_000).to_usd(),
+            max_supply: Decimal::from_integer(10_000).to_usd(),
+            ..Default::default()
+        };
+        let total_debt = Decimal::from_integer(1_000).to_usd();
+        let liquidation_amount = Decimal::from_integer(50).to_usd();
+        let insurance_amount = Decimal::from_integer(2).to_usd();
+        // account with collateral left
+        {
+            let mut state = base_state.clone();
+            let mut exchange_account = base_exchange_account.clone();
+            let mut usd_synthetic = base_synthetic.clone();
+
+            let burned_amount = settle_liquidation(
+                &mut state,
+                &mut exchange_account,
+                &mut usd_synthetic,
+                total_debt,
+                liquidation_amount,
+                insurance_amount,
+            )
+            .unwrap();
+
+            assert_eq!(burned_amount, Decimal::from_integer(52).to_usd());
+            assert_eq!({ state.debt_shares }, 950);
+            assert_eq!({ exchange_account.debt_shares }, 50);
+            assert_eq!(usd_synthetic.supply, Decimal::from_integer(950).to_usd());
+            assert_eq!(state.insurance_fund, Decimal::from_integer(12).to_usd());
+            assert_eq!(state.staking.finished_round.all_points, 900);
+            assert_eq!(state.staking.current_round.all_points, 900);
+            assert_eq!(state.staking.next_round.all_points, 950);
+            assert_eq!(exchange_account.user_staking_data.next_round_points, 50);
+            assert_eq!(state.bad_debt, Decimal::from_usd(0));
+        }
+        // account without collateral left
+        {
+            let mut state = base_state.clone();
+            let mut exchange_account = base_exchange_account.clone();
+            exchange_account.head = 0;
+            let mut usd_synthetic = base_synthetic.clone();
+
+            let burned_amount = settle_liquidation(
+                &mut state,
+                &mut exchange_account,
+                &mut usd_synthetic,
+                total_debt,
+                liquidation_amount,
+                insurance_amount,
+            )
+            .unwrap();
+
+            // 50 USD of debt left, 12 USD covered by insurance fund
+            assert_eq!(burned_amount, Decimal::from_integer(52).to_usd());
+            assert_eq!({ exchange_account.debt_shares }, 0);
+            assert_eq!({ state.debt_shares }, 900);
+            assert_eq!(state.insurance_fund, Decimal::from_usd(0));
+            assert_eq!(usd_synthetic.supply, Decimal::from_integer(938).to_usd());
+            assert_eq!(state.bad_debt, Decimal::from_integer(38).to_usd());
+            assert_eq!(exchange_account.liquidation_deadline, u64::MAX);
+        }
+    }
+    #[test]
+    fn test_release_shortfall_cascade() {
+        let synthetic_decimal = 8;
+        let mut synthetic = Synthetic {
+            supply: Decimal::from_integer(300).to_scale(synthetic_decimal),
+            borrowed_supply: Decimal::from_integer(200).to_scale(synthetic_decimal),
+            ..Default::default()
+        };
+        let mut vault = Vault {
+            mint_amount: Decimal::from_integer(200).to_scale(synthetic_decimal),
+            ..Default::default()
+        };
+        let mut vault_entry = VaultEntry {
+            synthetic_amount: Decimal::from_integer(15).to_scale(synthetic_decimal),
+            collateral_amount: Decimal::from_integer(0).to_scale(6),
+            ..Default::default()
+        };
+
+        let shortfall = vault_entry.release_shortfall_cascade(&mut vault, &mut synthetic);
+
+        assert_eq!(
+            shortfall,
+            Decimal::from_integer(15).to_scale(synthetic_decimal)
+        );
+        assert_eq!(
+            vault_entry.synthetic_amount,
+            Decimal::from_integer(0).to_scale(synthetic_decimal)
+        );
+        assert_eq!(
+            vault.mint_amount,
+            Decimal::from_integer(185).to_scale(synthetic_decimal)
+        );
+        assert_eq!(
+            synthetic.borrowed_supply,
+            Decimal::from_integer(185).to_scale(synthetic_decimal)
+        );
+        // supply is not changed
+        assert_eq!(
+            synthetic.supply,
+            Decimal::from_integer(300).to_scale(synthetic_decimal)
+        );
+    }
 }
@@ -0,0 +1,69 @@
+use anchor_lang::prelude::*;
+use anchor_spl::token::{self, TokenAccount, Transfer};
+
+const FLASH_LIQUIDATOR_SEED: &str = "FlashLiquidator";
+#[program]
+pub mod flash_liquidator {
+    use super::*;
+
+    // Callback for exchange flash_liquidate
+    // Buys whole collateral of liquidator for fixed amount of xUSD from treasury
+    pub fn swap_collateral(
+        ctx: Context<SwapCollateral>,
+        nonce: u8,
+        usd_amount: u64,
+    ) -> ProgramResult {
+        msg!("FlashLiquidator: SWAP COLLATERAL");
+        let collateral_amount = ctx.accounts.liquidator_collateral_account.amount;
+        {
+            // Transfer collateral to treasury
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.liquidator_collateral_account.to_account_info(),
+                to: ctx.accounts.treasury_collateral_account.to_account_info(),
+                authority: ctx.accounts.liquidator.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            token::transfer(
+                CpiContext::new(cpi_program, cpi_accounts),
+                collateral_amount,
+            )?;
+        }
+        {
+            // Transfer xUSD to liquidator
+            let seeds = &[FLASH_LIQUIDATOR_SEED.as_bytes(), &[nonce]];
+            let signer = &[&seeds[..]];
+            let cpi_accounts = Transfer {
+                from: ctx.accounts.treasury_usd_account.to_account_info(),
+                to: ctx.accounts.liquidator_usd_account.to_account_info(),
+                authority: ctx.accounts.treasury_authority.to_account_info(),
+            };
+            let cpi_program = ctx.accounts.token_program.to_account_info();
+            token::transfer(
+                CpiContext::new(cpi_program, cpi_accounts).with_signer(signer),
+                usd_amount,
+            )?;
+        }
+        Ok(())
+    }
+}
+#[derive(Accounts)]
+pub struct SwapCollateral<'info> {
+    #[account(signer)]
+    pub liquidator: AccountInfo<'info>,
+    #[account(mut, constraint = &liquidator_collateral_account.owner == liquidator.key)]
+    pub liquidator_collateral_account: CpiAccount<'info, TokenAccount>,
+    #[account(mut)]
+    pub liquidator_usd_account: CpiAccount<'info, TokenAccount>,
+    #[account(mut,
+        constraint = treasury_collateral_account.mint == liquidator_collateral_account.mint
+    )]
+    pub treasury_collateral_account: CpiAccount<'info, TokenAccount>,
+    #[account(mut,
+        constraint = treasury_usd_account.mint == liquidator_usd_account.mint,
+        constraint = &treasury_usd_account.owner == treasury_authority.key
+    )]
+    pub treasury_usd_account: CpiAccount<'info, TokenAccount>,
+    pub treasury_authority: AccountInfo<'info>,
+    #[account(address = token::ID)]
+    pub token_program: AccountInfo<'info>,
+}
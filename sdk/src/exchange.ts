@@ -14,7 +14,8 @@ import {
   TransactionInstruction,
   Transaction,
   sendAndConfirmRawTransaction,
-  Keypair
+  Keypair,
+  AccountMeta
 } from '@solana/web3.js'
 
 export const STATE_SEED = 'statev1'
@@ -137,6 +138,23 @@ export class Exchange {
       }
     })) as TransactionInstruction
   }
+  public async migrateAssetsList(exchangeAdmin: Keypair | Account) {
+    // Moves assets list into account sized for current collateral layout
+    const assetsListAccount = Keypair.generate()
+    await this.program.rpc.migrateAssetsList({
+      accounts: {
+        state: this.stateAddress,
+        legacyAssetsList: this.state.assetsList,
+        assetsList: assetsListAccount.publicKey,
+        admin: exchangeAdmin.publicKey,
+        rent: SYSVAR_RENT_PUBKEY
+      },
+      signers: [exchangeAdmin, assetsListAccount],
+      instructions: [await this.program.account.assetsList.createInstruction(assetsListAccount)]
+    })
+    await this.getState()
+    return assetsListAccount.publicKey
+  }
   public async getState() {
     const state = (await this.program.account.state.fetch(this.stateAddress)) as ExchangeState
     // need to add hooks on change
@@ -160,7 +178,20 @@ export class Exchange {
     const account = (await this.program.account.settlement.fetch(settlement)) as Settlement
     return account
   }
-  public async getVaultAddress(synthetic: PublicKey, collateral: PublicKey) {
+  public async getVaultAddress(synthetic: PublicKey, collateral: PublicKey, vaultType: number = 0) {
+    const [vaultAddress, bump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(utils.bytes.utf8.encode('vaultv1')),
+        synthetic.toBuffer(),
+        collateral.toBuffer(),
+        Buffer.from([vaultType])
+      ],
+      this.program.programId
+    )
+    return { vaultAddress, bump }
+  }
+  public async getLegacyVaultAddress(synthetic: PublicKey, collateral: PublicKey) {
+    // vaults created before vault types, migrated into vault of type 0
     const [vaultAddress, bump] = await PublicKey.findProgramAddress(
       [
         Buffer.from(utils.bytes.utf8.encode('vaultv1')),
@@ -171,8 +202,29 @@ export class Exchange {
     )
     return { vaultAddress, bump }
   }
-  public async getVaultEntryAddress(synthetic: PublicKey, collateral: PublicKey, owner: PublicKey) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+  public async getLegacyVaultEntryAddress(
+    synthetic: PublicKey,
+    collateral: PublicKey,
+    owner: PublicKey
+  ) {
+    const { vaultAddress } = await this.getLegacyVaultAddress(synthetic, collateral)
+    const [vaultEntryAddress, bump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(utils.bytes.utf8.encode('vault_entryv1')),
+        owner.toBuffer(),
+        vaultAddress.toBuffer()
+      ],
+      this.program.programId
+    )
+    return { vaultEntryAddress, bump }
+  }
+  public async getVaultEntryAddress(
+    synthetic: PublicKey,
+    collateral: PublicKey,
+    owner: PublicKey,
+    vaultType: number = 0
+  ) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
     const [vaultEntryAddress, bump] = await PublicKey.findProgramAddress(
       [
         Buffer.from(utils.bytes.utf8.encode('vault_entryv1')),
@@ -186,17 +238,33 @@ export class Exchange {
       bump
     }
   }
-  public async getVaultForPair(synthetic: PublicKey, collateral: PublicKey) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+  public async getVaultForPair(synthetic: PublicKey, collateral: PublicKey, vaultType: number = 0) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
     const account = (await this.program.account.vault.fetch(vaultAddress)) as Vault
-    return account
+    return {
+      ...account,
+      basket: account.basket.slice(0, account.basketHead)
+    } as Vault
+  }
+  public getVaultCollateralReserve(vault: Vault, collateral: PublicKey) {
+    // primary collateral or one of basket
+    if (vault.collateral.equals(collateral)) {
+      return vault.collateralReserve
+    }
+    return vault.basket.find((c) => c.collateral.equals(collateral)).collateralReserve
   }
   public async getVaultEntryForOwner(
     synthetic: PublicKey,
     collateral: PublicKey,
-    owner: PublicKey
+    owner: PublicKey,
+    vaultType: number = 0
   ) {
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
     const account = (await this.program.account.vaultEntry.fetch(vaultEntryAddress)) as VaultEntry
     return account
   }
@@ -227,12 +295,12 @@ export class Exchange {
 
     return userAccount.debtShares.mul(debt).div(state.debtShares)
   }
-  public async createExchangeAccount(owner: PublicKey) {
+  public async createExchangeAccount(owner: PublicKey, id: number = 0) {
     const [account, bump] = await PublicKey.findProgramAddress(
-      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer()],
+      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer(), Buffer.from([id])],
       this.program.programId
     )
-    await this.program.rpc.createExchangeAccount(bump, {
+    await this.program.rpc.createExchangeAccount(bump, id, {
       accounts: {
         exchangeAccount: account,
         rent: SYSVAR_RENT_PUBKEY,
@@ -389,12 +457,12 @@ export class Exchange {
     const swaplineData = (await this.program.account.swapline.fetch(swapline)) as Swapline
     return swaplineData
   }
-  public async createExchangeAccountInstruction(owner: PublicKey) {
+  public async createExchangeAccountInstruction(owner: PublicKey, id: number = 0) {
     const [account, bump] = await PublicKey.findProgramAddress(
-      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer()],
+      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer(), Buffer.from([id])],
       this.program.programId
     )
-    const ix = (await this.program.instruction.createExchangeAccount(bump, {
+    const ix = (await this.program.instruction.createExchangeAccount(bump, id, {
       accounts: {
         exchangeAccount: account,
         rent: SYSVAR_RENT_PUBKEY,
@@ -405,9 +473,57 @@ export class Exchange {
     })) as TransactionInstruction
     return { account, ix }
   }
-  public async getExchangeAccountAddress(owner: PublicKey) {
+  public async setDelegateInstruction({ owner, id, delegate, permissions }: SetDelegate) {
+    const account = await this.getExchangeAccountAddress(owner, id)
+    return (await this.program.instruction.setDelegate(permissions, {
+      accounts: {
+        exchangeAccount: account,
+        owner: owner,
+        delegate: delegate
+      }
+    })) as TransactionInstruction
+  }
+  public async transferPositionInstruction({
+    owner,
+    id = 0,
+    newOwner,
+    newId = 0
+  }: TransferPosition) {
+    const account = await this.getExchangeAccountAddress(owner, id)
+    const [newAccount, bump] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(utils.bytes.utf8.encode('accountv1')),
+        newOwner.toBuffer(),
+        Buffer.from([newId])
+      ],
+      this.program.programId
+    )
+    const ix = (await this.program.instruction.transferPosition(bump, newId, {
+      accounts: {
+        state: this.stateAddress,
+        exchangeAccount: account,
+        newExchangeAccount: newAccount,
+        owner: owner,
+        newOwner: newOwner,
+        rent: SYSVAR_RENT_PUBKEY,
+        systemProgram: SystemProgram.programId
+      }
+    })) as TransactionInstruction
+    return { newAccount, ix }
+  }
+  public async closeExchangeAccountInstruction(owner: PublicKey, id: number = 0) {
+    const account = await this.getExchangeAccountAddress(owner, id)
+    return (await this.program.instruction.closeExchangeAccount({
+      accounts: {
+        state: this.stateAddress,
+        exchangeAccount: account,
+        owner: owner
+      }
+    })) as TransactionInstruction
+  }
+  public async getExchangeAccountAddress(owner: PublicKey, id: number = 0) {
     const [account, bump] = await PublicKey.findProgramAddress(
-      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer()],
+      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer(), Buffer.from([id])],
       this.program.programId
     )
     return account
@@ -518,6 +634,74 @@ export class Exchange {
       }
     }) as TransactionInstruction)
   }
+  public async flashLiquidateInstruction({
+    exchangeAccount,
+    signer,
+    liquidationFund,
+    liquidatorCollateralAccount,
+    liquidatorUsdAccount,
+    reserveAccount,
+    amount,
+    callback
+  }: FlashLiquidateInstruction) {
+    // callback instruction is invoked by exchange after seized collateral is transferred
+    return await (this.program.instruction.flashLiquidate(amount, callback.data, {
+      remainingAccounts: callback.keys,
+      accounts: {
+        state: this.stateAddress,
+        exchangeAuthority: this.exchangeAuthority,
+        assetsList: this.state.assetsList,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        usdToken: this.assetsList.synthetics[0].assetAddress,
+        liquidatorUsdAccount: liquidatorUsdAccount,
+        liquidatorCollateralAccount: liquidatorCollateralAccount,
+        exchangeAccount: exchangeAccount,
+        signer: signer,
+        liquidationFund: liquidationFund,
+        reserveAccount: reserveAccount,
+        callbackProgram: callback.programId
+      }
+    }) as TransactionInstruction)
+  }
+  public async liquidateMultiInstruction({
+    exchangeAccount,
+    signer,
+    liquidatorUsdAccount,
+    collaterals,
+    amount
+  }: LiquidateMultiInstruction) {
+    // collaterals are seized in passed order
+    const remainingAccounts: AccountMeta[] = []
+    for (const c of collaterals) {
+      remainingAccounts.push(
+        { pubkey: c.reserveAccount, isWritable: true, isSigner: false },
+        { pubkey: c.liquidationFund, isWritable: true, isSigner: false },
+        { pubkey: c.liquidatorCollateralAccount, isWritable: true, isSigner: false }
+      )
+    }
+    return await (this.program.instruction.liquidateMulti(amount, {
+      remainingAccounts,
+      accounts: {
+        state: this.stateAddress,
+        exchangeAuthority: this.exchangeAuthority,
+        assetsList: this.state.assetsList,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        usdToken: this.assetsList.synthetics[0].assetAddress,
+        liquidatorUsdAccount: liquidatorUsdAccount,
+        exchangeAccount: exchangeAccount,
+        signer: signer
+      }
+    }) as TransactionInstruction)
+  }
+  public async settleBadDebtInstruction(exchangeAccount: PublicKey) {
+    return await (this.program.instruction.settleBadDebt({
+      accounts: {
+        state: this.stateAddress,
+        exchangeAccount: exchangeAccount,
+        assetsList: this.state.assetsList
+      }
+    }) as TransactionInstruction)
+  }
   public async burnInstruction({
     amount,
     exchangeAccount,
@@ -537,6 +721,36 @@ export class Exchange {
       }
     }) as TransactionInstruction)
   }
+  public async getLegacyExchangeAccountAddress(owner: PublicKey) {
+    const [account, bump] = await PublicKey.findProgramAddress(
+      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer()],
+      this.program.programId
+    )
+    return { account, bump }
+  }
+  public async migrateExchangeAccountInstruction(
+    owner: PublicKey,
+    payer: PublicKey = owner,
+    id: number = 0
+  ) {
+    const legacy = await this.getLegacyExchangeAccountAddress(owner)
+    const [account, bump] = await PublicKey.findProgramAddress(
+      [Buffer.from(utils.bytes.utf8.encode('accountv1')), owner.toBuffer(), Buffer.from([id])],
+      this.program.programId
+    )
+    return await (this.program.instruction.migrateExchangeAccount(bump, legacy.bump, id, {
+      accounts: {
+        state: this.stateAddress,
+        legacyExchangeAccount: legacy.account,
+        exchangeAccount: account,
+        payer: payer,
+        owner: owner,
+        assetsList: this.state.assetsList,
+        rent: SYSVAR_RENT_PUBKEY,
+        systemProgram: SystemProgram.programId
+      }
+    }) as TransactionInstruction)
+  }
   public async claimRewardsInstruction(exchangeAccount: PublicKey) {
     return await (this.program.instruction.claimRewards({
       accounts: {
@@ -562,6 +776,17 @@ export class Exchange {
       }
     }) as TransactionInstruction)
   }
+  public async getLiquidationPreview(exchangeAccount: PublicKey) {
+    // read only instruction, result is emitted as event
+    const { events } = await this.program.simulate.previewLiquidation({
+      accounts: {
+        state: this.stateAddress,
+        exchangeAccount: exchangeAccount,
+        assetsList: this.state.assetsList
+      }
+    })
+    return events[0].data as LiquidationPreview
+  }
   public async checkAccountInstruction(exchangeAccount: PublicKey) {
     return await (this.program.instruction.checkAccountCollateralization({
       accounts: {
@@ -571,6 +796,24 @@ export class Exchange {
       }
     }) as TransactionInstruction)
   }
+  public async markAccountForLiquidationInstruction({
+    exchangeAccount,
+    signer,
+    keeperAccount
+  }: MarkAccountForLiquidationInstruction) {
+    return await (this.program.instruction.markAccountForLiquidation({
+      accounts: {
+        state: this.stateAddress,
+        exchangeAccount: exchangeAccount,
+        assetsList: this.state.assetsList,
+        exchangeAuthority: this.exchangeAuthority,
+        liquidationFund: this.assetsList.collaterals[0].liquidationFund,
+        keeperAccount: keeperAccount,
+        signer: signer,
+        tokenProgram: TOKEN_PROGRAM_ID
+      }
+    }) as TransactionInstruction)
+  }
   public async setLiquidationBufferInstruction(newLiquidationBuffer: number) {
     return await (this.program.instruction.setLiquidationBuffer(newLiquidationBuffer, {
       accounts: {
@@ -658,6 +901,148 @@ export class Exchange {
       }
     }) as TransactionInstruction)
   }
+  public async setCollateralLiquidationThreshold(
+    collateralAddress: PublicKey,
+    liquidationThreshold: Decimal
+  ) {
+    return await (this.program.instruction.setCollateralLiquidationThreshold(
+      liquidationThreshold,
+      {
+        accounts: {
+          state: this.stateAddress,
+          admin: this.state.admin,
+          assetsList: this.state.assetsList,
+          collateralAddress: collateralAddress
+        }
+      }
+    ) as TransactionInstruction)
+  }
+  public async setCollateralLiquidationMode(
+    collateralAddress: PublicKey,
+    liquidationMode: LiquidationMode
+  ) {
+    return await (this.program.instruction.setCollateralLiquidationMode(liquidationMode, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin,
+        assetsList: this.state.assetsList,
+        collateralAddress: collateralAddress
+      }
+    }) as TransactionInstruction)
+  }
+  public async deprecateCollateral(collateralAddress: PublicKey, deprecationLength: number) {
+    return await (this.program.instruction.deprecateCollateral(deprecationLength, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin,
+        assetsList: this.state.assetsList,
+        collateralAddress: collateralAddress
+      }
+    }) as TransactionInstruction)
+  }
+  public async removeCollateral(collateralAddress: PublicKey) {
+    return await (this.program.instruction.removeCollateral({
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin,
+        assetsList: this.state.assetsList,
+        collateralAddress: collateralAddress
+      }
+    }) as TransactionInstruction)
+  }
+  public async removeSyntheticInstruction(syntheticAddress: PublicKey) {
+    return await (this.program.instruction.removeSynthetic({
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin,
+        assetsList: this.state.assetsList,
+        syntheticAddress: syntheticAddress
+      }
+    }) as TransactionInstruction)
+  }
+  public async removeAssetInstruction(feedAddress: PublicKey) {
+    return await (this.program.instruction.removeAsset({
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin,
+        assetsList: this.state.assetsList,
+        feedAddress: feedAddress
+      }
+    }) as TransactionInstruction)
+  }
+  public async migrateStateInstruction() {
+    return await (this.program.instruction.migrateState({
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
+  public async setLiquidationBounty({ bounty, cooldown }: SetLiquidationBounty) {
+    return await (this.program.instruction.setLiquidationBounty(bounty, cooldown, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
+  public async setLiquidationCriticalRatio(liquidationCriticalRatio: Decimal) {
+    return await (this.program.instruction.setLiquidationCriticalRatio(liquidationCriticalRatio, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
+  public async setLiquidationPriceSourceInstruction(liquidationPriceSource: PriceSource) {
+    return await (this.program.instruction.setLiquidationPriceSource(liquidationPriceSource, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
+  public async setLiquidationAuction({
+    startDiscount,
+    maxDiscount,
+    auctionLength
+  }: SetLiquidationAuction) {
+    return await (this.program.instruction.setLiquidationAuction(
+      startDiscount,
+      maxDiscount,
+      auctionLength,
+      {
+        accounts: {
+          state: this.stateAddress,
+          admin: this.state.admin
+        }
+      }
+    ) as TransactionInstruction)
+  }
+  public async setInsuranceRatios({ swapTaxRatio, penaltyRatio }: SetInsuranceRatios) {
+    return await (this.program.instruction.setInsuranceRatios(swapTaxRatio, penaltyRatio, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
+  public async setInsuranceFundTarget(insuranceFundTarget: Decimal) {
+    return await (this.program.instruction.setInsuranceFundTarget(insuranceFundTarget, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
+  public async setInsuranceWithdrawalDelay(insuranceWithdrawalDelay: number) {
+    return await (this.program.instruction.setInsuranceWithdrawalDelay(insuranceWithdrawalDelay, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    }) as TransactionInstruction)
+  }
   public async setAdmin(newAdmin: PublicKey) {
     return await (this.program.instruction.setAdmin({
       accounts: {
@@ -743,6 +1128,22 @@ export class Exchange {
     }) as TransactionInstruction
     return ix
   }
+  public async closeSettlementInstruction(tokenToSettle: PublicKey) {
+    const settlement = await this.getSettlementAccountForSynthetic(tokenToSettle)
+    const [settlementAddress] = await PublicKey.findProgramAddress(
+      [Buffer.from(utils.bytes.utf8.encode('settlement')), tokenToSettle.toBuffer()],
+      this.program.programId
+    )
+    return (await this.program.instruction.closeSettlement({
+      accounts: {
+        settlement: settlementAddress,
+        state: this.stateAddress,
+        admin: this.state.admin,
+        tokenToSettle: tokenToSettle,
+        settlementReserve: settlement.reserveAddress
+      }
+    })) as TransactionInstruction
+  }
   public async setSwapTaxRatioInstruction(swapTaxRatio: Decimal) {
     return await (this.program.instruction.setSwapTaxRatio(swapTaxRatio, {
       accounts: {
@@ -970,6 +1371,17 @@ export class Exchange {
     const txs = await this.processOperations([tx])
     return sendAndConfirmRawTransaction(this.connection, txs[0].serialize())
   }
+  public async migrateExchangeAccount(
+    payer: Keypair | Account,
+    owner: PublicKey = payer.publicKey,
+    id: number = 0
+  ) {
+    // Moves account created before sub-accounts to [accountv1, owner, id]
+    // anyone can pay for migration, rent of legacy account goes to owner
+    const migrateIx = await this.migrateExchangeAccountInstruction(owner, payer.publicKey, id)
+    await signAndSend(new Transaction().add(migrateIx), [payer], this.connection)
+    return await this.getExchangeAccountAddress(owner, id)
+  }
 
   // Asset list
   public async getAssetsList(assetsList: PublicKey): Promise<AssetsList> {
@@ -1100,6 +1512,27 @@ export class Exchange {
       }
     })) as TransactionInstruction
   }
+  public async requestInsuranceFundWithdrawalInstruction(amount: BN) {
+    return (await this.program.instruction.requestInsuranceFundWithdrawal(amount, {
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin
+      }
+    })) as TransactionInstruction
+  }
+  public async withdrawInsuranceFundInstruction(to: PublicKey) {
+    return (await this.program.instruction.withdrawInsuranceFund({
+      accounts: {
+        state: this.stateAddress,
+        admin: this.state.admin,
+        exchangeAuthority: this.exchangeAuthority,
+        assetsList: this.state.assetsList,
+        usdToken: this.assetsList.synthetics[0].assetAddress,
+        to: to,
+        tokenProgram: TOKEN_PROGRAM_ID
+      }
+    })) as TransactionInstruction
+  }
   public async withdrawAccumulatedDebtInterestInstruction({ amount, to }: AdminWithdraw) {
     return (await this.program.instruction.withdrawAccumulatedDebtInterest(amount, {
       accounts: {
@@ -1151,18 +1584,13 @@ export class Exchange {
     liquidationThreshold,
     liquidationPenaltyLiquidator,
     liquidationPenaltyExchange,
-    liquidationRatio
+    liquidationRatio,
+    vaultType = 0
   }: CreateVault) {
-    const [vaultAddress, bump] = await PublicKey.findProgramAddress(
-      [
-        Buffer.from(utils.bytes.utf8.encode('vaultv1')),
-        synthetic.toBuffer(),
-        collateral.toBuffer()
-      ],
-      this.program.programId
-    )
+    const { vaultAddress, bump } = await this.getVaultAddress(synthetic, collateral, vaultType)
     const ix = await this.program.instruction.createVault(
       bump,
+      vaultType,
       debtInterestRate,
       collateralRatio,
       maxBorrow,
@@ -1186,15 +1614,13 @@ export class Exchange {
     )
     return { ix, vaultAddress }
   }
-  public async createVaultEntryInstruction({ owner, synthetic, collateral }: CreateVaultEntry) {
-    const [vaultAddress] = await PublicKey.findProgramAddress(
-      [
-        Buffer.from(utils.bytes.utf8.encode('vaultv1')),
-        synthetic.toBuffer(),
-        collateral.toBuffer()
-      ],
-      this.program.programId
-    )
+  public async createVaultEntryInstruction({
+    owner,
+    synthetic,
+    collateral,
+    vaultType = 0
+  }: CreateVaultEntry) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
     const [vaultEntryAddress, bump] = await PublicKey.findProgramAddress(
       [
         Buffer.from(utils.bytes.utf8.encode('vault_entryv1')),
@@ -1218,16 +1644,139 @@ export class Exchange {
     })
     return { ix, vaultEntryAddress }
   }
+  public async migrateVaultInstruction({ synthetic, collateral }: MigrateVault) {
+    const legacy = await this.getLegacyVaultAddress(synthetic, collateral)
+    const { vaultAddress, bump } = await this.getVaultAddress(synthetic, collateral, 0)
+    const ix = await this.program.instruction.migrateVault(bump, legacy.bump, {
+      accounts: {
+        state: this.stateAddress,
+        legacyVault: legacy.vaultAddress,
+        vault: vaultAddress,
+        admin: this.state.admin,
+        assetsList: this.state.assetsList,
+        synthetic: synthetic,
+        collateral: collateral,
+        rent: SYSVAR_RENT_PUBKEY,
+        systemProgram: SystemProgram.programId
+      }
+    })
+    return { ix, vaultAddress }
+  }
+  public async migrateVaultEntryInstruction({
+    owner,
+    synthetic,
+    collateral,
+    payer = owner
+  }: MigrateVaultEntry) {
+    // vault has to be migrated first, anyone can pay for migration of entry
+    const legacyVault = await this.getLegacyVaultAddress(synthetic, collateral)
+    const legacy = await this.getLegacyVaultEntryAddress(synthetic, collateral, owner)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, 0)
+    const { vaultEntryAddress, bump } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      0
+    )
+    const ix = await this.program.instruction.migrateVaultEntry(
+      bump,
+      legacy.bump,
+      legacyVault.bump,
+      {
+        accounts: {
+          state: this.stateAddress,
+          legacyVault: legacyVault.vaultAddress,
+          legacyVaultEntry: legacy.vaultEntryAddress,
+          vaultEntry: vaultEntryAddress,
+          payer: payer,
+          owner: owner,
+          vault: vaultAddress,
+          synthetic: synthetic,
+          collateral: collateral,
+          rent: SYSVAR_RENT_PUBKEY,
+          systemProgram: SystemProgram.programId
+        }
+      }
+    )
+    return { ix, vaultEntryAddress }
+  }
+  public async closeVaultEntryInstruction({
+    owner,
+    synthetic,
+    collateral,
+    vaultType
+  }: CloseVaultEntry) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+    return (await this.program.instruction.closeVaultEntry({
+      accounts: {
+        vaultEntry: vaultEntryAddress,
+        owner: owner,
+        vault: vaultAddress,
+        synthetic: synthetic,
+        collateral: collateral
+      }
+    })) as TransactionInstruction
+  }
+  public async transferVaultPositionInstruction({
+    owner,
+    newOwner,
+    synthetic,
+    collateral,
+    vaultType
+  }: TransferVaultPosition) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+    const { vaultEntryAddress: newVaultEntryAddress, bump } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      newOwner,
+      vaultType
+    )
+    const ix = (await this.program.instruction.transferVaultPosition(bump, {
+      accounts: {
+        state: this.stateAddress,
+        vaultEntry: vaultEntryAddress,
+        newVaultEntry: newVaultEntryAddress,
+        owner: owner,
+        newOwner: newOwner,
+        vault: vaultAddress,
+        synthetic: synthetic,
+        collateral: collateral,
+        rent: SYSVAR_RENT_PUBKEY,
+        systemProgram: SystemProgram.programId
+      }
+    })) as TransactionInstruction
+    return { ix, newVaultEntryAddress }
+  }
   public async vaultDepositInstruction({
     owner,
     synthetic,
     collateral,
     userCollateralAccount,
     reserveAddress,
-    amount
+    amount,
+    vaultType,
+    entryOwner = owner
   }: VaultDepositInstruction) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    // depositor can credit vault entry of any user
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      entryOwner,
+      vaultType
+    )
 
     return (await this.program.instruction.depositVault(amount, {
       accounts: {
@@ -1255,7 +1804,9 @@ export class Exchange {
     userCollateralAccount,
     reserveAddress,
     collateralToken,
-    signers
+    signers,
+    vaultType,
+    entryOwner
   }: DepositVault) {
     const depositVaultIx = await this.vaultDepositInstruction({
       owner,
@@ -1263,7 +1814,9 @@ export class Exchange {
       collateral,
       userCollateralAccount,
       reserveAddress,
-      amount
+      amount,
+      vaultType,
+      entryOwner
     })
     const approveIx = Token.createApproveInstruction(
       collateralToken.programId,
@@ -1279,89 +1832,218 @@ export class Exchange {
       this.connection
     )
   }
-  public async borrowVaultInstruction({
+  public async borrowVaultInstruction({
+    owner,
+    to,
+    synthetic,
+    collateral,
+    amount,
+    vaultType
+  }: BorrowVaultInstruction) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+
+    return (await this.program.instruction.borrowVault(amount, {
+      accounts: {
+        synthetic,
+        collateral,
+        owner,
+        to,
+        state: this.stateAddress,
+        vaultEntry: vaultEntryAddress,
+        vault: vaultAddress,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        assetsList: this.state.assetsList,
+        exchangeAuthority: this.exchangeAuthority,
+        rent: SYSVAR_RENT_PUBKEY,
+        systemProgram: SystemProgram.programId
+      }
+    })) as TransactionInstruction
+  }
+  public async liquidateVaultInstruction({
+    owner,
+    synthetic,
+    collateral,
+    amount,
+    liquidator,
+    liquidatorCollateralAccount,
+    liquidatorSyntheticAccount,
+    vaultType,
+    basketCollateral = collateral
+  }: LiquidateVaultInstruction) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+    const vaultData = await this.getVaultForPair(synthetic, collateral, vaultType)
+    // liquidator picks which collateral of vault is seized
+    const collateralData = this.assetsList.collaterals.find((c) =>
+      c.collateralAddress.equals(basketCollateral)
+    )
+    return (await this.program.instruction.liquidateVault(amount, {
+      accounts: {
+        state: this.stateAddress,
+        vaultEntry: vaultEntryAddress,
+        vault: vaultAddress,
+        collateralReserve: this.getVaultCollateralReserve(vaultData, basketCollateral),
+        liquidationFund: collateralData.liquidationFund,
+        synthetic,
+        collateral,
+        assetsList: this.state.assetsList,
+        liquidatorSyntheticAccount,
+        liquidatorCollateralAccount,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        owner,
+        liquidator,
+        exchangeAuthority: this.exchangeAuthority
+      }
+    })) as TransactionInstruction
+  }
+  public async borrowVault({
+    owner,
+    to,
+    synthetic,
+    collateral,
+    amount,
+    signers,
+    vaultType
+  }: BorrowVault) {
+    await this.getState()
+    const borrowVaultIx = await this.borrowVaultInstruction({
+      owner,
+      to,
+      synthetic,
+      collateral,
+      amount,
+      vaultType
+    })
+    return this.updatePricesAndSend([borrowVaultIx], signers, this.assetsList.headAssets >= 20)
+  }
+  public async leverageVaultInstruction({
+    owner,
+    synthetic,
+    collateral,
+    targetRatio,
+    iterations,
+    minCollateralOut,
+    vaultType,
+    swaplineSynthetic = synthetic
+  }: LeverageVaultInstruction) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+    const vault = await this.getVaultForPair(synthetic, collateral, vaultType)
+    // borrowed synthetic is swapped to swaplineSynthetic if they differ, then to collateral
+    const [swaplineAddress] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(utils.bytes.utf8.encode('swaplinev1')),
+        swaplineSynthetic.toBuffer(),
+        collateral.toBuffer()
+      ],
+      this.program.programId
+    )
+    const swapline = await this.getSwapline(swaplineAddress)
+
+    return (await this.program.instruction.leverageVault(
+      targetRatio,
+      iterations,
+      minCollateralOut,
+      {
+        accounts: {
+          synthetic,
+          swaplineSynthetic,
+          collateral,
+          owner,
+          state: this.stateAddress,
+          vaultEntry: vaultEntryAddress,
+          vault: vaultAddress,
+          swapline: swaplineAddress,
+          assetsList: this.state.assetsList,
+          reserveAddress: vault.collateralReserve,
+          swaplineReserve: swapline.collateralReserve,
+          tokenProgram: TOKEN_PROGRAM_ID,
+          exchangeAuthority: this.exchangeAuthority
+        }
+      }
+    )) as TransactionInstruction
+  }
+  public async closeVaultPositionInstruction({
     owner,
-    to,
     synthetic,
     collateral,
-    amount
-  }: BorrowVaultInstruction) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
+    userCollateralAccount,
+    maxCollateralIn,
+    vaultType,
+    swaplineSynthetic = synthetic
+  }: CloseVaultPositionInstruction) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+    const vault = await this.getVaultForPair(synthetic, collateral, vaultType)
+    // collateral is swapped to swaplineSynthetic, then swapped to synthetic if they differ
+    const [swaplineAddress] = await PublicKey.findProgramAddress(
+      [
+        Buffer.from(utils.bytes.utf8.encode('swaplinev1')),
+        swaplineSynthetic.toBuffer(),
+        collateral.toBuffer()
+      ],
+      this.program.programId
+    )
+    const swapline = await this.getSwapline(swaplineAddress)
 
-    return (await this.program.instruction.borrowVault(amount, {
+    return (await this.program.instruction.closeVaultPosition(maxCollateralIn, {
       accounts: {
         synthetic,
+        swaplineSynthetic,
         collateral,
         owner,
-        to,
-        state: this.stateAddress,
-        vaultEntry: vaultEntryAddress,
-        vault: vaultAddress,
-        tokenProgram: TOKEN_PROGRAM_ID,
-        assetsList: this.state.assetsList,
-        exchangeAuthority: this.exchangeAuthority,
-        rent: SYSVAR_RENT_PUBKEY,
-        systemProgram: SystemProgram.programId
-      }
-    })) as TransactionInstruction
-  }
-  public async liquidateVaultInstruction({
-    owner,
-    synthetic,
-    collateral,
-    amount,
-    liquidator,
-    liquidatorCollateralAccount,
-    liquidatorSyntheticAccount
-  }: LiquidateVaultInstruction) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
-    const vaultData = await this.getVaultForPair(synthetic, collateral)
-    const collateralData = this.assetsList.collaterals.find((c) =>
-      c.collateralAddress.equals(collateral)
-    )
-    return (await this.program.instruction.liquidateVault(amount, {
-      accounts: {
+        userCollateralAccount,
         state: this.stateAddress,
         vaultEntry: vaultEntryAddress,
         vault: vaultAddress,
-        collateralReserve: vaultData.collateralReserve,
-        liquidationFund: collateralData.liquidationFund,
-        synthetic,
-        collateral,
+        swapline: swaplineAddress,
         assetsList: this.state.assetsList,
-        liquidatorSyntheticAccount,
-        liquidatorCollateralAccount,
+        reserveAddress: vault.collateralReserve,
+        swaplineReserve: swapline.collateralReserve,
         tokenProgram: TOKEN_PROGRAM_ID,
-        owner,
-        liquidator,
         exchangeAuthority: this.exchangeAuthority
       }
     })) as TransactionInstruction
   }
-  public async borrowVault({ owner, to, synthetic, collateral, amount, signers }: BorrowVault) {
-    await this.getState()
-    const borrowVaultIx = await this.borrowVaultInstruction({
-      owner,
-      to,
-      synthetic,
-      collateral,
-      amount
-    })
-    return this.updatePricesAndSend([borrowVaultIx], signers, this.assetsList.headAssets >= 20)
-  }
   public async withdrawVaultInstruction({
     amount,
     owner,
     collateral,
     synthetic,
-    userCollateralAccount
+    userCollateralAccount,
+    vaultType,
+    basketCollateral = collateral
   }: WithdrawVault) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
 
-    const vault = await this.getVaultForPair(synthetic, collateral)
+    const vault = await this.getVaultForPair(synthetic, collateral, vaultType)
 
     const ix = await this.program.instruction.withdrawVault(amount, {
       accounts: {
@@ -1372,7 +2054,7 @@ export class Exchange {
         state: this.stateAddress,
         vaultEntry: vaultEntryAddress,
         vault: vaultAddress,
-        reserveAddress: vault.collateralReserve,
+        reserveAddress: this.getVaultCollateralReserve(vault, basketCollateral),
         tokenProgram: TOKEN_PROGRAM_ID,
         assetsList: this.state.assetsList,
         exchangeAuthority: this.exchangeAuthority,
@@ -1388,12 +2070,18 @@ export class Exchange {
     owner,
     synthetic,
     collateral,
-    userTokenAccountRepay
+    userTokenAccountRepay,
+    vaultType
   }: RepayVaultInstruction) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
 
-    const vault = await this.getVaultForPair(synthetic, collateral)
+    const vault = await this.getVaultForPair(synthetic, collateral, vaultType)
 
     const ix = await this.program.instruction.repayVault(amount, {
       accounts: {
@@ -1421,7 +2109,8 @@ export class Exchange {
     synthetic,
     collateral,
     userTokenAccountRepay,
-    signers
+    signers,
+    vaultType
   }: RepayVault) {
     const approveIx = Token.createApproveInstruction(
       TOKEN_PROGRAM_ID,
@@ -1436,13 +2125,52 @@ export class Exchange {
       owner,
       synthetic,
       collateral,
-      userTokenAccountRepay
+      userTokenAccountRepay,
+      vaultType
     })
 
     await signAndSend(new Transaction().add(approveIx).add(repayIx), signers, this.connection)
   }
-  public async setVaultHaltedInstruction({ halted, collateral, synthetic }: SetVaultHalted) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+  public async repayVaultForInstruction({
+    amount,
+    owner,
+    signer,
+    synthetic,
+    collateral,
+    signerTokenAccountRepay,
+    vaultType
+  }: RepayVaultForInstruction) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    // debt of owner is repaid with tokens of signer
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
+
+    return (await this.program.instruction.repayVaultFor(amount, {
+      accounts: {
+        signer,
+        synthetic,
+        collateral,
+        signerTokenAccountRepay,
+        state: this.stateAddress,
+        vaultEntry: vaultEntryAddress,
+        vault: vaultAddress,
+        tokenProgram: TOKEN_PROGRAM_ID,
+        assetsList: this.state.assetsList,
+        exchangeAuthority: this.exchangeAuthority
+      }
+    })) as TransactionInstruction
+  }
+  public async setVaultHaltedInstruction({
+    halted,
+    collateral,
+    synthetic,
+    vaultType
+  }: SetVaultHalted) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     const ix = await this.program.instruction.setVaultHalted(halted, {
       accounts: {
@@ -1461,9 +2189,9 @@ export class Exchange {
   }
   public async setVaultDebtInterestRateInstruction(
     debtInterestRate: Decimal,
-    { synthetic, collateral }: SetVaultParameter
+    { synthetic, collateral, vaultType }: SetVaultParameter
   ): Promise<TransactionInstruction> {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.setVaultDebtInterestRate(debtInterestRate, {
       accounts: {
@@ -1477,9 +2205,9 @@ export class Exchange {
   }
   public async setVaultLiquidationThresholdInstruction(
     liquidationThreshold: Decimal,
-    { synthetic, collateral }: SetVaultParameter
+    { synthetic, collateral, vaultType }: SetVaultParameter
   ) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.setVaultLiquidationThreshold(liquidationThreshold, {
       accounts: {
@@ -1493,9 +2221,9 @@ export class Exchange {
   }
   public async setVaultSetLiquidationRatioInstruction(
     liquidationRatio: Decimal,
-    { synthetic, collateral }: SetVaultParameter
+    { synthetic, collateral, vaultType }: SetVaultParameter
   ) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.setVaultSetLiquidationRatio(liquidationRatio, {
       accounts: {
@@ -1509,9 +2237,9 @@ export class Exchange {
   }
   public async setVaultLiquidationPenaltyLiquidatorInstruction(
     liquidationPenaltyLiquidator: Decimal,
-    { synthetic, collateral }: SetVaultParameter
+    { synthetic, collateral, vaultType }: SetVaultParameter
   ) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.setVaultLiquidationPenaltyLiquidator(
       liquidationPenaltyLiquidator,
@@ -1528,9 +2256,9 @@ export class Exchange {
   }
   public async setVaultLiquidationPenaltyExchangeInstruction(
     liquidationPenaltyExchange: Decimal,
-    { synthetic, collateral }: SetVaultParameter
+    { synthetic, collateral, vaultType }: SetVaultParameter
   ) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.setVaultLiquidationPenaltyExchange(
       liquidationPenaltyExchange,
@@ -1547,9 +2275,9 @@ export class Exchange {
   }
   public async setVaultMaxBorrowInstruction(
     maxBorrow: Decimal,
-    { synthetic, collateral }: SetVaultParameter
+    { synthetic, collateral, vaultType }: SetVaultParameter
   ) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.setVaultMaxBorrow(maxBorrow, {
       accounts: {
@@ -1561,13 +2289,54 @@ export class Exchange {
       }
     })) as TransactionInstruction
   }
+  public async setVaultOracleSettingsInstruction(
+    { maxDelay, maxPriceDeviation, requireTrading }: OracleSettings,
+    { synthetic, collateral, vaultType }: SetVaultParameter
+  ) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+
+    return (await this.program.instruction.setVaultOracleSettings(
+      maxDelay,
+      maxPriceDeviation,
+      requireTrading,
+      {
+        accounts: {
+          synthetic,
+          collateral,
+          state: this.stateAddress,
+          admin: this.state.admin,
+          vault: vaultAddress
+        }
+      }
+    )) as TransactionInstruction
+  }
+  public async setVaultLiquidationPriceSourceInstruction(
+    liquidationPriceSource: PriceSource,
+    { synthetic, collateral, vaultType }: SetVaultParameter
+  ) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+
+    return (await this.program.instruction.setVaultLiquidationPriceSource(
+      liquidationPriceSource,
+      {
+        accounts: {
+          synthetic,
+          collateral,
+          state: this.stateAddress,
+          admin: this.state.admin,
+          vault: vaultAddress
+        }
+      }
+    )) as TransactionInstruction
+  }
   public async withdrawVaultAccumulatedInterestInstruction({
     synthetic,
     collateral,
     to,
-    amount
+    amount,
+    vaultType
   }: WithdrawVaultAccumulatedInterest) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
 
     return (await this.program.instruction.withdrawVaultAccumulatedInterest(amount, {
       accounts: {
@@ -1583,6 +2352,57 @@ export class Exchange {
       }
     })) as TransactionInstruction
   }
+  public async addVaultCollateralInstruction({
+    synthetic,
+    collateral,
+    basketCollateral,
+    collateralReserve,
+    collateralRatio,
+    liquidationThreshold,
+    vaultType
+  }: AddVaultCollateral) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    return (await this.program.instruction.addVaultCollateral(
+      collateralRatio,
+      liquidationThreshold,
+      {
+        accounts: {
+          state: this.stateAddress,
+          admin: this.state.admin,
+          vault: vaultAddress,
+          assetsList: this.state.assetsList,
+          collateralReserve,
+          synthetic,
+          collateral,
+          basketCollateral
+        }
+      }
+    )) as TransactionInstruction
+  }
+  public async setVaultBasketCollateralInstruction({
+    synthetic,
+    collateral,
+    basketCollateral,
+    collateralRatio,
+    liquidationThreshold,
+    vaultType
+  }: SetVaultBasketCollateral) {
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    return (await this.program.instruction.setVaultBasketCollateral(
+      collateralRatio,
+      liquidationThreshold,
+      {
+        accounts: {
+          state: this.stateAddress,
+          admin: this.state.admin,
+          vault: vaultAddress,
+          synthetic,
+          collateral,
+          basketCollateral
+        }
+      }
+    )) as TransactionInstruction
+  }
   public async updatePrices(assetsList: PublicKey) {
     const assetsListData = await this.getAssetsList(assetsList)
     const feedAddresses = assetsListData.assets
@@ -1625,10 +2445,16 @@ export class Exchange {
   public async triggerVaultEntryDebtAdjustmentInstruction({
     synthetic,
     collateral,
-    owner
+    owner,
+    vaultType
   }: VaultEntryId) {
-    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral)
-    const { vaultEntryAddress } = await this.getVaultEntryAddress(synthetic, collateral, owner)
+    const { vaultAddress } = await this.getVaultAddress(synthetic, collateral, vaultType)
+    const { vaultEntryAddress } = await this.getVaultEntryAddress(
+      synthetic,
+      collateral,
+      owner,
+      vaultType
+    )
 
     return (await this.program.instruction.triggerVaultEntryDebtAdjustment({
       accounts: {
@@ -1688,6 +2514,23 @@ export interface Collateral {
   reserveBalance: Decimal
   collateralRatio: Decimal
   maxCollateral: Decimal
+  liquidationMode: LiquidationMode
+  liquidationThreshold: Decimal
+  deprecated: boolean
+  deprecationStart: BN
+  deprecationLength: number
+  initialCollateralRatio: Decimal
+  initialLiquidationThreshold: Decimal
+  vaultCount: number
+}
+export enum LiquidationMode {
+  Fixed = 0,
+  Auction = 1
+}
+export enum PriceSource {
+  Spot = 0,
+  Twap = 1,
+  Conservative = 2
 }
 export interface Synthetic {
   assetIndex: number
@@ -1724,6 +2567,19 @@ export interface SetPriceFeedInstruction {
   oldPriceFeed: PublicKey
 }
 
+export interface SetLiquidationAuction {
+  startDiscount: Decimal
+  maxDiscount: Decimal
+  auctionLength: number
+}
+export interface SetLiquidationBounty {
+  bounty: Decimal
+  cooldown: number
+}
+export interface SetInsuranceRatios {
+  swapTaxRatio: Decimal
+  penaltyRatio: Decimal
+}
 export interface SetLiquidationPenaltiesInstruction {
   penaltyToExchange: Decimal
   penaltyToLiquidator: Decimal
@@ -1830,6 +2686,28 @@ export interface LiquidateInstruction {
   amount: BN
 }
 
+export interface MarkAccountForLiquidationInstruction {
+  exchangeAccount: PublicKey
+  signer: PublicKey
+  keeperAccount: PublicKey
+}
+
+export interface FlashLiquidateInstruction extends LiquidateInstruction {
+  callback: TransactionInstruction
+}
+
+export interface LiquidateMultiInstruction {
+  exchangeAccount: PublicKey
+  liquidatorUsdAccount: PublicKey
+  collaterals: LiquidatedCollateral[]
+  signer: PublicKey
+  amount: BN
+}
+export interface LiquidatedCollateral {
+  reserveAccount: PublicKey
+  liquidationFund: PublicKey
+  liquidatorCollateralAccount: PublicKey
+}
 export interface BurnInstruction {
   exchangeAccount: PublicKey
   owner: PublicKey
@@ -1896,6 +2774,22 @@ export interface ExchangeState {
   penaltyToExchange: Decimal
   liquidationBuffer: number
   staking: Staking
+  liquidationAuctionStartDiscount: Decimal
+  liquidationAuctionMaxDiscount: Decimal
+  liquidationAuctionLength: number
+  badDebt: Decimal
+  insuranceFund: Decimal
+  insuranceFundTarget: Decimal
+  insuranceSwapTaxRatio: Decimal
+  insurancePenaltyRatio: Decimal
+  liquidationCriticalRatio: Decimal
+  liquidationBounty: Decimal
+  liquidationBountyCooldown: number
+  liquidationPriceSource: PriceSource
+  version: number
+  insuranceWithdrawalDelay: number
+  insuranceWithdrawalAmount: Decimal
+  insuranceWithdrawalSlot: BN
 }
 export interface Staking {
   fundAccount: PublicKey
@@ -1910,6 +2804,24 @@ export interface StakingRound {
   amount: Decimal
   allPoints: BN
 }
+export interface CollateralPreview {
+  collateralAddress: PublicKey
+  liquidationPrice: Decimal
+  maxWithdrawable: Decimal
+}
+export interface LiquidationPreview {
+  owner: PublicKey
+  collateralRatio: Decimal
+  maxMintable: Decimal
+  collaterals: Array<CollateralPreview>
+}
+export interface CollateralDeposited {
+  benefactor: PublicKey
+  owner: PublicKey
+  position: PublicKey
+  collateralAddress: PublicKey
+  amount: BN
+}
 export interface ExchangeAccount {
   owner: PublicKey
   version: number
@@ -1918,6 +2830,27 @@ export interface ExchangeAccount {
   userStakingData: UserStaking
   head: number
   collaterals: Array<CollateralEntry>
+  delegate: PublicKey
+  delegatePermissions: number
+  id: number
+  liquidationBountySlot: BN
+}
+export enum DelegatePermission {
+  Burn = 2,
+  Mint = 4,
+  Withdraw = 8
+}
+export interface SetDelegate {
+  owner: PublicKey
+  id?: number
+  delegate: PublicKey
+  permissions: number
+}
+export interface TransferPosition {
+  owner: PublicKey
+  id?: number
+  newOwner: PublicKey
+  newId?: number
 }
 export interface Settlement {
   reserveAddress: PublicKey
@@ -1945,6 +2878,16 @@ export interface Vault {
   collateralAmount: Decimal
   maxBorrow: Decimal
   lastUpdate: BN
+  vaultType: number
+  oracleSettings: OracleSettings
+  liquidationPriceSource: PriceSource
+  basketHead: number
+  basket: BasketCollateral[]
+}
+export interface OracleSettings {
+  maxDelay: number
+  maxPriceDeviation: Decimal
+  requireTrading: boolean
 }
 export interface VaultEntry {
   owner: PublicKey
@@ -1952,24 +2895,29 @@ export interface VaultEntry {
   lastAccumulatedInterestRate: Decimal
   syntheticAmount: Decimal
   collateralAmount: Decimal
+  basketAmounts: Decimal[]
 }
 export interface VaultDepositInstruction {
   owner: PublicKey
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   userCollateralAccount: PublicKey
   reserveAddress: PublicKey
   amount: BN
+  entryOwner?: PublicKey
 }
 export interface DepositVault {
   amount: BN
   owner: PublicKey
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   userCollateralAccount: PublicKey
   reserveAddress: PublicKey
   collateralToken: Token
   signers: Array<Account | Keypair>
+  entryOwner?: PublicKey
 }
 export interface LiquidateVaultInstruction {
   owner: PublicKey
@@ -1978,20 +2926,43 @@ export interface LiquidateVaultInstruction {
   liquidatorCollateralAccount: PublicKey
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   amount: BN
+  basketCollateral?: PublicKey
 }
 export interface BorrowVaultInstruction {
   owner: PublicKey
   to: PublicKey
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   amount: BN
 }
+export interface LeverageVaultInstruction {
+  owner: PublicKey
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  targetRatio: Decimal
+  iterations: number
+  minCollateralOut: BN
+  swaplineSynthetic?: PublicKey
+}
+export interface CloseVaultPositionInstruction {
+  owner: PublicKey
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  userCollateralAccount: PublicKey
+  maxCollateralIn: BN
+  swaplineSynthetic?: PublicKey
+}
 export interface BorrowVault {
   owner: PublicKey
   to: PublicKey
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   amount: BN
   signers: Array<Account | Keypair>
 }
@@ -2000,8 +2971,10 @@ export interface WithdrawVault {
   amount: BN
   owner: PublicKey
   collateral: PublicKey
+  vaultType?: number
   synthetic: PublicKey
   userCollateralAccount: PublicKey
+  basketCollateral?: PublicKey
 }
 
 export interface RepayVaultInstruction {
@@ -2009,11 +2982,21 @@ export interface RepayVaultInstruction {
   owner: PublicKey
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   userTokenAccountRepay: PublicKey
 }
 export interface RepayVault extends RepayVaultInstruction {
   signers: Array<Account | Keypair>
 }
+export interface RepayVaultForInstruction {
+  amount: BN
+  owner: PublicKey
+  signer: PublicKey
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  signerTokenAccountRepay: PublicKey
+}
 export interface CollateralEntry {
   amount: BN
   collateralAddress: PublicKey
@@ -2029,6 +3012,7 @@ export interface UserStaking {
 export interface CreateVault {
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   collateralReserve: PublicKey
   debtInterestRate: Decimal
   collateralRatio: Decimal
@@ -2042,12 +3026,61 @@ export interface CreateVault {
 export interface CreateVaultEntry {
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   owner: PublicKey
 }
+export interface MigrateVault {
+  synthetic: PublicKey
+  collateral: PublicKey
+}
+export interface MigrateVaultEntry {
+  synthetic: PublicKey
+  collateral: PublicKey
+  owner: PublicKey
+  payer?: PublicKey
+}
+export interface CloseVaultEntry {
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  owner: PublicKey
+}
+export interface TransferVaultPosition {
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  owner: PublicKey
+  newOwner: PublicKey
+}
 export interface SetVaultHalted {
   halted: boolean
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
+}
+export interface BasketCollateral {
+  collateral: PublicKey
+  collateralReserve: PublicKey
+  collateralRatio: Decimal
+  liquidationThreshold: Decimal
+  collateralAmount: Decimal
+}
+export interface AddVaultCollateral {
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  basketCollateral: PublicKey
+  collateralReserve: PublicKey
+  collateralRatio: Decimal
+  liquidationThreshold: Decimal
+}
+export interface SetVaultBasketCollateral {
+  synthetic: PublicKey
+  collateral: PublicKey
+  vaultType?: number
+  basketCollateral: PublicKey
+  collateralRatio: Decimal
+  liquidationThreshold: Decimal
 }
 
 export interface Decimal {
@@ -2089,17 +3122,20 @@ export interface WithdrawSwaplineFee {
 export interface SetVaultParameter {
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
 }
 
 export interface VaultEntryId {
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   owner: PublicKey
 }
 
 export interface WithdrawVaultAccumulatedInterest {
   synthetic: PublicKey
   collateral: PublicKey
+  vaultType?: number
   to: PublicKey
   amount: BN
 }
@@ -9,7 +9,14 @@ import {
   Account,
   Keypair
 } from '@solana/web3.js'
-import { Asset, AssetsList, Collateral, Decimal, ExchangeAccount } from './exchange'
+import {
+  Asset,
+  AssetsList,
+  Collateral,
+  CollateralEntry,
+  Decimal,
+  ExchangeAccount
+} from './exchange'
 
 export const DEFAULT_PUBLIC_KEY = new PublicKey(0)
 export const ORACLE_OFFSET = 8
@@ -64,7 +71,19 @@ export enum ERRORS_EXCHANGE {
   USER_BORROW_LIMIT = '0x14d', // 33
   VAULT_BORROW_LIMIT = '0x14e', // 34
   VAULT_WITHDRAW_LIMIT = '0x14f', // 35
-  INVALID_ACCOUNT = '0x150' // 36
+  INVALID_ACCOUNT = '0x150', // 36
+  NO_BAD_DEBT = '0x151', // 37
+  FLASH_LIQUIDATION_NOT_REPAID = '0x152', // 38
+  COLLATERAL_DEPRECATED = '0x153', // 39
+  COLLATERAL_NOT_REMOVABLE = '0x154', // 40
+  ASSET_IN_USE = '0x155', // 41
+  COLLATERAL_ENTRIES_LIMIT = '0x156', // 42
+  OUTDATED_ACCOUNT_VERSION = '0x157', // 43
+  ACCOUNT_NOT_EMPTY = '0x158', // 44
+  LEVERAGE_SLIPPAGE = '0x159', // 45
+  CLOSE_SLIPPAGE = '0x15a', // 46
+  UNRELIABLE_PRICE = '0x15b', // 47
+  INSURANCE_WITHDRAWAL_LOCKED = '0x15c' // 48
 }
 export const signAndSend = async (
   tx: Transaction,
@@ -157,12 +176,22 @@ export const calculateDebt = (assetsList: AssetsList) => {
     new BN(0)
   )
 }
+export const getCollateralOfEntry = (entry: CollateralEntry, assetsList: AssetsList) => {
+  // index of entry can be outdated after removal of other collateral
+  const collateral = assetsList.collaterals[entry.index]
+  if (collateral !== undefined && collateral.collateralAddress.equals(entry.collateralAddress)) {
+    return collateral
+  }
+  return assetsList.collaterals.find((c) =>
+    c.collateralAddress.equals(entry.collateralAddress)
+  ) as Collateral
+}
 export const calculateUserCollateral = (
   exchangeAccount: ExchangeAccount,
   assetsList: AssetsList
 ) => {
   return exchangeAccount.collaterals.reduce((acc, entry) => {
-    const collateral = assetsList.collaterals[entry.index]
+    const collateral = getCollateralOfEntry(entry, assetsList)
     return acc.add(
       entry.amount
         .mul(assetsList.assets[collateral.assetIndex].price.val)
@@ -172,7 +201,7 @@ export const calculateUserCollateral = (
 }
 export const calculateUserMaxDebt = (exchangeAccount: ExchangeAccount, assetsList: AssetsList) => {
   return exchangeAccount.collaterals.reduce((acc, entry) => {
-    const collateral = assetsList.collaterals[entry.index]
+    const collateral = getCollateralOfEntry(entry, assetsList)
     const asset = assetsList.assets[collateral.assetIndex]
     return acc.add(
       entry.amount
@@ -183,6 +212,28 @@ export const calculateUserMaxDebt = (exchangeAccount: ExchangeAccount, assetsLis
     )
   }, new BN(0))
 }
+export const calculateUserMaxDebtForLiquidation = (
+  exchangeAccount: ExchangeAccount,
+  assetsList: AssetsList
+) => {
+  return exchangeAccount.collaterals.reduce((acc, entry) => {
+    const collateral = getCollateralOfEntry(entry, assetsList)
+    const asset = assetsList.assets[collateral.assetIndex]
+    // liquidation threshold is never lower than collateral ratio
+    const threshold =
+      collateral.liquidationThreshold.scale === collateral.collateralRatio.scale &&
+      collateral.liquidationThreshold.val.gt(collateral.collateralRatio.val)
+        ? collateral.liquidationThreshold
+        : collateral.collateralRatio
+    return acc.add(
+      entry.amount
+        .mul(asset.price.val)
+        .mul(threshold.val)
+        .divn(10 ** threshold.scale)
+        .div(new BN(10 ** (collateral.reserveBalance.scale + ORACLE_OFFSET - ACCURACY)))
+    )
+  }, new BN(0))
+}
 export const toEffectiveFee = (fee: Decimal, userCollateralBalance: BN) => {
   // decimals of token = 6
   const ONE_SNY = new BN(1000000)
@@ -48,7 +48,7 @@ import {
   fromPercentToInterestRate,
   toEffectiveFee
 } from '@synthetify/sdk/src/utils'
-import { Collateral, PriceStatus, Synthetic } from '../sdk/lib/exchange'
+import { Collateral, PriceSource, PriceStatus, Synthetic } from '../sdk/lib/exchange'
 import { Decimal } from '@synthetify/sdk/src/exchange'
 
 describe('ADMIN VAULTS', () => {
@@ -227,6 +227,13 @@ describe('ADMIN VAULTS', () => {
       assert.ok(eqDecimals(vault.mintAmount, toDecimal(new BN(0), XUSD_DECIMALS)))
       assert.ok(eqDecimals(vault.maxBorrow, maxBorrow))
       assert.ok(almostEqual(vault.lastUpdate, new BN(timestamp), new BN(5)))
+      assert.ok(vault.oracleSettings.maxDelay === exchange.state.maxDelay)
+      assert.ok(eqDecimals(vault.oracleSettings.maxPriceDeviation, percentToDecimal(0)))
+      assert.ok(vault.oracleSettings.requireTrading === false)
+      assert.ok(vault.liquidationPriceSource === PriceSource.Spot)
+
+      const assetsListAfter = await exchange.getAssetsList(assetsList)
+      assert.ok(assetsListAfter.collaterals[1].vaultCount === usdc.vaultCount + 1)
     })
   })
   describe('#triggerVaultEntryDebtAdjustmentInstruction', async () => {
@@ -556,4 +563,96 @@ describe('ADMIN VAULTS', () => {
       assert.ok(eqDecimals(vaultAfter.maxBorrow, maxBorrow))
     })
   })
+  describe('#setVaultOracleSettings', async () => {
+    const oracleSettings = {
+      maxDelay: 10,
+      maxPriceDeviation: percentToDecimal(20),
+      requireTrading: true
+    }
+    it('should failed without admin signature', async () => {
+      const ix = await exchange.setVaultOracleSettingsInstruction(oracleSettings, {
+        collateral: collateralAddress,
+        synthetic: syntheticAddress
+      })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [wallet], connection),
+        ERRORS.SIGNATURE
+      )
+    })
+    it('should fail cause invalid max price deviation scale', async () => {
+      const maxPriceDeviation = toDecimal(new BN(1000), oracleSettings.maxPriceDeviation.scale - 1)
+
+      const ix = await exchange.setVaultOracleSettingsInstruction(
+        { ...oracleSettings, maxPriceDeviation },
+        {
+          collateral: collateralAddress,
+          synthetic: syntheticAddress
+        }
+      )
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+      )
+    })
+    it('should set vault oracle settings', async () => {
+      const ix = await exchange.setVaultOracleSettingsInstruction(oracleSettings, {
+        collateral: collateralAddress,
+        synthetic: syntheticAddress
+      })
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+
+      const vaultAfter = await exchange.getVaultForPair(syntheticAddress, collateralAddress)
+      assert.ok(vaultAfter.oracleSettings.maxDelay === oracleSettings.maxDelay)
+      assert.ok(
+        eqDecimals(vaultAfter.oracleSettings.maxPriceDeviation, oracleSettings.maxPriceDeviation)
+      )
+      assert.ok(vaultAfter.oracleSettings.requireTrading === oracleSettings.requireTrading)
+    })
+  })
+  describe('#setVaultLiquidationPriceSource', async () => {
+    it('should failed without admin signature', async () => {
+      const ix = await exchange.setVaultLiquidationPriceSourceInstruction(PriceSource.Twap, {
+        collateral: collateralAddress,
+        synthetic: syntheticAddress
+      })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [wallet], connection),
+        ERRORS.SIGNATURE
+      )
+    })
+    it('should fail cause unknown price source', async () => {
+      const ix = await exchange.setVaultLiquidationPriceSourceInstruction(3, {
+        collateral: collateralAddress,
+        synthetic: syntheticAddress
+      })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+      )
+    })
+    it('should set vault liquidation price source', async () => {
+      const ix = await exchange.setVaultLiquidationPriceSourceInstruction(PriceSource.Twap, {
+        collateral: collateralAddress,
+        synthetic: syntheticAddress
+      })
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+
+      const vaultAfter = await exchange.getVaultForPair(syntheticAddress, collateralAddress)
+      assert.ok(vaultAfter.liquidationPriceSource === PriceSource.Twap)
+    })
+  })
+  describe('#removeCollateral', async () => {
+    it('should fail to remove collateral used by vault', async () => {
+      const deprecateIx = await exchange.deprecateCollateral(collateralAddress, 100)
+      await signAndSend(new Transaction().add(deprecateIx), [EXCHANGE_ADMIN], connection)
+
+      const ix = await exchange.removeCollateral(collateralAddress)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.COLLATERAL_NOT_REMOVABLE
+      )
+      const assetsListData = await exchange.getAssetsList(assetsList)
+      assert.ok(assetsListData.collaterals[1].collateralAddress.equals(collateralAddress))
+    })
+  })
 })
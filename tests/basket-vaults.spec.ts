@@ -0,0 +1,480 @@
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Token, TOKEN_PROGRAM_ID } from '@solana/spl-token'
+import { Account, Keypair, PublicKey, Transaction } from '@solana/web3.js'
+import { assert } from 'chai'
+import { BN, Exchange, Network, signAndSend } from '@synthetify/sdk'
+
+import {
+  createAssetsList,
+  createToken,
+  EXCHANGE_ADMIN,
+  tou64,
+  SYNTHETIFY_EXCHANGE_SEED,
+  createAccountWithCollateralAndMaxMintUsd,
+  assertThrowsAsync,
+  createCollateralToken,
+  U64_MAX,
+  eqDecimals
+} from './utils'
+import { createPriceFeed, setFeedPrice } from './oracleUtils'
+import {
+  INTEREST_RATE_DECIMALS,
+  percentToDecimal,
+  SNY_DECIMALS,
+  toDecimal,
+  toScale
+} from '@synthetify/sdk/lib/utils'
+import { ERRORS_EXCHANGE } from '@synthetify/sdk/src/utils'
+import { Collateral, Synthetic } from '@synthetify/sdk/lib/exchange'
+
+describe('basket vaults', () => {
+  const provider = anchor.Provider.local()
+  const connection = provider.connection
+  const exchangeProgram = anchor.workspace.Exchange as Program
+  let exchange: Exchange
+
+  const oracleProgram = anchor.workspace.Pyth as Program
+
+  //@ts-ignore
+  const wallet = provider.wallet.payer as Account
+
+  let snyToken: Token
+  let xusdToken: Token
+  let assetsList: PublicKey
+  let snyTokenFeed: PublicKey
+  let exchangeAuthority: PublicKey
+  let snyReserve: PublicKey
+  let stakingFundAccount: PublicKey
+  let snyLiquidationFund: PublicKey
+  let nonce: number
+  let CollateralTokenMinter: Account = wallet
+  let ethToken: Token
+  let ethPriceFeed: PublicKey
+  let ethVaultReserve: PublicKey
+  let usdcToken: Token
+  let usdcVaultReserve: PublicKey
+  let xusd: Synthetic
+  let eth: Collateral
+  let usdc: Collateral
+  let userXusdAccount: PublicKey
+
+  const accountOwner = Keypair.generate()
+  let liquidator: Account
+  let liquidatorXusdAccount: PublicKey
+
+  before(async () => {
+    await connection.requestAirdrop(accountOwner.publicKey, 10e9)
+    await connection.requestAirdrop(EXCHANGE_ADMIN.publicKey, 10e9)
+
+    const [_mintAuthority, _nonce] = await anchor.web3.PublicKey.findProgramAddress(
+      [SYNTHETIFY_EXCHANGE_SEED],
+      exchangeProgram.programId
+    )
+    nonce = _nonce
+
+    exchangeAuthority = _mintAuthority
+    snyTokenFeed = await createPriceFeed({
+      oracleProgram,
+      initPrice: 2,
+      expo: -6
+    })
+
+    snyToken = await createToken({
+      connection,
+      payer: wallet,
+      mintAuthority: CollateralTokenMinter.publicKey
+    })
+
+    snyReserve = await snyToken.createAccount(exchangeAuthority)
+    snyLiquidationFund = await snyToken.createAccount(exchangeAuthority)
+    stakingFundAccount = await snyToken.createAccount(exchangeAuthority)
+
+    // @ts-expect-error
+    exchange = new Exchange(
+      connection,
+      Network.LOCAL,
+      provider.wallet,
+      exchangeAuthority,
+      exchangeProgram.programId
+    )
+
+    await exchange.init({
+      admin: EXCHANGE_ADMIN.publicKey,
+      nonce,
+      amountPerRound: new BN(100),
+      stakingRoundLength: 300,
+      stakingFundAccount: stakingFundAccount,
+      exchangeAuthority: exchangeAuthority
+    })
+
+    exchange = await Exchange.build(
+      connection,
+      Network.LOCAL,
+      provider.wallet,
+      exchangeAuthority,
+      exchangeProgram.programId
+    )
+
+    const data = await createAssetsList({
+      exchangeAuthority,
+      collateralToken: snyToken,
+      collateralTokenFeed: snyTokenFeed,
+      connection,
+      wallet,
+      exchangeAdmin: EXCHANGE_ADMIN,
+      exchange,
+      snyReserve,
+      snyLiquidationFund
+    })
+    assetsList = data.assetsList
+    xusdToken = data.usdToken
+
+    await exchange.setAssetsList({ exchangeAdmin: EXCHANGE_ADMIN, assetsList })
+    await exchange.getState()
+
+    // create ETH and USDC collateral tokens
+    const ethData = await createCollateralToken({
+      collateralRatio: 50,
+      connection,
+      decimals: 6,
+      exchange,
+      exchangeAuthority,
+      oracleProgram,
+      price: 2000,
+      wallet
+    })
+    ethPriceFeed = ethData.feed
+    ethToken = ethData.token
+    ethVaultReserve = await ethToken.createAccount(exchangeAuthority)
+
+    const usdcData = await createCollateralToken({
+      collateralRatio: 50,
+      connection,
+      decimals: 6,
+      exchange,
+      exchangeAuthority,
+      oracleProgram,
+      price: 1,
+      wallet
+    })
+    usdcToken = usdcData.token
+    usdcVaultReserve = await usdcToken.createAccount(exchangeAuthority)
+
+    const assetsListData = await exchange.getAssetsList(assetsList)
+    xusd = assetsListData.synthetics[0]
+    eth = assetsListData.collaterals.find((c) => c.collateralAddress.equals(ethToken.publicKey))
+    usdc = assetsListData.collaterals.find((c) => c.collateralAddress.equals(usdcToken.publicKey))
+
+    const liquidatorData = await createAccountWithCollateralAndMaxMintUsd({
+      usdToken: xusdToken,
+      collateralToken: snyToken,
+      exchangeAuthority,
+      exchange,
+      reserveAddress: snyReserve,
+      collateralTokenMintAuthority: CollateralTokenMinter.publicKey,
+      amount: new BN(100000 * 10 ** SNY_DECIMALS) // give enough for liquidations
+    })
+
+    liquidator = liquidatorData.accountOwner
+    liquidatorXusdAccount = liquidatorData.usdTokenAccount
+    await connection.requestAirdrop(liquidator.publicKey, 10e9)
+  })
+  it('should create vault with eth as primary collateral', async () => {
+    const { ix } = await exchange.createVaultInstruction({
+      collateralReserve: ethVaultReserve,
+      collateral: eth.collateralAddress,
+      synthetic: xusd.assetAddress,
+      // zero interest for sake of tests
+      debtInterestRate: toScale(percentToDecimal(0), INTEREST_RATE_DECIMALS),
+      collateralRatio: percentToDecimal(50),
+      maxBorrow: { val: new BN(1e14), scale: xusd.maxSupply.scale },
+      liquidationPenaltyExchange: percentToDecimal(5),
+      liquidationPenaltyLiquidator: percentToDecimal(5),
+      liquidationThreshold: percentToDecimal(60),
+      liquidationRatio: percentToDecimal(50)
+    })
+    await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+
+    const vault = await exchange.getVaultForPair(xusd.assetAddress, eth.collateralAddress)
+    assert.ok(vault.synthetic.equals(xusd.assetAddress))
+    assert.ok(vault.collateral.equals(eth.collateralAddress))
+    assert.ok(vault.basketHead === 0)
+    assert.ok(vault.basket.length === 0)
+    assert.ok(eqDecimals(vault.mintAmount, toDecimal(new BN(0), xusd.maxSupply.scale)))
+  })
+  it('should add collateral with own weights to basket', async () => {
+    const addUsdcIx = await exchange.addVaultCollateralInstruction({
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: usdc.collateralAddress,
+      collateralReserve: usdcVaultReserve,
+      collateralRatio: percentToDecimal(80),
+      liquidationThreshold: percentToDecimal(90)
+    })
+    await signAndSend(new Transaction().add(addUsdcIx), [EXCHANGE_ADMIN], connection)
+
+    const vault = await exchange.getVaultForPair(xusd.assetAddress, eth.collateralAddress)
+    assert.ok(vault.basketHead === 1)
+    assert.ok(vault.basket[0].collateral.equals(usdc.collateralAddress))
+    assert.ok(vault.basket[0].collateralReserve.equals(usdcVaultReserve))
+    assert.ok(eqDecimals(vault.basket[0].collateralRatio, percentToDecimal(80)))
+    assert.ok(eqDecimals(vault.basket[0].liquidationThreshold, percentToDecimal(90)))
+    assert.ok(vault.basket[0].collateralAmount.val.eqn(0))
+
+    // same collateral cannot be added twice
+    await assertThrowsAsync(
+      signAndSend(new Transaction().add(addUsdcIx), [EXCHANGE_ADMIN], connection),
+      ERRORS_EXCHANGE.ALREADY_COLLATERAL
+    )
+    // primary collateral is already part of vault
+    const addEthIx = await exchange.addVaultCollateralInstruction({
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: eth.collateralAddress,
+      collateralReserve: ethVaultReserve,
+      collateralRatio: percentToDecimal(50),
+      liquidationThreshold: percentToDecimal(60)
+    })
+    await assertThrowsAsync(
+      signAndSend(new Transaction().add(addEthIx), [EXCHANGE_ADMIN], connection),
+      ERRORS_EXCHANGE.ALREADY_COLLATERAL
+    )
+  })
+  it('should deposit basket of collaterals', async () => {
+    const { ix: createEntryIx } = await exchange.createVaultEntryInstruction({
+      owner: accountOwner.publicKey,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress
+    })
+    await signAndSend(new Transaction().add(createEntryIx), [accountOwner], connection)
+
+    const userEthAccount = await ethToken.createAccount(accountOwner.publicKey)
+    const userUsdcAccount = await usdcToken.createAccount(accountOwner.publicKey)
+    const ethAmount = new BN(1e6) // 1 ETH
+    const usdcAmount = new BN(1000 * 1e6) // 1000 USDC
+    await ethToken.mintTo(userEthAccount, wallet, [], tou64(ethAmount))
+    await usdcToken.mintTo(userUsdcAccount, wallet, [], tou64(usdcAmount))
+
+    const approveEthIx = Token.createApproveInstruction(
+      TOKEN_PROGRAM_ID,
+      userEthAccount,
+      exchangeAuthority,
+      accountOwner.publicKey,
+      [],
+      tou64(ethAmount)
+    )
+    const approveUsdcIx = Token.createApproveInstruction(
+      TOKEN_PROGRAM_ID,
+      userUsdcAccount,
+      exchangeAuthority,
+      accountOwner.publicKey,
+      [],
+      tou64(usdcAmount)
+    )
+    const depositEthIx = await exchange.vaultDepositInstruction({
+      owner: accountOwner.publicKey,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      reserveAddress: ethVaultReserve,
+      userCollateralAccount: userEthAccount,
+      amount: ethAmount
+    })
+    // basket collateral goes through same instruction with its own reserve
+    const depositUsdcIx = await exchange.vaultDepositInstruction({
+      owner: accountOwner.publicKey,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      reserveAddress: usdcVaultReserve,
+      userCollateralAccount: userUsdcAccount,
+      amount: usdcAmount
+    })
+    await signAndSend(
+      new Transaction().add(approveEthIx).add(approveUsdcIx).add(depositEthIx).add(depositUsdcIx),
+      [accountOwner],
+      connection
+    )
+
+    const vault = await exchange.getVaultForPair(xusd.assetAddress, eth.collateralAddress)
+    const vaultEntry = await exchange.getVaultEntryForOwner(
+      xusd.assetAddress,
+      eth.collateralAddress,
+      accountOwner.publicKey
+    )
+    assert.ok(vault.collateralAmount.val.eq(ethAmount))
+    assert.ok(vault.basket[0].collateralAmount.val.eq(usdcAmount))
+    assert.ok(vaultEntry.collateralAmount.val.eq(ethAmount))
+    assert.ok(vaultEntry.basketAmounts[0].val.eq(usdcAmount))
+    assert.ok((await ethToken.getAccountInfo(ethVaultReserve)).amount.eq(ethAmount))
+    assert.ok((await usdcToken.getAccountInfo(usdcVaultReserve)).amount.eq(usdcAmount))
+  })
+  it('should borrow against sum of weighted collaterals', async () => {
+    userXusdAccount = await xusdToken.createAccount(accountOwner.publicKey)
+    const updatePricesIx = await exchange.updatePricesInstruction(assetsList)
+    // 2000 * 1 ETH * 50% + 1 * 1000 USDC * 80% = 1800 xUSD
+    const borrowLimit = new BN(1800 * 1e6)
+
+    const overLimitIx = await exchange.borrowVaultInstruction({
+      owner: accountOwner.publicKey,
+      to: userXusdAccount,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      amount: borrowLimit.addn(1)
+    })
+    await assertThrowsAsync(
+      signAndSend(
+        new Transaction().add(updatePricesIx).add(overLimitIx),
+        [accountOwner],
+        connection
+      ),
+      ERRORS_EXCHANGE.USER_BORROW_LIMIT
+    )
+
+    const borrowIx = await exchange.borrowVaultInstruction({
+      owner: accountOwner.publicKey,
+      to: userXusdAccount,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      amount: borrowLimit
+    })
+    await signAndSend(
+      new Transaction().add(updatePricesIx).add(borrowIx),
+      [accountOwner],
+      connection
+    )
+
+    const vault = await exchange.getVaultForPair(xusd.assetAddress, eth.collateralAddress)
+    const vaultEntry = await exchange.getVaultEntryForOwner(
+      xusd.assetAddress,
+      eth.collateralAddress,
+      accountOwner.publicKey
+    )
+    assert.ok(vault.mintAmount.val.eq(borrowLimit))
+    assert.ok(vaultEntry.syntheticAmount.val.eq(borrowLimit))
+    assert.ok((await xusdToken.getAccountInfo(userXusdAccount)).amount.eq(borrowLimit))
+  })
+  it('withdraw should fail when basket is fully used', async () => {
+    const userUsdcAccount = await usdcToken.createAccount(accountOwner.publicKey)
+    const updatePricesIx = await exchange.updatePricesInstruction(assetsList)
+    const withdrawIx = await exchange.withdrawVaultInstruction({
+      amount: new BN(1),
+      owner: accountOwner.publicKey,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: usdc.collateralAddress,
+      userCollateralAccount: userUsdcAccount
+    })
+    await assertThrowsAsync(
+      signAndSend(
+        new Transaction().add(updatePricesIx).add(withdrawIx),
+        [accountOwner],
+        connection
+      ),
+      ERRORS_EXCHANGE.VAULT_WITHDRAW_LIMIT
+    )
+  })
+  it('liquidator should seize picked collateral', async () => {
+    const updatePricesIx = await exchange.updatePricesInstruction(assetsList)
+    const liquidatorUsdcAccount = await usdcToken.createAccount(liquidator.publicKey)
+    const approveIx = Token.createApproveInstruction(
+      TOKEN_PROGRAM_ID,
+      liquidatorXusdAccount,
+      exchangeAuthority,
+      liquidator.publicKey,
+      [],
+      tou64(U64_MAX)
+    )
+    const liquidateIx = await exchange.liquidateVaultInstruction({
+      amount: U64_MAX,
+      owner: accountOwner.publicKey,
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: usdc.collateralAddress,
+      liquidator: liquidator.publicKey,
+      liquidatorCollateralAccount: liquidatorUsdcAccount,
+      liquidatorSyntheticAccount: liquidatorXusdAccount
+    })
+    // 2000 * 1 ETH * 60% + 1 * 1000 USDC * 90% = 2100 > 1800 xUSD debt
+    await assertThrowsAsync(
+      signAndSend(
+        new Transaction().add(approveIx).add(updatePricesIx).add(liquidateIx),
+        [liquidator],
+        connection
+      ),
+      ERRORS_EXCHANGE.INVALID_LIQUIDATION
+    )
+
+    // Change price of ETH from 2000 -> 1000
+    // 1000 * 1 ETH * 60% + 1 * 1000 USDC * 90% = 1500 < 1800 xUSD debt
+    await setFeedPrice(oracleProgram, 1000, ethPriceFeed)
+    const liquidatorXusdBefore = await xusdToken.getAccountInfo(liquidatorXusdAccount)
+    const liquidationFundBefore = await usdcToken.getAccountInfo(usdc.liquidationFund)
+
+    await signAndSend(
+      new Transaction().add(approveIx).add(updatePricesIx).add(liquidateIx),
+      [liquidator],
+      connection
+    )
+
+    // 900 xUSD repaid (50% of debt), 990 USDC seized with 10% penalty
+    const liquidatorXusdAfter = await xusdToken.getAccountInfo(liquidatorXusdAccount)
+    assert.ok(liquidatorXusdAfter.amount.eq(liquidatorXusdBefore.amount.sub(new BN(900 * 1e6))))
+    const liquidatorUsdcAfter = await usdcToken.getAccountInfo(liquidatorUsdcAccount)
+    assert.ok(liquidatorUsdcAfter.amount.eq(new BN(945 * 1e6)))
+    const liquidationFundAfter = await usdcToken.getAccountInfo(usdc.liquidationFund)
+    assert.ok(liquidationFundAfter.amount.eq(liquidationFundBefore.amount.add(new BN(45 * 1e6))))
+
+    // ETH stays untouched
+    const vault = await exchange.getVaultForPair(xusd.assetAddress, eth.collateralAddress)
+    const vaultEntry = await exchange.getVaultEntryForOwner(
+      xusd.assetAddress,
+      eth.collateralAddress,
+      accountOwner.publicKey
+    )
+    assert.ok(vaultEntry.collateralAmount.val.eq(new BN(1e6)))
+    assert.ok(vaultEntry.basketAmounts[0].val.eq(new BN(10 * 1e6)))
+    assert.ok(vault.basket[0].collateralAmount.val.eq(new BN(10 * 1e6)))
+    assert.ok(vaultEntry.syntheticAmount.val.eq(new BN(900 * 1e6)))
+    assert.ok(vault.mintAmount.val.eq(new BN(900 * 1e6)))
+  })
+  it('should set weights of basket collateral', async () => {
+    const setUsdcIx = await exchange.setVaultBasketCollateralInstruction({
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: usdc.collateralAddress,
+      collateralRatio: percentToDecimal(70),
+      liquidationThreshold: percentToDecimal(85)
+    })
+    await signAndSend(new Transaction().add(setUsdcIx), [EXCHANGE_ADMIN], connection)
+
+    const vault = await exchange.getVaultForPair(xusd.assetAddress, eth.collateralAddress)
+    assert.ok(eqDecimals(vault.basket[0].collateralRatio, percentToDecimal(70)))
+    assert.ok(eqDecimals(vault.basket[0].liquidationThreshold, percentToDecimal(85)))
+    assert.ok(vault.basket[0].collateralAmount.val.eq(new BN(10 * 1e6)))
+
+    // collateral ratio cannot exceed liquidation threshold
+    const invalidIx = await exchange.setVaultBasketCollateralInstruction({
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: usdc.collateralAddress,
+      collateralRatio: percentToDecimal(90),
+      liquidationThreshold: percentToDecimal(85)
+    })
+    await assertThrowsAsync(
+      signAndSend(new Transaction().add(invalidIx), [EXCHANGE_ADMIN], connection),
+      ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+    )
+    // primary collateral is set through vault setters
+    const primaryIx = await exchange.setVaultBasketCollateralInstruction({
+      synthetic: xusd.assetAddress,
+      collateral: eth.collateralAddress,
+      basketCollateral: eth.collateralAddress,
+      collateralRatio: percentToDecimal(50),
+      liquidationThreshold: percentToDecimal(60)
+    })
+    await assertThrowsAsync(
+      signAndSend(new Transaction().add(primaryIx), [EXCHANGE_ADMIN], connection),
+      ERRORS_EXCHANGE.INVALID_ACCOUNT
+    )
+  })
+})
@@ -17,7 +17,13 @@ import {
 } from './utils'
 import { createPriceFeed, getFeedData, setFeedPrice, setFeedTrading } from './oracleUtils'
 import { ERRORS, INTEREST_RATE_DECIMALS, toScale } from '@synthetify/sdk/src/utils'
-import { Asset, Collateral, PriceStatus, Synthetic } from '@synthetify/sdk/lib/exchange'
+import {
+  Asset,
+  Collateral,
+  PriceSource,
+  PriceStatus,
+  Synthetic
+} from '@synthetify/sdk/lib/exchange'
 import {
   ERRORS_EXCHANGE,
   percentToDecimal,
@@ -388,6 +394,141 @@ describe('admin', () => {
       assert.ok(state.maxDelay === newMaxDelay)
     })
   })
+  describe('#setLiquidationBounty()', async () => {
+    it('Fail without admin signature', async () => {
+      const bounty = toDecimal(new BN(10).pow(new BN(SNY_DECIMALS)), SNY_DECIMALS)
+      const ix = await exchange.setLiquidationBounty({ bounty, cooldown: 1000 })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [wallet], connection),
+        ERRORS.SIGNATURE
+      )
+    })
+    it('change value', async () => {
+      const bounty = toDecimal(new BN(10).pow(new BN(SNY_DECIMALS)), SNY_DECIMALS)
+      const ix = await exchange.setLiquidationBounty({ bounty, cooldown: 1000 })
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      const state = await exchange.getState()
+      assert.ok(eqDecimals(state.liquidationBounty, bounty))
+      assert.ok(state.liquidationBountyCooldown === 1000)
+    })
+    it('should fail because of bounty above cap', async () => {
+      const bounty = toDecimal(new BN(101).mul(new BN(10).pow(new BN(SNY_DECIMALS))), SNY_DECIMALS)
+      const ix = await exchange.setLiquidationBounty({ bounty, cooldown: 1000 })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+      )
+    })
+    it('should fail because of zero cooldown', async () => {
+      const bounty = toDecimal(new BN(10).pow(new BN(SNY_DECIMALS)), SNY_DECIMALS)
+      const ix = await exchange.setLiquidationBounty({ bounty, cooldown: 0 })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+      )
+      const state = await exchange.getState()
+      assert.ok(state.liquidationBountyCooldown === 1000)
+    })
+  })
+  describe('#setInsuranceWithdrawalDelay()', async () => {
+    it('Fail without admin signature', async () => {
+      const newDelay = 1000
+      const ix = await exchange.setInsuranceWithdrawalDelay(newDelay)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [wallet], connection),
+        ERRORS.SIGNATURE
+      )
+      const state = await exchange.getState()
+      assert.ok(state.insuranceWithdrawalDelay !== newDelay)
+    })
+    it('change value', async () => {
+      const newDelay = 1000
+      const ix = await exchange.setInsuranceWithdrawalDelay(newDelay)
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      const state = await exchange.getState()
+      assert.ok(state.insuranceWithdrawalDelay === newDelay)
+    })
+  })
+  describe('#withdrawInsuranceFund()', async () => {
+    it('Fail to request more than excess above target', async () => {
+      const ix = await exchange.requestInsuranceFundWithdrawalInstruction(new BN(1))
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.INSUFFICIENT_AMOUNT_ADMIN_WITHDRAW
+      )
+    })
+    it('Fail to withdraw before timelock ends', async () => {
+      const requestIx = await exchange.requestInsuranceFundWithdrawalInstruction(new BN(0))
+      await signAndSend(new Transaction().add(requestIx), [EXCHANGE_ADMIN], connection)
+      const state = await exchange.getState()
+      assert.ok(state.insuranceWithdrawalSlot.gtn(0))
+
+      const to = await usdToken.createAccount(EXCHANGE_ADMIN.publicKey)
+      const ix = await exchange.withdrawInsuranceFundInstruction(to)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.INSURANCE_WITHDRAWAL_LOCKED
+      )
+    })
+  })
+  describe('#setLiquidationPriceSource()', async () => {
+    it('Fail without admin signature', async () => {
+      const ix = await exchange.setLiquidationPriceSourceInstruction(PriceSource.Twap)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [wallet], connection),
+        ERRORS.SIGNATURE
+      )
+      const state = await exchange.getState()
+      assert.ok(state.liquidationPriceSource === PriceSource.Spot)
+    })
+    it('change value', async () => {
+      const ix = await exchange.setLiquidationPriceSourceInstruction(PriceSource.Conservative)
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      const state = await exchange.getState()
+      assert.ok(state.liquidationPriceSource === PriceSource.Conservative)
+    })
+    it('should fail because of unknown price source', async () => {
+      const ix = await exchange.setLiquidationPriceSourceInstruction(3)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+      )
+      const state = await exchange.getState()
+      assert.ok(state.liquidationPriceSource === PriceSource.Conservative)
+    })
+  })
+  describe('#migrateState()', async () => {
+    it('Fail without admin signature', async () => {
+      const ix = await exchange.migrateStateInstruction()
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [wallet], connection),
+        ERRORS.SIGNATURE
+      )
+    })
+    it('should not change migrated state', async () => {
+      const stateBefore = await exchange.getState()
+      assert.ok(stateBefore.version === 2)
+
+      const ix = await exchange.migrateStateInstruction()
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      const state = await exchange.getState()
+      assert.ok(state.version === 2)
+      assert.ok(state.liquidationPriceSource === PriceSource.Conservative)
+      assert.ok(eqDecimals(state.insuranceFund, stateBefore.insuranceFund))
+    })
+  })
+  describe('#migrateAssetsList()', async () => {
+    it('Fail without admin signature', async () => {
+      await assertThrowsAsync(exchange.migrateAssetsList(wallet))
+    })
+    it('Fail on assets list of current layout', async () => {
+      const assetsListBefore = (await exchange.getState()).assetsList
+      await assertThrowsAsync(exchange.migrateAssetsList(EXCHANGE_ADMIN))
+
+      const state = await exchange.getState()
+      assert.ok(state.assetsList.equals(assetsListBefore))
+    })
+  })
   describe('#setHalted()', async () => {
     it('Fail without admin signature', async () => {
       const halted = true
@@ -1,7 +1,7 @@
 import * as anchor from '@project-serum/anchor'
 import { Program } from '@project-serum/anchor'
 import { Token } from '@solana/spl-token'
-import { Account, PublicKey, Transaction } from '@solana/web3.js'
+import { Account, Keypair, PublicKey, Transaction, TransactionInstruction } from '@solana/web3.js'
 import { assert } from 'chai'
 import { BN, Exchange, Network, signAndSend } from '@synthetify/sdk'
 
@@ -150,9 +150,55 @@ describe('exchange', () => {
     assert.ok(userExchangeAccount.owner.equals(accountOwner))
     // Initial values
     assert.ok(userExchangeAccount.debtShares.eq(new BN(0)))
-    assert.ok(userExchangeAccount.version === 0)
+    assert.ok(userExchangeAccount.version === 1)
+    assert.ok(userExchangeAccount.id === 0)
     assert.ok(userExchangeAccount.collaterals.length === 0)
   })
+  it('Sub-account Creation', async () => {
+    const accountOwner = new Account().publicKey
+    const firstExchangeAccount = await exchange.createExchangeAccount(accountOwner)
+    const secondExchangeAccount = await exchange.createExchangeAccount(accountOwner, 1)
+
+    // Sub-accounts are separate accounts of same owner
+    assert.ok(!firstExchangeAccount.equals(secondExchangeAccount))
+    const secondExchangeAccountAddress = await exchange.getExchangeAccountAddress(accountOwner, 1)
+    assert.ok(secondExchangeAccount.equals(secondExchangeAccountAddress))
+    const secondExchangeAccountData = await exchange.getExchangeAccount(secondExchangeAccount)
+    assert.ok(secondExchangeAccountData.owner.equals(accountOwner))
+    assert.ok(secondExchangeAccountData.id === 1)
+    assert.ok(secondExchangeAccountData.debtShares.eq(new BN(0)))
+
+    // Same sub-account cannot be created twice
+    await assertThrowsAsync(exchange.createExchangeAccount(accountOwner, 1))
+  })
+  it('Migration requires legacy account', async () => {
+    const accountOwner = Keypair.generate()
+    const legacy = await exchange.getLegacyExchangeAccountAddress(accountOwner.publicKey)
+    assert.ok((await connection.getAccountInfo(legacy.account)) === null)
+
+    // Nothing to migrate and no account is created
+    await assertThrowsAsync(exchange.migrateExchangeAccount(accountOwner))
+    const exchangeAccount = await exchange.getExchangeAccountAddress(accountOwner.publicKey)
+    assert.ok((await connection.getAccountInfo(exchangeAccount)) === null)
+  })
+  it('Migration can be paid by anyone', async () => {
+    const accountOwner = Keypair.generate()
+
+    // Still nothing to migrate for owner without legacy account
+    await assertThrowsAsync(exchange.migrateExchangeAccount(wallet, accountOwner.publicKey))
+    const exchangeAccount = await exchange.getExchangeAccountAddress(accountOwner.publicKey)
+    assert.ok((await connection.getAccountInfo(exchangeAccount)) === null)
+  })
+  it('Migration into chosen sub-account', async () => {
+    const accountOwner = Keypair.generate()
+
+    // Legacy account is still required when migrating into sub-account other than 0
+    await assertThrowsAsync(
+      exchange.migrateExchangeAccount(accountOwner, accountOwner.publicKey, 2)
+    )
+    const exchangeAccount = await exchange.getExchangeAccountAddress(accountOwner.publicKey, 2)
+    assert.ok((await connection.getAccountInfo(exchangeAccount)) === null)
+  })
   describe('#deposit()', async () => {
     it('Deposit collateral 1st', async () => {
       const accountOwner = new Account()
@@ -254,6 +300,76 @@ describe('exchange', () => {
           .eq(amount)
       )
     })
+    it('Deposit to exchange account of other user', async () => {
+      const accountOwner = new Account()
+      const benefactor = new Account()
+      const exchangeAccount = await exchange.createExchangeAccount(accountOwner.publicKey)
+
+      const benefactorCollateralTokenAccount = await collateralToken.createAccount(
+        benefactor.publicKey
+      )
+      const amount = new anchor.BN(10 * 1e6) // Mint 10 SNY
+      await collateralToken.mintTo(benefactorCollateralTokenAccount, wallet, [], tou64(amount))
+
+      // Benefactor pays collateral for account of other owner
+      const depositIx = await exchange.depositInstruction({
+        amount,
+        exchangeAccount,
+        userCollateralAccount: benefactorCollateralTokenAccount,
+        owner: benefactor.publicKey,
+        reserveAddress: snyReserve
+      })
+      const approveIx = Token.createApproveInstruction(
+        collateralToken.programId,
+        benefactorCollateralTokenAccount,
+        exchangeAuthority,
+        benefactor.publicKey,
+        [],
+        tou64(amount)
+      )
+      // Only owner or delegate can open new collateral entry
+      await assertThrowsAsync(
+        signAndSend(
+          new Transaction().add(approveIx).add(depositIx),
+          [wallet, benefactor],
+          connection
+        ),
+        ERRORS_EXCHANGE.UNAUTHORIZED
+      )
+
+      // Owner opens entry
+      const ownerCollateralTokenAccount = await collateralToken.createAccount(
+        accountOwner.publicKey
+      )
+      const ownerAmount = new anchor.BN(1e6) // Mint 1 SNY
+      await collateralToken.mintTo(ownerCollateralTokenAccount, wallet, [], tou64(ownerAmount))
+      await exchange.deposit({
+        amount: ownerAmount,
+        exchangeAccount,
+        owner: accountOwner.publicKey,
+        userCollateralAccount: ownerCollateralTokenAccount,
+        reserveAccount: snyReserve,
+        collateralToken,
+        exchangeAuthority,
+        signers: [wallet, accountOwner]
+      })
+
+      // Benefactor tops up existing entry
+      await signAndSend(
+        new Transaction().add(approveIx).add(depositIx),
+        [wallet, benefactor],
+        connection
+      )
+
+      const userExchangeAccountAfter = await exchange.getExchangeAccount(exchangeAccount)
+      assert.ok(userExchangeAccountAfter.owner.equals(accountOwner.publicKey))
+      assert.ok(userExchangeAccountAfter.collaterals.length === 1)
+      assert.ok(userExchangeAccountAfter.collaterals[0].amount.eq(amount.add(ownerAmount)))
+      const benefactorCollateralTokenAccountInfo = await collateralToken.getAccountInfo(
+        benefactorCollateralTokenAccount
+      )
+      assert.ok(benefactorCollateralTokenAccountInfo.amount.eq(new BN(0)))
+    })
     it('Deposit more than allowance', async () => {
       const accountOwner = new Account()
       const exchangeAccount = await exchange.createExchangeAccount(accountOwner.publicKey)
@@ -331,6 +447,110 @@ describe('exchange', () => {
       await signAndSend(new Transaction().add(ixBack), [EXCHANGE_ADMIN], connection)
     })
   })
+  describe('#closeExchangeAccount()', async () => {
+    it('Close empty account', async () => {
+      const accountOwner = new Account()
+      const exchangeAccount = await exchange.createExchangeAccount(accountOwner.publicKey)
+      const accountLamports = (await connection.getAccountInfo(exchangeAccount))!.lamports
+      const ownerLamportsBefore = await connection.getBalance(accountOwner.publicKey)
+
+      const closeIx = await exchange.closeExchangeAccountInstruction(accountOwner.publicKey)
+      await signAndSend(new Transaction().add(closeIx), [wallet, accountOwner], connection)
+
+      // Rent is returned to owner
+      const ownerLamportsAfter = await connection.getBalance(accountOwner.publicKey)
+      assert.ok(ownerLamportsAfter === ownerLamportsBefore + accountLamports)
+      assert.ok((await connection.getAccountInfo(exchangeAccount)) === null)
+    })
+    it('Fail to close account with collateral', async () => {
+      const { accountOwner, exchangeAccount } = await createAccountWithCollateral({
+        reserveAddress: snyReserve,
+        collateralToken,
+        exchangeAuthority,
+        exchange,
+        collateralTokenMintAuthority: CollateralTokenMinter.publicKey,
+        amount: new BN(10 * 1e6)
+      })
+
+      const closeIx = await exchange.closeExchangeAccountInstruction(accountOwner.publicKey)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(closeIx), [wallet, accountOwner], connection),
+        ERRORS_EXCHANGE.ACCOUNT_NOT_EMPTY
+      )
+      assert.ok((await connection.getAccountInfo(exchangeAccount)) !== null)
+    })
+    it('Fail to close account of other owner', async () => {
+      const accountOwner = new Account()
+      const other = new Account()
+      const exchangeAccount = await exchange.createExchangeAccount(accountOwner.publicKey)
+
+      const closeIx = (await exchange.program.instruction.closeExchangeAccount({
+        accounts: {
+          state: exchange.stateAddress,
+          exchangeAccount: exchangeAccount,
+          owner: other.publicKey
+        }
+      })) as TransactionInstruction
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(closeIx), [wallet, other], connection)
+      )
+      assert.ok((await connection.getAccountInfo(exchangeAccount)) !== null)
+    })
+  })
+  it('Position Transfer', async () => {
+    const accountOwner = new Account()
+    const newAccountOwner = new Account()
+    const signature = await connection.requestAirdrop(newAccountOwner.publicKey, 1e9)
+    await connection.confirmTransaction(signature)
+    const exchangeAccount = await exchange.createExchangeAccount(accountOwner.publicKey)
+
+    const userCollateralTokenAccount = await collateralToken.createAccount(accountOwner.publicKey)
+    const amount = new anchor.BN(10 * 1e6)
+    await collateralToken.mintTo(userCollateralTokenAccount, wallet, [], tou64(amount))
+    const depositIx = await exchange.depositInstruction({
+      amount,
+      exchangeAccount,
+      userCollateralAccount: userCollateralTokenAccount,
+      owner: accountOwner.publicKey,
+      reserveAddress: snyReserve
+    })
+    const approveIx = Token.createApproveInstruction(
+      collateralToken.programId,
+      userCollateralTokenAccount,
+      exchangeAuthority,
+      accountOwner.publicKey,
+      [],
+      tou64(amount)
+    )
+    await signAndSend(
+      new Transaction().add(approveIx).add(depositIx),
+      [wallet, accountOwner],
+      connection
+    )
+
+    const { newAccount, ix } = await exchange.transferPositionInstruction({
+      owner: accountOwner.publicKey,
+      newOwner: newAccountOwner.publicKey
+    })
+    // Recipient has to sign
+    await assertThrowsAsync(
+      signAndSend(new Transaction().add(ix), [wallet, accountOwner], connection),
+      ERRORS.SIGNATURE
+    )
+    await signAndSend(
+      new Transaction().add(ix),
+      [wallet, accountOwner, newAccountOwner],
+      connection
+    )
+
+    const newExchangeAccountData = await exchange.getExchangeAccount(newAccount)
+    assert.ok(newExchangeAccountData.owner.equals(newAccountOwner.publicKey))
+    assert.ok(newExchangeAccountData.head === 1)
+    assert.ok(newExchangeAccountData.collaterals[0].amount.eq(amount))
+    assert.ok(newExchangeAccountData.debtShares.eq(new BN(0)))
+    // Previous account is closed
+    assert.ok((await connection.getAccountInfo(exchangeAccount)) === null)
+  })
   describe('#mint()', async () => {
     it('Mint #1', async () => {
       const collateralAmount = new BN(100 * 1e6)
@@ -0,0 +1,287 @@
+import * as anchor from '@project-serum/anchor'
+import { Program } from '@project-serum/anchor'
+import { Token, TOKEN_PROGRAM_ID } from '@solana/spl-token'
+import { Account, PublicKey, Transaction, TransactionInstruction } from '@solana/web3.js'
+import { assert } from 'chai'
+import { BN, calculateLiquidation, Exchange, Network, signAndSend } from '@synthetify/sdk'
+
+import {
+  createAssetsList,
+  createToken,
+  EXCHANGE_ADMIN,
+  tou64,
+  SYNTHETIFY_EXCHANGE_SEED,
+  createAccountWithCollateralAndMaxMintUsd,
+  assertThrowsAsync,
+  newAccountWithLamports,
+  almostEqual
+} from './utils'
+import { createPriceFeed, setFeedPrice } from './oracleUtils'
+import { ERRORS_EXCHANGE } from '@synthetify/sdk/src/utils'
+import { calculateUserMaxDebt, SNY_DECIMALS } from '@synthetify/sdk/lib/utils'
+
+const FLASH_LIQUIDATOR_SEED = Buffer.from('FlashLiquidator')
+
+describe('flash liquidation', () => {
+  const provider = anchor.Provider.local()
+  const connection = provider.connection
+  const exchangeProgram = anchor.workspace.Exchange as Program
+  const flashLiquidatorProgram = anchor.workspace.FlashLiquidator as Program
+  let exchange: Exchange
+
+  const oracleProgram = anchor.workspace.Pyth as Program
+
+  // @ts-expect-error
+  const wallet = provider.wallet.payer as Account
+  let collateralToken: Token
+  let usdToken: Token
+  let collateralTokenFeed: PublicKey
+  let assetsList: PublicKey
+  let exchangeAuthority: PublicKey
+  let stakingFundAccount: PublicKey
+  let snyReserve: PublicKey
+  let snyLiquidationFund: PublicKey
+  let CollateralTokenMinter: Account = wallet
+  let nonce: number
+
+  let treasuryAuthority: PublicKey
+  let treasuryNonce: number
+  let treasuryUsdAccount: PublicKey
+  let treasuryCollateralAccount: PublicKey
+
+  // liquidator does not hold any xUSD
+  let liquidator: Account
+  let liquidatorUsdAccount: PublicKey
+  let liquidatorCollateralAccount: PublicKey
+
+  const initialCollateralPrice = 2
+  before(async () => {
+    const [_mintAuthority, _nonce] = await anchor.web3.PublicKey.findProgramAddress(
+      [SYNTHETIFY_EXCHANGE_SEED],
+      exchangeProgram.programId
+    )
+    nonce = _nonce
+    exchangeAuthority = _mintAuthority
+    collateralTokenFeed = await createPriceFeed({
+      oracleProgram,
+      initPrice: initialCollateralPrice,
+      expo: -6
+    })
+    collateralToken = await createToken({
+      connection,
+      payer: wallet,
+      mintAuthority: CollateralTokenMinter.publicKey
+    })
+    snyReserve = await collateralToken.createAccount(exchangeAuthority)
+    snyLiquidationFund = await collateralToken.createAccount(exchangeAuthority)
+    stakingFundAccount = await collateralToken.createAccount(exchangeAuthority)
+
+    // @ts-expect-error
+    exchange = new Exchange(
+      connection,
+      Network.LOCAL,
+      provider.wallet,
+      exchangeAuthority,
+      exchangeProgram.programId
+    )
+
+    await exchange.init({
+      admin: EXCHANGE_ADMIN.publicKey,
+      nonce,
+      amountPerRound: new BN(100),
+      stakingRoundLength: 300,
+      stakingFundAccount: stakingFundAccount,
+      exchangeAuthority: exchangeAuthority
+    })
+
+    exchange = await Exchange.build(
+      connection,
+      Network.LOCAL,
+      provider.wallet,
+      exchangeAuthority,
+      exchangeProgram.programId
+    )
+
+    const data = await createAssetsList({
+      exchangeAuthority,
+      collateralToken,
+      collateralTokenFeed,
+      connection,
+      wallet,
+      exchangeAdmin: EXCHANGE_ADMIN,
+      exchange,
+      snyReserve,
+      snyLiquidationFund
+    })
+    assetsList = data.assetsList
+    usdToken = data.usdToken
+
+    await exchange.setAssetsList({ exchangeAdmin: EXCHANGE_ADMIN, assetsList })
+    await exchange.getState()
+    await connection.requestAirdrop(EXCHANGE_ADMIN.publicKey, 1e10)
+
+    // treasury of flash liquidator is funded with xUSD
+    const [_treasuryAuthority, _treasuryNonce] = await anchor.web3.PublicKey.findProgramAddress(
+      [FLASH_LIQUIDATOR_SEED],
+      flashLiquidatorProgram.programId
+    )
+    treasuryAuthority = _treasuryAuthority
+    treasuryNonce = _treasuryNonce
+    treasuryUsdAccount = await usdToken.createAccount(treasuryAuthority)
+    treasuryCollateralAccount = await collateralToken.createAccount(treasuryAuthority)
+
+    const whale = await createAccountWithCollateralAndMaxMintUsd({
+      usdToken,
+      collateralToken,
+      exchangeAuthority,
+      exchange,
+      reserveAddress: snyReserve,
+      collateralTokenMintAuthority: CollateralTokenMinter.publicKey,
+      amount: new BN(100000 * 10 ** SNY_DECIMALS)
+    })
+    await usdToken.transfer(
+      whale.usdTokenAccount,
+      treasuryUsdAccount,
+      whale.accountOwner,
+      [],
+      tou64(whale.usdMintAmount)
+    )
+
+    liquidator = await newAccountWithLamports(connection)
+    liquidatorUsdAccount = await usdToken.createAccount(liquidator.publicKey)
+    liquidatorCollateralAccount = await collateralToken.createAccount(liquidator.publicKey)
+
+    // change liquidation buffer for sake of tests
+    const ix = await exchange.setLiquidationBufferInstruction(0)
+    await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+  })
+  afterEach(async () => {
+    await setFeedPrice(oracleProgram, initialCollateralPrice, collateralTokenFeed)
+  })
+  const createLiquidatableAccount = async () => {
+    const { exchangeAccount } = await createAccountWithCollateralAndMaxMintUsd({
+      reserveAddress: snyReserve,
+      collateralToken,
+      exchangeAuthority,
+      exchange,
+      usdToken,
+      collateralTokenMintAuthority: CollateralTokenMinter.publicKey,
+      amount: new BN(1000 * 10 ** SNY_DECIMALS)
+    })
+    await setFeedPrice(oracleProgram, initialCollateralPrice / 5, collateralTokenFeed)
+    await exchange.updatePrices(assetsList)
+    await exchange.checkAccount(exchangeAccount)
+
+    const state = await exchange.getState()
+    const assetsListData = await exchange.getAssetsList(assetsList)
+    const exchangeAccountData = await exchange.getExchangeAccount(exchangeAccount)
+    const userDebtBalance = await exchange.getUserDebtBalance(exchangeAccount)
+    const userMaxDebt = calculateUserMaxDebt(exchangeAccountData, assetsListData)
+    const collateral = assetsListData.collaterals[0]
+    const liquidation = calculateLiquidation(
+      userMaxDebt,
+      userDebtBalance,
+      state.penaltyToLiquidator,
+      state.penaltyToExchange,
+      state.liquidationRate,
+      assetsListData.assets[collateral.assetIndex],
+      collateral
+    )
+    return { exchangeAccount, userDebtBalance, collateral, ...liquidation }
+  }
+  const getBalance = async (token: Token, account: PublicKey) => {
+    return (await token.getAccountInfo(account)).amount
+  }
+  const swapCollateralInstruction = async (usdAmount: BN) => {
+    return (await flashLiquidatorProgram.instruction.swapCollateral(treasuryNonce, usdAmount, {
+      accounts: {
+        liquidator: liquidator.publicKey,
+        liquidatorCollateralAccount,
+        liquidatorUsdAccount,
+        treasuryCollateralAccount,
+        treasuryUsdAccount,
+        treasuryAuthority,
+        tokenProgram: TOKEN_PROGRAM_ID
+      }
+    })) as TransactionInstruction
+  }
+  it('should flash liquidate without holding xUSD', async () => {
+    const {
+      exchangeAccount,
+      userDebtBalance,
+      collateral,
+      maxAmount,
+      collateralToExchange,
+      collateralToLiquidator
+    } = await createLiquidatableAccount()
+
+    const liquidationFundBefore = await getBalance(collateralToken, collateral.liquidationFund)
+    const treasuryCollateralBefore = await getBalance(collateralToken, treasuryCollateralAccount)
+    assert.ok((await getBalance(usdToken, liquidatorUsdAccount)).eqn(0))
+
+    const updateIx = await exchange.updatePricesInstruction(assetsList)
+    const flashLiquidateIx = await exchange.flashLiquidateInstruction({
+      exchangeAccount,
+      signer: liquidator.publicKey,
+      liquidationFund: collateral.liquidationFund,
+      liquidatorCollateralAccount,
+      liquidatorUsdAccount,
+      reserveAccount: collateral.reserveAddress,
+      amount: maxAmount,
+      callback: await swapCollateralInstruction(maxAmount)
+    })
+    const tx = new Transaction().add(updateIx).add(flashLiquidateIx)
+    await signAndSend(tx, [liquidator], connection)
+
+    // seized collateral ended up in treasury and borrowed xUSD was burned
+    const treasuryCollateralAfter = await getBalance(collateralToken, treasuryCollateralAccount)
+    assert.ok(treasuryCollateralAfter.sub(treasuryCollateralBefore).eq(collateralToLiquidator))
+    const liquidationFundAfter = await getBalance(collateralToken, collateral.liquidationFund)
+    assert.ok(liquidationFundAfter.sub(liquidationFundBefore).eq(collateralToExchange))
+    assert.ok((await getBalance(usdToken, liquidatorUsdAccount)).eqn(0))
+
+    const userDebtBalanceAfter = await exchange.getUserDebtBalance(exchangeAccount)
+    assert.ok(almostEqual(userDebtBalance.sub(maxAmount), userDebtBalanceAfter))
+  })
+  it('fail if xUSD is not repaid', async () => {
+    const { exchangeAccount, collateral, maxAmount } = await createLiquidatableAccount()
+
+    const updateIx = await exchange.updatePricesInstruction(assetsList)
+    const flashLiquidateIx = await exchange.flashLiquidateInstruction({
+      exchangeAccount,
+      signer: liquidator.publicKey,
+      liquidationFund: collateral.liquidationFund,
+      liquidatorCollateralAccount,
+      liquidatorUsdAccount,
+      reserveAccount: collateral.reserveAddress,
+      amount: maxAmount,
+      callback: await swapCollateralInstruction(maxAmount.subn(1))
+    })
+    const tx = new Transaction().add(updateIx).add(flashLiquidateIx)
+    await assertThrowsAsync(
+      signAndSend(tx, [liquidator], connection),
+      ERRORS_EXCHANGE.FLASH_LIQUIDATION_NOT_REPAID
+    )
+  })  it('fail with wrong liquidation fund', async () => {
+    const { exchangeAccount, collateral, maxAmount } = await createLiquidatableAccount()
+    const wrongLiquidationFund = await collateralToken.createAccount(liquidator.publicKey)
+
+    const updateIx = await exchange.updatePricesInstruction(assetsList)
+    const flashLiquidateIx = await exchange.flashLiquidateInstruction({
+      exchangeAccount,
+      signer: liquidator.publicKey,
+      liquidationFund: wrongLiquidationFund,
+      liquidatorCollateralAccount,
+      liquidatorUsdAccount,
+      reserveAccount: collateral.reserveAddress,
+      amount: maxAmount,
+      callback: await swapCollateralInstruction(maxAmount)
+    })
+    const tx = new Transaction().add(updateIx).add(flashLiquidateIx)
+    await assertThrowsAsync(
+      signAndSend(tx, [liquidator], connection),
+      ERRORS_EXCHANGE.EXCHANGE_LIQUIDATION_ACCOUNT
+    )
+    assert.ok((await getBalance(collateralToken, wrongLiquidationFund)).eqn(0))
+  })
+})
@@ -1,7 +1,7 @@
 import * as anchor from '@project-serum/anchor'
 import { Program } from '@project-serum/anchor'
 import { Token, TOKEN_PROGRAM_ID } from '@solana/spl-token'
-import { Account, PublicKey, Transaction } from '@solana/web3.js'
+import { Account, PublicKey, Transaction, TransactionInstruction } from '@solana/web3.js'
 import { assert } from 'chai'
 import { BN, Exchange, Network, signAndSend } from '@synthetify/sdk'
 
@@ -262,6 +262,13 @@ describe('settlement', () => {
       const debtAfter = calculateDebt(assetsListAfterSettlement)
       assert.ok(almostEqual(debtBefore, debtAfter))
 
+      // Settlement with tokens left in reserve cannot be closed
+      const closeIx = await exchange.closeSettlementInstruction(tokenToSettle.publicKey)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(closeIx), [wallet, EXCHANGE_ADMIN], connection),
+        ERRORS_EXCHANGE.ACCOUNT_NOT_EMPTY
+      )
+
       const approveIx = await Token.createApproveInstruction(
         TOKEN_PROGRAM_ID,
         tokenToSettleAccount,
@@ -293,5 +300,43 @@ describe('settlement', () => {
         )
       )
     })
+    it('Should close settlement', async () => {
+      const [settlementAddress] = await PublicKey.findProgramAddress(
+        [
+          Buffer.from(anchor.utils.bytes.utf8.encode('settlement')),
+          tokenToSettle.publicKey.toBuffer()
+        ],
+        exchangeProgram.programId
+      )
+      // Every settled token was swapped
+      const settlementReserveAmount = (await usdToken.getAccountInfo(settlementReserve)).amount
+      assert.ok(settlementReserveAmount.eqn(0))
+
+      // Only admin can close settlement
+      const settlement = await exchange.getSettlementAccountForSynthetic(tokenToSettle.publicKey)
+      const unauthorizedCloseIx = (await exchangeProgram.instruction.closeSettlement({
+        accounts: {
+          settlement: settlementAddress,
+          state: exchange.stateAddress,
+          admin: wallet.publicKey,
+          tokenToSettle: tokenToSettle.publicKey,
+          settlementReserve: settlement.reserveAddress
+        }
+      })) as TransactionInstruction
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(unauthorizedCloseIx), [wallet], connection),
+        ERRORS_EXCHANGE.UNAUTHORIZED
+      )
+
+      // Rent is returned to admin
+      const settlementLamports = (await connection.getAccountInfo(settlementAddress))!.lamports
+      const adminLamportsBefore = await connection.getBalance(EXCHANGE_ADMIN.publicKey)
+      const closeIx = await exchange.closeSettlementInstruction(tokenToSettle.publicKey)
+      await signAndSend(new Transaction().add(closeIx), [wallet, EXCHANGE_ADMIN], connection)
+
+      const adminLamportsAfter = await connection.getBalance(EXCHANGE_ADMIN.publicKey)
+      assert.ok(adminLamportsAfter === adminLamportsBefore + settlementLamports)
+      assert.ok((await connection.getAccountInfo(settlementAddress)) === null)
+    })
   })
 })
@@ -22,8 +22,10 @@ import { createPriceFeed } from './oracleUtils'
 import {
   calculateDebt,
   ERRORS_EXCHANGE,
+  INTEREST_RATE_DECIMALS,
   percentToDecimal,
-  toDecimal
+  toDecimal,
+  toScale
 } from '@synthetify/sdk/lib/utils'
 
 describe('swap-line', () => {
@@ -471,4 +473,323 @@ describe('swap-line', () => {
       assert.ok(swapLineHaltedFalse.halted === false)
     })
   })
+  describe('Leverage vault', async () => {
+    let syntheticToken: Token
+    let collateralToken: Token
+    let swapLinePubkey: PublicKey
+    let ownerAccount: Account
+    const limit = new BN(10 ** 12)
+
+    before(async () => {
+      syntheticToken = await createToken({
+        connection,
+        payer: wallet,
+        mintAuthority: exchangeAuthority,
+        decimals: 8
+      })
+      const newAssetLimit = new BN(10).pow(new BN(18))
+      const { feed, token } = await createCollateralToken({
+        collateralRatio: 50,
+        connection,
+        decimals: 6,
+        exchange,
+        exchangeAuthority,
+        oracleProgram,
+        price: 2,
+        wallet
+      })
+      collateralToken = token
+      const addNativeSynthetic = await exchange.addSyntheticInstruction({
+        assetAddress: syntheticToken.publicKey,
+        assetsList,
+        maxSupply: newAssetLimit,
+        priceFeed: feed
+      })
+      await signAndSend(new Transaction().add(addNativeSynthetic), [EXCHANGE_ADMIN], connection)
+
+      // swapline with native liquidity
+      const swaplineReserve = await collateralToken.createAccount(exchangeAuthority)
+      const { ix, swaplineAddress } = await exchange.createSwaplineInstruction({
+        collateral: collateralToken.publicKey,
+        collateralReserve: swaplineReserve,
+        synthetic: syntheticToken.publicKey,
+        limit
+      })
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      swapLinePubkey = swaplineAddress
+
+      const liquidityProvider = await newAccountWithLamports(connection)
+      const providerCollateralAccount = await collateralToken.createAccount(
+        liquidityProvider.publicKey
+      )
+      const providerSyntheticAccount = await syntheticToken.createAccount(
+        liquidityProvider.publicKey
+      )
+      const liquidity = 10 ** 9
+      await collateralToken.mintTo(providerCollateralAccount, wallet, [], liquidity)
+      const nativeToSyntheticIx = await exchange.nativeToSynthetic({
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        amount: new BN(liquidity),
+        signer: liquidityProvider.publicKey,
+        userCollateralAccount: providerCollateralAccount,
+        userSyntheticAccount: providerSyntheticAccount
+      })
+      const approveIx = Token.createApproveInstruction(
+        TOKEN_PROGRAM_ID,
+        providerCollateralAccount,
+        exchange.exchangeAuthority,
+        liquidityProvider.publicKey,
+        [],
+        tou64(liquidity)
+      )
+      await signAndSend(
+        new Transaction().add(approveIx).add(nativeToSyntheticIx),
+        [liquidityProvider],
+        connection
+      )
+
+      // vault for the same pair
+      const vaultReserve = await collateralToken.createAccount(exchangeAuthority)
+      const { ix: createVaultIx } = await exchange.createVaultInstruction({
+        collateralReserve: vaultReserve,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        debtInterestRate: toScale(percentToDecimal(1), INTEREST_RATE_DECIMALS),
+        collateralRatio: percentToDecimal(80),
+        maxBorrow: toDecimal(new BN(10).pow(new BN(16)), 8),
+        liquidationPenaltyExchange: percentToDecimal(5),
+        liquidationPenaltyLiquidator: percentToDecimal(5),
+        liquidationThreshold: percentToDecimal(90),
+        liquidationRatio: percentToDecimal(50)
+      })
+      await signAndSend(new Transaction().add(createVaultIx), [EXCHANGE_ADMIN], connection)
+
+      ownerAccount = await newAccountWithLamports(connection)
+      const { ix: createVaultEntryIx } = await exchange.createVaultEntryInstruction({
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey
+      })
+      await signAndSend(new Transaction().add(createVaultEntryIx), [ownerAccount], connection)
+
+      const ownerCollateralAccount = await collateralToken.createAccount(ownerAccount.publicKey)
+      await collateralToken.mintTo(ownerCollateralAccount, wallet, [], 100 * 10 ** 6)
+      await exchange.vaultDeposit({
+        amount: new BN(100 * 10 ** 6),
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        userCollateralAccount: ownerCollateralAccount,
+        reserveAddress: vaultReserve,
+        collateralToken,
+        signers: [ownerAccount]
+      })
+    })
+    it('leverage above collateral ratio should fail', async () => {
+      const ix = await exchange.leverageVaultInstruction({
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        targetRatio: percentToDecimal(90),
+        iterations: 3,
+        minCollateralOut: new BN(0)
+      })
+      const updatePricesIx = await exchange.updatePricesInstruction(exchange.state.assetsList)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(updatePricesIx).add(ix), [ownerAccount], connection),
+        ERRORS_EXCHANGE.PARAMETER_OUT_OF_RANGE
+      )
+    })
+    it('leverage over slippage bound should fail', async () => {
+      const ix = await exchange.leverageVaultInstruction({
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        targetRatio: percentToDecimal(50),
+        iterations: 3,
+        minCollateralOut: new BN(100 * 10 ** 6)
+      })
+      const updatePricesIx = await exchange.updatePricesInstruction(exchange.state.assetsList)
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(updatePricesIx).add(ix), [ownerAccount], connection),
+        ERRORS_EXCHANGE.LEVERAGE_SLIPPAGE
+      )
+    })
+    it('should leverage vault entry', async () => {
+      const vaultBefore = await exchange.getVaultForPair(
+        syntheticToken.publicKey,
+        collateralToken.publicKey
+      )
+      const swaplineBefore = await exchange.getSwapline(swapLinePubkey)
+      const syntheticSupplyBefore = (await syntheticToken.getMintInfo()).supply
+      const ix = await exchange.leverageVaultInstruction({
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        targetRatio: percentToDecimal(50),
+        iterations: 3,
+        minCollateralOut: new BN(80 * 10 ** 6)
+      })
+      const updatePricesIx = await exchange.updatePricesInstruction(exchange.state.assetsList)
+      await signAndSend(new Transaction().add(updatePricesIx).add(ix), [ownerAccount], connection)
+
+      const vaultEntry = await exchange.getVaultEntryForOwner(
+        syntheticToken.publicKey,
+        collateralToken.publicKey,
+        ownerAccount.publicKey
+      )
+      const swaplineAfter = await exchange.getSwapline(swapLinePubkey)
+      const collateralOut = vaultEntry.collateralAmount.val.sub(new BN(100 * 10 ** 6))
+
+      // about 49.5 + 24.5 + 12.13 after 1% swapline fee
+      assert.ok(collateralOut.gt(new BN(80 * 10 ** 6)))
+      assert.ok(vaultEntry.syntheticAmount.val.gt(new BN(0)))
+      // collateral moved from swapline to vault reserve
+      assert.ok(
+        (await collateralToken.getAccountInfo(vaultBefore.collateralReserve)).amount.eq(
+          vaultEntry.collateralAmount.val
+        )
+      )
+      assert.ok(swaplineBefore.balance.val.sub(swaplineAfter.balance.val).eq(collateralOut))
+      // borrowed synthetic is not minted to user
+      assert.ok((await syntheticToken.getMintInfo()).supply.eq(syntheticSupplyBefore))
+    })
+    it('close vault position over slippage bound should fail', async () => {
+      const userCollateralAccount = await collateralToken.createAccount(ownerAccount.publicKey)
+      const ix = await exchange.closeVaultPositionInstruction({
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        userCollateralAccount,
+        maxCollateralIn: new BN(10 * 10 ** 6)
+      })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(ix), [ownerAccount], connection),
+        ERRORS_EXCHANGE.CLOSE_SLIPPAGE
+      )
+    })
+    it('should close leveraged vault position', async () => {
+      const vaultEntry = await exchange.getVaultEntryForOwner(
+        syntheticToken.publicKey,
+        collateralToken.publicKey,
+        ownerAccount.publicKey
+      )
+      const vaultBefore = await exchange.getVaultForPair(
+        syntheticToken.publicKey,
+        collateralToken.publicKey
+      )
+      const swaplineBefore = await exchange.getSwapline(swapLinePubkey)
+      const userCollateralAccount = await collateralToken.createAccount(ownerAccount.publicKey)
+      const { vaultEntryAddress } = await exchange.getVaultEntryAddress(
+        syntheticToken.publicKey,
+        collateralToken.publicKey,
+        ownerAccount.publicKey
+      )
+
+      const ix = await exchange.closeVaultPositionInstruction({
+        owner: ownerAccount.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: syntheticToken.publicKey,
+        userCollateralAccount,
+        maxCollateralIn: vaultEntry.collateralAmount.val
+      })
+      await signAndSend(new Transaction().add(ix), [ownerAccount], connection)
+
+      const vaultAfter = await exchange.getVaultForPair(
+        syntheticToken.publicKey,
+        collateralToken.publicKey
+      )
+      const swaplineAfter = await exchange.getSwapline(swapLinePubkey)
+      const collateralIn = swaplineAfter.balance.val.sub(swaplineBefore.balance.val)
+      const withdrawn = (await collateralToken.getAccountInfo(userCollateralAccount)).amount
+
+      // debt is repaid and rest of collateral returned
+      assert.ok(vaultBefore.mintAmount.val.gt(new BN(0)))
+      assert.ok(almostEqual(vaultAfter.mintAmount.val, new BN(0)))
+      assert.ok(vaultAfter.collateralAmount.val.eq(new BN(0)))
+      assert.ok(withdrawn.add(collateralIn).eq(vaultEntry.collateralAmount.val))
+      // swapped collateral covers debt with fee, synthetic has 2 more decimals
+      assert.ok(collateralIn.gte(vaultEntry.syntheticAmount.val.divn(100)))
+      // vault entry is closed
+      assert.ok((await connection.getAccountInfo(vaultEntryAddress)) === null)
+    })
+    it('should close vault position through swap', async () => {
+      // xUSD vault has no swapline with collateral
+      const vaultReserve = await collateralToken.createAccount(exchangeAuthority)
+      const { ix: createVaultIx } = await exchange.createVaultInstruction({
+        collateralReserve: vaultReserve,
+        collateral: collateralToken.publicKey,
+        synthetic: usdToken.publicKey,
+        debtInterestRate: toScale(percentToDecimal(1), INTEREST_RATE_DECIMALS),
+        collateralRatio: percentToDecimal(80),
+        maxBorrow: toDecimal(new BN(10).pow(new BN(12)), 6),
+        liquidationPenaltyExchange: percentToDecimal(5),
+        liquidationPenaltyLiquidator: percentToDecimal(5),
+        liquidationThreshold: percentToDecimal(90),
+        liquidationRatio: percentToDecimal(50)
+      })
+      await signAndSend(new Transaction().add(createVaultIx), [EXCHANGE_ADMIN], connection)
+
+      const owner = await newAccountWithLamports(connection)
+      const { ix: entryIx, vaultEntryAddress } = await exchange.createVaultEntryInstruction({
+        owner: owner.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: usdToken.publicKey
+      })
+      await signAndSend(new Transaction().add(entryIx), [owner], connection)
+
+      // deposit 100 tokens worth 200 USD and borrow 50 xUSD
+      const depositAmount = new BN(100 * 10 ** 6)
+      const ownerCollateralAccount = await collateralToken.createAccount(owner.publicKey)
+      await collateralToken.mintTo(ownerCollateralAccount, wallet, [], tou64(depositAmount))
+      await exchange.vaultDeposit({
+        amount: depositAmount,
+        owner: owner.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: usdToken.publicKey,
+        userCollateralAccount: ownerCollateralAccount,
+        reserveAddress: vaultReserve,
+        collateralToken,
+        signers: [owner]
+      })
+      const ownerUsdAccount = await usdToken.createAccount(owner.publicKey)
+      await exchange.borrowVault({
+        amount: new BN(50 * 10 ** 6),
+        owner: owner.publicKey,
+        to: ownerUsdAccount,
+        collateral: collateralToken.publicKey,
+        synthetic: usdToken.publicKey,
+        signers: [owner]
+      })
+
+      const swaplineBefore = await exchange.getSwapline(swapLinePubkey)
+      const ix = await exchange.closeVaultPositionInstruction({
+        owner: owner.publicKey,
+        collateral: collateralToken.publicKey,
+        synthetic: usdToken.publicKey,
+        swaplineSynthetic: syntheticToken.publicKey,
+        userCollateralAccount: ownerCollateralAccount,
+        maxCollateralIn: depositAmount
+      })
+      const updatePricesIx = await exchange.updatePricesInstruction(exchange.state.assetsList)
+      await signAndSend(new Transaction().add(updatePricesIx).add(ix), [owner], connection)
+
+      const vaultAfter = await exchange.getVaultForPair(
+        usdToken.publicKey,
+        collateralToken.publicKey
+      )
+      const swaplineAfter = await exchange.getSwapline(swapLinePubkey)
+      const collateralIn = swaplineAfter.balance.val.sub(swaplineBefore.balance.val)
+      const withdrawn = (await collateralToken.getAccountInfo(ownerCollateralAccount)).amount
+
+      // debt is repaid and rest of collateral returned
+      assert.ok(almostEqual(vaultAfter.mintAmount.val, new BN(0)))
+      assert.ok(vaultAfter.collateralAmount.val.eq(new BN(0)))
+      assert.ok(withdrawn.add(collateralIn).eq(depositAmount))
+      // 50 xUSD at price 2 with swap and swapline fees
+      assert.ok(collateralIn.gt(new BN(25 * 10 ** 6)))
+      assert.ok((await connection.getAccountInfo(vaultEntryAddress)) === null)
+    })
+  })
 })
@@ -45,7 +45,7 @@ import {
   XUSD_DECIMALS
 } from '@synthetify/sdk/lib/utils'
 import { ERRORS_EXCHANGE, toEffectiveFee } from '@synthetify/sdk/src/utils'
-import { Collateral, PriceStatus, Synthetic } from '@synthetify/sdk/lib/exchange'
+import { Collateral, PriceSource, PriceStatus, Synthetic } from '@synthetify/sdk/lib/exchange'
 import { Decimal } from '@synthetify/sdk/src/exchange'
 
 describe('vaults liquidation', () => {
@@ -297,6 +297,27 @@ describe('vaults liquidation', () => {
 
     // Change price of ETH from 2000 -> 1750
     await setFeedPrice(oracleProgram, 1750, ethPriceFeed)
+
+    // Twap of ETH stays at 2000 so user is still safe
+    const setTwapSourceIx = await exchange.setVaultLiquidationPriceSourceInstruction(
+      PriceSource.Twap,
+      { collateral: eth.collateralAddress, synthetic: xusd.assetAddress }
+    )
+    await signAndSend(new Transaction().add(setTwapSourceIx), [EXCHANGE_ADMIN], connection)
+    await assertThrowsAsync(
+      signAndSend(
+        new Transaction().add(approveIx).add(updatePricesIx).add(liquidateVaultInstruction),
+        [liquidator],
+        connection
+      ),
+      ERRORS_EXCHANGE.INVALID_LIQUIDATION
+    )
+    // Conservative source values collateral at lower of spot and twap
+    const setConservativeSourceIx = await exchange.setVaultLiquidationPriceSourceInstruction(
+      PriceSource.Conservative,
+      { collateral: eth.collateralAddress, synthetic: xusd.assetAddress }
+    )
+    await signAndSend(new Transaction().add(setConservativeSourceIx), [EXCHANGE_ADMIN], connection)
     // Liquidate
     await signAndSend(
       new Transaction().add(approveIx).add(updatePricesIx).add(liquidateVaultInstruction),
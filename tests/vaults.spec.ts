@@ -31,9 +31,10 @@ import {
   U64_MAX,
   eqDecimals,
   mulByDecimal,
-  almostEqual
+  almostEqual,
+  newAccountWithLamports
 } from './utils'
-import { createPriceFeed, getFeedData, setFeedTrading } from './oracleUtils'
+import { createPriceFeed, getFeedData, setFeedTrading, setTwap } from './oracleUtils'
 import {
   decimalToPercent,
   ERRORS,
@@ -70,6 +71,7 @@ describe('vaults', () => {
   let CollateralTokenMinter: Account = wallet
   let usdcToken: Token
   let usdcVaultReserve: PublicKey
+  let usdcFeed: PublicKey
   let userUsdcTokenAccount: PublicKey
   let userXusdTokenAccount: PublicKey
   let allUserCollateralAmount: BN
@@ -156,6 +158,7 @@ describe('vaults', () => {
       wallet
     })
     usdcToken = token
+    usdcFeed = feed
     usdcVaultReserve = await usdcToken.createAccount(exchangeAuthority)
   })
   describe('#createVault', async () => {
@@ -202,6 +205,18 @@ describe('vaults', () => {
         ERRORS.SIGNATURE
       )
     })
+    it('migration requires legacy vault', async () => {
+      const legacy = await exchange.getLegacyVaultAddress(xusd.assetAddress, usdc.collateralAddress)
+      assert.ok((await connection.getAccountInfo(legacy.vaultAddress)) === null)
+
+      // Nothing to migrate and no vault is created
+      const { ix, vaultAddress } = await exchange.migrateVaultInstruction({
+        synthetic: xusd.assetAddress,
+        collateral: usdc.collateralAddress
+      })
+      await assertThrowsAsync(signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection))
+      assert.ok((await connection.getAccountInfo(vaultAddress)) === null)
+    })
     it('should create usdc/xusd vault', async () => {
       const timestamp = (await connection.getBlockTime(await connection.getSlot())) as number
       await signAndSend(new Transaction().add(createVaultIx), [EXCHANGE_ADMIN], connection)
@@ -227,12 +242,46 @@ describe('vaults', () => {
       assert.ok(eqDecimals(vault.mintAmount, toDecimal(new BN(0), XUSD_DECIMALS)))
       assert.ok(eqDecimals(vault.maxBorrow, maxBorrow))
       assert.ok(almostEqual(vault.lastUpdate, new BN(timestamp), new BN(5)))
+      assert.ok(vault.vaultType === 0)
     })
-    it('create usdc/xusd vault should fail cause there can only be one vault per synthetic/collateral pair', async () => {
+    it('create usdc/xusd vault should fail cause there can only be one vault per synthetic/collateral pair and type', async () => {
       await assertThrowsAsync(
         signAndSend(new Transaction().add(createVaultIx), [EXCHANGE_ADMIN], connection)
       )
     })
+    it('should create usdc/xusd vault of another type', async () => {
+      const vaultType = 1
+      const riskyCollateralRatio = percentToDecimal(90)
+      const riskyVaultReserve = await usdcToken.createAccount(exchangeAuthority)
+      const { ix } = await exchange.createVaultInstruction({
+        collateralReserve: riskyVaultReserve,
+        collateral: usdc.collateralAddress,
+        synthetic: xusd.assetAddress,
+        debtInterestRate,
+        collateralRatio: riskyCollateralRatio,
+        maxBorrow,
+        liquidationPenaltyExchange,
+        liquidationPenaltyLiquidator,
+        liquidationThreshold,
+        liquidationRatio,
+        vaultType
+      })
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      const vault = await exchange.getVaultForPair(
+        xusd.assetAddress,
+        usdc.collateralAddress,
+        vaultType
+      )
+      const { vaultAddress } = await exchange.getVaultAddress(
+        xusd.assetAddress,
+        usdc.collateralAddress
+      )
+
+      assert.ok(vault.vaultType === vaultType)
+      assert.ok(vault.collateralReserve.equals(riskyVaultReserve))
+      assert.ok(eqDecimals(vault.collateralRatio, riskyCollateralRatio))
+      assert.ok(!ix.keys.some((key) => key.pubkey.equals(vaultAddress)))
+    })
   })
   describe('#createVaultEntry', async () => {
     it('should create vault entry on usdc/xusd vault', async () => {
@@ -282,6 +331,104 @@ describe('vaults', () => {
       await assertThrowsAsync(signAndSend(new Transaction().add(ix), [accountOwner], connection))
     })
   })
+  describe('#closeVaultEntry', async () => {
+    const vaultType = 1
+    let xusdAddress: PublicKey
+    let usdcAddress: PublicKey
+    let entryOwner: Account
+
+    before(async () => {
+      const assetsListData = await exchange.getAssetsList(assetsList)
+      xusdAddress = assetsListData.synthetics[0].assetAddress
+      usdcAddress = assetsListData.collaterals[1].collateralAddress
+
+      // entry with collateral on vault of another type
+      entryOwner = await newAccountWithLamports(connection)
+      const { ix } = await exchange.createVaultEntryInstruction({
+        owner: entryOwner.publicKey,
+        collateral: usdcAddress,
+        synthetic: xusdAddress,
+        vaultType
+      })
+      await signAndSend(new Transaction().add(ix), [entryOwner], connection)
+
+      const vault = await exchange.getVaultForPair(xusdAddress, usdcAddress, vaultType)
+      const entryOwnerUsdcTokenAccount = await usdcToken.createAccount(entryOwner.publicKey)
+      const amount = new BN(1e6)
+      await usdcToken.mintTo(entryOwnerUsdcTokenAccount, wallet, [], tou64(amount))
+      await exchange.vaultDeposit({
+        amount,
+        owner: entryOwner.publicKey,
+        collateral: usdcAddress,
+        synthetic: xusdAddress,
+        vaultType,
+        userCollateralAccount: entryOwnerUsdcTokenAccount,
+        reserveAddress: vault.collateralReserve,
+        collateralToken: usdcToken,
+        signers: [entryOwner]
+      })
+    })
+    it('should close empty vault entry', async () => {
+      const owner = await newAccountWithLamports(connection)
+      const { ix, vaultEntryAddress } = await exchange.createVaultEntryInstruction({
+        owner: owner.publicKey,
+        collateral: usdcAddress,
+        synthetic: xusdAddress,
+        vaultType
+      })
+      await signAndSend(new Transaction().add(ix), [owner], connection)
+      const entryLamports = (await connection.getAccountInfo(vaultEntryAddress))!.lamports
+      const ownerLamportsBefore = await connection.getBalance(owner.publicKey)
+
+      const closeIx = await exchange.closeVaultEntryInstruction({
+        owner: owner.publicKey,
+        collateral: usdcAddress,
+        synthetic: xusdAddress,
+        vaultType
+      })
+      await signAndSend(new Transaction().add(closeIx), [wallet, owner], connection)
+
+      // Rent is returned to owner
+      const ownerLamportsAfter = await connection.getBalance(owner.publicKey)
+      assert.ok(ownerLamportsAfter === ownerLamportsBefore + entryLamports)
+      assert.ok((await connection.getAccountInfo(vaultEntryAddress)) === null)
+    })
+    it('close vault entry with collateral should fail', async () => {
+      const closeIx = await exchange.closeVaultEntryInstruction({
+        owner: entryOwner.publicKey,
+        collateral: usdcAddress,
+        synthetic: xusdAddress,
+        vaultType
+      })
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(closeIx), [wallet, entryOwner], connection),
+        ERRORS_EXCHANGE.ACCOUNT_NOT_EMPTY
+      )
+    })
+    it('close vault entry of other user should fail', async () => {
+      const other = await newAccountWithLamports(connection)
+      const { vaultAddress } = await exchange.getVaultAddress(xusdAddress, usdcAddress, vaultType)
+      const { vaultEntryAddress } = await exchange.getVaultEntryAddress(
+        xusdAddress,
+        usdcAddress,
+        entryOwner.publicKey,
+        vaultType
+      )
+      const closeIx = (await exchange.program.instruction.closeVaultEntry({
+        accounts: {
+          vaultEntry: vaultEntryAddress,
+          owner: other.publicKey,
+          vault: vaultAddress,
+          synthetic: xusdAddress,
+          collateral: usdcAddress
+        }
+      })) as TransactionInstruction
+      await assertThrowsAsync(
+        signAndSend(new Transaction().add(closeIx), [wallet, other], connection)
+      )
+      assert.ok((await connection.getAccountInfo(vaultEntryAddress)) !== null)
+    })
+  })
   describe('#depositVault', async () => {
     it('should perform 1st deposit to usdc/xusd vault entry', async () => {
       const assetsListData = await exchange.getAssetsList(assetsList)
@@ -724,6 +871,68 @@ describe('vaults', () => {
       // check synthetic borrowed supply
       assert.ok(repayAmount.eq(xusdBefore.borrowedSupply.val.sub(xusdAfter.borrowedSupply.val)))
     })
+    it('should repay xusd for other user from usdc/xusd vault entry', async () => {
+      const assetsListData = await exchange.getAssetsList(assetsList)
+      const xusdBefore = assetsListData.synthetics[0]
+      const usdc = assetsListData.collaterals[1]
+
+      const vaultEntryBeforeRepay = await exchange.getVaultEntryForOwner(
+        xusdBefore.assetAddress,
+        usdc.collateralAddress,
+        accountOwner.publicKey
+      )
+      const repayAmount = vaultEntryBeforeRepay.syntheticAmount.val.divn(2)
+
+      // keeper gets synthetic from owner and repays part of owner debt
+      const keeper = Keypair.generate()
+      const keeperXusdTokenAccount = await xusdToken.createAccount(keeper.publicKey)
+      await xusdToken.transfer(
+        userXusdTokenAccount,
+        keeperXusdTokenAccount,
+        accountOwner,
+        [],
+        tou64(repayAmount)
+      )
+      const approveIx = Token.createApproveInstruction(
+        TOKEN_PROGRAM_ID,
+        keeperXusdTokenAccount,
+        exchangeAuthority,
+        keeper.publicKey,
+        [],
+        tou64(repayAmount)
+      )
+      const repayIx = await exchange.repayVaultForInstruction({
+        amount: repayAmount,
+        owner: accountOwner.publicKey,
+        signer: keeper.publicKey,
+        collateral: usdc.collateralAddress,
+        synthetic: xusdBefore.assetAddress,
+        signerTokenAccountRepay: keeperXusdTokenAccount
+      })
+      await signAndSend(new Transaction().add(approveIx).add(repayIx), [wallet, keeper], connection)
+
+      const xusdAfter = (await exchange.getAssetsList(assetsList)).synthetics[0]
+      const vaultEntryAfterRepay = await exchange.getVaultEntryForOwner(
+        xusdBefore.assetAddress,
+        usdc.collateralAddress,
+        accountOwner.publicKey
+      )
+      const keeperXusdTokenAccountAfterRepay = await xusdToken.getAccountInfo(
+        keeperXusdTokenAccount
+      )
+
+      // tokens of keeper are burned
+      assert.ok(keeperXusdTokenAccountAfterRepay.amount.eq(new BN(0)))
+      // check vault entry of owner
+      assert.ok(
+        repayAmount.eq(
+          vaultEntryBeforeRepay.syntheticAmount.val.sub(vaultEntryAfterRepay.syntheticAmount.val)
+        )
+      )
+      // check synthetic supply
+      assert.ok(repayAmount.eq(xusdBefore.supply.val.sub(xusdAfter.supply.val)))
+      assert.ok(repayAmount.eq(xusdBefore.borrowedSupply.val.sub(xusdAfter.borrowedSupply.val)))
+    })
     it('repay over limit should repay rest of borrowed synthetic', async () => {
       const assetsListData = await exchange.getAssetsList(assetsList)
       const xusdBefore = assetsListData.synthetics[0]
@@ -769,6 +978,112 @@ describe('vaults', () => {
       assert.ok(userXusdTokenAccountAfter.amount.eq(expectedBorrowedSupply.val))
     })
   })
+  describe('#setVaultOracleSettings', async () => {
+    let xusdAssetAddress: PublicKey
+    let usdcCollateralAddress: PublicKey
+
+    before(async () => {
+      const assetsListData = await exchange.getAssetsList(assetsList)
+      xusdAssetAddress = assetsListData.synthetics[0].assetAddress
+      usdcCollateralAddress = assetsListData.collaterals[1].collateralAddress
+    })
+    after(async () => {
+      const ix = await exchange.setVaultOracleSettingsInstruction(
+        {
+          maxDelay: exchange.state.maxDelay,
+          maxPriceDeviation: percentToDecimal(0),
+          requireTrading: false
+        },
+        { synthetic: xusdAssetAddress, collateral: usdcCollateralAddress }
+      )
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+    })
+    it('borrow should failed when price is not trading', async () => {
+      const ix = await exchange.setVaultOracleSettingsInstruction(
+        {
+          maxDelay: exchange.state.maxDelay,
+          maxPriceDeviation: percentToDecimal(0),
+          requireTrading: true
+        },
+        { synthetic: xusdAssetAddress, collateral: usdcCollateralAddress }
+      )
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      await setFeedTrading(oracleProgram, PriceStatus.Halted, usdcFeed)
+
+      await assertThrowsAsync(
+        exchange.borrowVault({
+          amount: new BN(1),
+          owner: accountOwner.publicKey,
+          to: userXusdTokenAccount,
+          collateral: usdcCollateralAddress,
+          synthetic: xusdAssetAddress,
+          signers: [accountOwner]
+        }),
+        ERRORS_EXCHANGE.UNRELIABLE_PRICE
+      )
+    })
+    it('deposit should be allowed when price is not trading', async () => {
+      const vaultEntryBefore = await exchange.getVaultEntryForOwner(
+        xusdAssetAddress,
+        usdcCollateralAddress,
+        accountOwner.publicKey
+      )
+      const depositAmount = new BN(10)
+
+      await exchange.vaultDeposit({
+        amount: depositAmount,
+        owner: accountOwner.publicKey,
+        collateral: usdcCollateralAddress,
+        synthetic: xusdAssetAddress,
+        userCollateralAccount: userUsdcTokenAccount,
+        reserveAddress: usdcVaultReserve,
+        collateralToken: usdcToken,
+        signers: [accountOwner]
+      })
+
+      const vaultEntryAfter = await exchange.getVaultEntryForOwner(
+        xusdAssetAddress,
+        usdcCollateralAddress,
+        accountOwner.publicKey
+      )
+      assert.ok(
+        vaultEntryAfter.collateralAmount.val.eq(
+          vaultEntryBefore.collateralAmount.val.add(depositAmount)
+        )
+      )
+
+      // clean up: return status to trading
+      await setFeedTrading(oracleProgram, PriceStatus.Trading, usdcFeed)
+    })
+    it('borrow should failed when price deviates from twap', async () => {
+      const ix = await exchange.setVaultOracleSettingsInstruction(
+        {
+          maxDelay: exchange.state.maxDelay,
+          maxPriceDeviation: percentToDecimal(10),
+          requireTrading: true
+        },
+        { synthetic: xusdAssetAddress, collateral: usdcCollateralAddress }
+      )
+      await signAndSend(new Transaction().add(ix), [EXCHANGE_ADMIN], connection)
+      // (1 - 2) / 2 = 50% deviation
+      await setTwap(oracleProgram, 2, usdcFeed)
+
+      await assertThrowsAsync(
+        exchange.borrowVault({
+          amount: new BN(1),
+          owner: accountOwner.publicKey,
+          to: userXusdTokenAccount,
+          collateral: usdcCollateralAddress,
+          synthetic: xusdAssetAddress,
+          signers: [accountOwner]
+        }),
+        ERRORS_EXCHANGE.UNRELIABLE_PRICE
+      )
+
+      // clean up: return twap to price
+      await setTwap(oracleProgram, 1, usdcFeed)
+    })
+  })
   describe('#setHalted (exchange)', async () => {
     let xusdAssetAddress: PublicKey
     let usdcCollateralAddress: PublicKey